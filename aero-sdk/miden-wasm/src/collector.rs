@@ -0,0 +1,137 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use crate::error::ProverError;
+
+/// Aborts whichever collection the proving task is waiting on.
+///
+/// Worker callbacks raise it when a worker fails and `MidenProver::cancel` when the caller gives
+/// up; only the first error is kept since later ones are usually a consequence of it.
+#[derive(Clone, Default)]
+pub struct Abort {
+    state: Rc<RefCell<AbortState>>,
+}
+
+#[derive(Default)]
+struct AbortState {
+    error: Option<ProverError>,
+    waker: Option<Waker>,
+}
+
+impl Abort {
+    pub fn abort(&self, err: ProverError) {
+        let waker = {
+            let mut state = self.state.borrow_mut();
+            state.error.get_or_insert(err);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Takes the pending error, leaving the abort cleared for the next proof
+    pub fn take(&self) -> Option<ProverError> {
+        self.state.borrow_mut().error.take()
+    }
+
+    fn register(&self, waker: &Waker) {
+        self.state.borrow_mut().waker = Some(waker.clone());
+    }
+}
+
+/// Results posted back by workers, collected in arrival order.
+///
+/// The `onmessage` callbacks push into a clone of the collector and wake the task awaiting
+/// [`Collector::collect`] directly, so a stage ends as soon as its last result arrives.
+pub struct Collector<T> {
+    state: Rc<RefCell<CollectorState<T>>>,
+    abort: Abort,
+}
+
+struct CollectorState<T> {
+    results: Vec<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> Clone for Collector<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            abort: self.abort.clone(),
+        }
+    }
+}
+
+impl<T> Collector<T> {
+    pub fn new(abort: Abort) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(CollectorState {
+                results: Vec::new(),
+                waker: None,
+            })),
+            abort,
+        }
+    }
+
+    /// Stores a result and returns the number of results collected so far
+    pub fn push(&self, result: T) -> usize {
+        let (len, waker) = {
+            let mut state = self.state.borrow_mut();
+            state.results.push(result);
+            (state.results.len(), state.waker.take())
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        len
+    }
+
+    pub fn abort(&self, err: ProverError) {
+        self.abort.abort(err);
+    }
+
+    /// Drops the results of a previous run
+    pub fn clear(&self) {
+        self.state.borrow_mut().results.clear();
+    }
+
+    pub fn take(&self) -> Vec<T> {
+        std::mem::take(&mut self.state.borrow_mut().results)
+    }
+
+    /// Resolves once `expected` results were collected, or with the abort error
+    pub fn collect(&self, expected: usize) -> Collect<T> {
+        Collect {
+            collector: self.clone(),
+            expected,
+        }
+    }
+}
+
+pub struct Collect<T> {
+    collector: Collector<T>,
+    expected: usize,
+}
+
+impl<T> Future for Collect<T> {
+    type Output = Result<(), ProverError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(err) = self.collector.abort.take() {
+            return Poll::Ready(Err(err));
+        }
+        let mut state = self.collector.state.borrow_mut();
+        if state.results.len() >= self.expected {
+            return Poll::Ready(Ok(()));
+        }
+        state.waker = Some(cx.waker().clone());
+        self.collector.abort.register(cx.waker());
+        Poll::Pending
+    }
+}
@@ -1,98 +1,439 @@
+use crate::error::ProverError;
+use crate::shared::SharedMatrixView;
 use crate::utils::{
-    from_uint8array, set_once_logger, to_uint8array, ConstraintComputeResult,
-    ConstraintComputeWorkItem, FeltWrapper, TraceLdeWrapper,
+    elements_from_base, to_base_elements, unwrap_elements, ComputationFragment,
+    ConstraintComputeResult, ConstraintComputeWorkItem, FeltWrapper, TraceLdeRows, TraceLdeWindow,
 };
-use js_sys::Uint8Array;
 use log::debug;
 use miden_air::ProcessorAir;
-use miden_core::{Felt, FieldElement};
-use wasm_bindgen::prelude::*;
-use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
-use winter_air::Air;
-use winter_prover::{ConstraintEvaluationTable, ConstraintEvaluator, StarkDomain};
+use miden_core::{Felt, FieldElement, StarkField};
+use miden_proof_format::{field::FieldExtension, with_field_extension};
+use winter_air::{Air, AuxTraceRandElements, ConstraintCompositionCoefficients, EvaluationFrame};
+use winter_math::polynom;
 
-pub fn constraint_compute(work_item: &ConstraintComputeWorkItem) -> Result<Uint8Array, JsValue> {
+pub fn constraint_compute(
+    work_item: &ConstraintComputeWorkItem,
+) -> Result<ConstraintComputeResult, ProverError> {
+    let result = with_field_extension!(FieldExtension::from(work_item.field_extension), |E| {
+        constraint_compute_in::<E>(work_item)
+    })?;
+    debug!(
+        "done processing constraints for batch {}",
+        work_item.computation_fragment.fragment_offset
+    );
+    Ok(result)
+}
+
+fn constraint_compute_in<E: FieldElement<BaseField = Felt>>(
+    work_item: &ConstraintComputeWorkItem,
+) -> Result<ConstraintComputeResult, ProverError> {
     let air = ProcessorAir::new(
         work_item.trace_info.clone(),
         work_item.public_inputs.clone(),
         work_item.proof_options.clone(),
     );
-    // 2 ----- evaluate constraints -----------------------------------------------------------
-    // evaluate constraints specified by the AIR over the constraint evaluation domain, and
-    // compute random linear combinations of these evaluations using coefficients drawn from
-    // the channel; this step evaluates only constraint numerators, thus, only constraints with
-    // identical denominators are merged together. the results are saved into a constraint
-    // evaluation table where each column contains merged evaluations of constraints with
-    // identical denominators.
-    let constraint_coeffs = work_item.constraint_coeffs.clone();
-    let aux_trace_rand_elements = work_item.aux_rand_elements.clone();
-    // build a list of constraint divisors; currently, all transition constraints have the same
-    // divisor which we put at the front of the list; boundary constraint divisors are appended
-    // after that
-    let evaluator: ConstraintEvaluator<_, Felt> =
-        ConstraintEvaluator::new(&air, aux_trace_rand_elements, constraint_coeffs);
-    // build a list of constraint divisors; currently, all transition constraints have the same
-    // divisor which we put at the front of the list; boundary constraint divisors are appended
-    // after that
-    let mut divisors = vec![evaluator.transition_constraints.divisor().clone()];
-    divisors.append(&mut evaluator.boundary_constraints.get_divisors());
-
-    let domain = StarkDomain::new(&air);
-    let trace_lde_wrapper: TraceLdeWrapper = bincode::deserialize(&work_item.trace_lde_wrapper)
-        .map_err(|e| JsValue::from_str(&format!("cannot deser traceLdeWrapper: {}", e)))?;
-    let trace_table = &trace_lde_wrapper.trace_lde;
-
-    // allocate space for constraint evaluations; when we are in debug mode, we also allocate
-    // memory to hold all transition constraint evaluations (before they are merged into a
-    // single value) so that we can check their degrees later
-    #[cfg(not(debug_assertions))]
-    let mut evaluation_table = ConstraintEvaluationTable::<Felt>::new(&domain, divisors);
-    #[cfg(debug_assertions)]
-    let mut evaluation_table = ConstraintEvaluationTable::<Felt>::new(
-        &domain,
-        divisors,
-        &evaluator.transition_constraints,
-    );
+    let aux_rand_elements = work_item
+        .aux_rand_elements
+        .iter()
+        .map(|elements| elements_from_base::<E>(&unwrap_elements(elements)))
+        .collect::<Result<_, _>>()?;
+    let evaluations = evaluate_fragment(
+        &air,
+        &AuxTraceRandElements(aux_rand_elements),
+        &work_item.constraint_coeffs.decode::<E>()?,
+        &work_item.trace_rows,
+        &work_item.computation_fragment,
+    )?;
     let frag_num = work_item.computation_fragment.num_fragments;
-    let mut fragments = evaluation_table.fragments(frag_num);
-    let frag = &mut fragments[work_item.computation_fragment.fragment_offset];
-    evaluator.evaluate_fragment(&trace_table, &domain, frag);
-    debug!(
-        "done processing constraints for batch {}",
-        work_item.computation_fragment.fragment_offset
-    );
+    let frag_index = work_item.computation_fragment.fragment_offset * evaluations.len();
 
-    let mut evaluations = vec![vec![FeltWrapper(Felt::ZERO); frag.num_rows()]; frag.num_columns()];
-    for i in 0..frag.num_columns() {
-        for j in 0..frag.num_rows() {
-            evaluations[i][j] = FeltWrapper(frag.evaluations[i][j]);
-        }
+    // with a shared evaluation table only the fragment position is posted back
+    if let Some(evaluation_table) = &work_item.evaluation_table {
+        evaluation_table
+            .view()?
+            .write_elements(frag_index, &evaluations)?;
+        return Ok(ConstraintComputeResult {
+            frag_index,
+            frag_num,
+            evaluations: vec![],
+        });
     }
 
-    let response = ConstraintComputeResult {
-        frag_index: frag.offset(),
+    Ok(ConstraintComputeResult {
+        frag_index,
         frag_num,
-        constraint_evaluations: evaluations,
-    };
+        evaluations: to_base_elements(&evaluations),
+    })
+}
+
+/// Evaluates the constraints over one fragment of the constraint evaluation domain, reading the
+/// frames from the trace LDE rows of its window only, in place when they are shared.
+///
+/// Instead of a column of numerators per divisor, as `ConstraintEvaluator` fills, the fragment
+/// holds a single column: the composition polynomial, computed the way the verifier computes it
+/// at the out-of-domain point, multiplied by the transition divisor. Dividing it by the
+/// transition divisor gives the same composition polynomial, so the table only needs that
+/// divisor and every value allocated here belongs to the fragment.
+pub fn evaluate_fragment<A, E>(
+    air: &A,
+    aux_rand_elements: &AuxTraceRandElements<E>,
+    coefficients: &ConstraintCompositionCoefficients<E>,
+    trace_rows: &TraceLdeRows,
+    fragment: &ComputationFragment,
+) -> Result<Vec<E>, ProverError>
+where
+    A: Air<BaseField = Felt>,
+    E: FieldElement<BaseField = Felt>,
+{
+    let ce_domain_size = air.ce_domain_size();
+    let num_rows = ce_domain_size / fragment.num_fragments;
+    let offset = fragment.fragment_offset * num_rows;
+    let lde_shift = (air.lde_domain_size() / ce_domain_size).trailing_zeros();
+    let frames = FrameReader::<E>::new(trace_rows)?;
+    let last_row = ((num_rows - 1) << lde_shift) + frames.blowup;
+    if last_row >= frames.num_rows {
+        return Err(ProverError::Worker(format!(
+            "trace LDE window of {} rows does not cover fragment {} of {}",
+            frames.num_rows, fragment.fragment_offset, fragment.num_fragments
+        )));
+    }
+
+    let t_constraints = air.get_transition_constraints(&coefficients.transition);
+    let b_constraints = air.get_boundary_constraints(aux_rand_elements, &coefficients.boundary);
+    let periodic_polys = air.get_periodic_column_polys();
+    let mut main_frame = EvaluationFrame::<Felt>::new(frames.main_width);
+    let mut aux_frame = EvaluationFrame::<E>::new(frames.aux_width);
+    let mut periodic_values = vec![Felt::ZERO; periodic_polys.len()];
+    let mut main_evaluations = vec![Felt::ZERO; t_constraints.num_main_constraints()];
+    let mut aux_evaluations = E::zeroed_vector(t_constraints.num_aux_constraints());
+    let mut main_state = E::zeroed_vector(frames.main_width);
+
+    // x runs over the constraint evaluation domain from the first step of the fragment
+    let g = Felt::get_root_of_unity(ce_domain_size.trailing_zeros());
+    let mut x = air.domain_offset() * g.exp(offset as u64);
+    let mut evaluations = Vec::with_capacity(num_rows);
+    for i in 0..num_rows {
+        frames.read_frames(i << lde_shift, &mut main_frame, &mut aux_frame)?;
+
+        // transition constraints, periodic columns are evaluated at x
+        for (value, poly) in periodic_values.iter_mut().zip(periodic_polys.iter()) {
+            let num_cycles = air.trace_length() / poly.len();
+            *value = polynom::eval(poly, x.exp(num_cycles as u64));
+        }
+        air.evaluate_transition(&main_frame, &periodic_values, &mut main_evaluations);
+        if frames.aux_width > 0 {
+            air.evaluate_aux_transition(
+                &main_frame,
+                &aux_frame,
+                &periodic_values,
+                aux_rand_elements,
+                &mut aux_evaluations,
+            );
+        }
+        let mut result =
+            t_constraints.combine_evaluations::<Felt>(&main_evaluations, &aux_evaluations, x);
+
+        // boundary constraints, grouped by divisor and degree adjustment
+        let xe = E::from(x);
+        for (state, element) in main_state.iter_mut().zip(main_frame.current()) {
+            *state = E::from(*element);
+        }
+        for group in b_constraints.main_constraints() {
+            let xp = xe.exp(group.degree_adjustment().into());
+            result += group.evaluate_at(&main_state, xe, xp);
+        }
+        for group in b_constraints.aux_constraints() {
+            let xp = xe.exp(group.degree_adjustment().into());
+            result += group.evaluate_at(aux_frame.current(), xe, xp);
+        }
+
+        evaluations.push(result * E::from(t_constraints.divisor().evaluate_at(x)));
+        x *= g;
+    }
+    Ok(evaluations)
+}
+
+/// Evaluation frames read from the rows of a trace LDE window
+struct FrameReader<E> {
+    blowup: usize,
+    num_rows: usize,
+    main_width: usize,
+    aux_width: usize,
+    rows: FrameRows<E>,
+}
 
-    Ok(to_uint8array(&response))
+enum FrameRows<E> {
+    /// Rows copied into the work item, row major
+    Copied {
+        main_rows: Vec<Felt>,
+        aux_rows: Vec<E>,
+    },
+    /// Views of the shared main and auxiliary segments, the window starts at `first_row`
+    Shared {
+        first_row: usize,
+        main: SharedMatrixView,
+        aux: Vec<SharedMatrixView>,
+    },
+}
+
+impl<E: FieldElement<BaseField = Felt>> FrameReader<E> {
+    fn new(trace_rows: &TraceLdeRows) -> Result<Self, ProverError> {
+        match trace_rows {
+            TraceLdeRows::Copied(window) => Self::copied(window),
+            TraceLdeRows::Shared {
+                segments,
+                blowup,
+                first_row,
+                num_rows,
+            } => {
+                let mut views = segments
+                    .iter()
+                    .map(|segment| segment.view())
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter();
+                let main = views.next().ok_or_else(|| {
+                    ProverError::Worker("shared trace LDE has no main segment".to_string())
+                })?;
+                let aux: Vec<_> = views.collect();
+                if *num_rows > main.num_rows()
+                    || aux.iter().any(|view| {
+                        view.num_rows() != main.num_rows()
+                            || view.num_cols() % E::EXTENSION_DEGREE != 0
+                    })
+                {
+                    return Err(ProverError::Worker(format!(
+                        "trace LDE window of {} rows does not fit the shared trace LDE segments",
+                        num_rows
+                    )));
+                }
+                Ok(Self {
+                    blowup: *blowup,
+                    num_rows: *num_rows,
+                    main_width: main.num_cols(),
+                    aux_width: aux.iter().map(SharedMatrixView::num_cols).sum::<usize>()
+                        / E::EXTENSION_DEGREE,
+                    rows: FrameRows::Shared {
+                        first_row: *first_row,
+                        main,
+                        aux,
+                    },
+                })
+            }
+        }
+    }
+
+    fn copied(window: &TraceLdeWindow) -> Result<Self, ProverError> {
+        let width = |rows: &[FeltWrapper]| {
+            if window.num_rows == 0 || rows.len() % window.num_rows != 0 {
+                return Err(ProverError::Worker(format!(
+                    "trace LDE window of {} elements does not split into {} rows",
+                    rows.len(),
+                    window.num_rows
+                )));
+            }
+            Ok(rows.len() / window.num_rows)
+        };
+        // auxiliary rows are in base field coordinates
+        let aux_rows = elements_from_base::<E>(&unwrap_elements(&window.aux_rows))?;
+        Ok(Self {
+            blowup: window.blowup,
+            num_rows: window.num_rows,
+            main_width: width(&window.main_rows)?,
+            aux_width: width(&window.aux_rows)? / E::EXTENSION_DEGREE,
+            rows: FrameRows::Copied {
+                main_rows: unwrap_elements(&window.main_rows),
+                aux_rows,
+            },
+        })
+    }
+
+    /// Reads the frame of the LDE row `row` of the window, the next row is `blowup` rows further
+    fn read_frames(
+        &self,
+        row: usize,
+        main_frame: &mut EvaluationFrame<Felt>,
+        aux_frame: &mut EvaluationFrame<E>,
+    ) -> Result<(), ProverError> {
+        let next = row + self.blowup;
+        match &self.rows {
+            FrameRows::Copied {
+                main_rows,
+                aux_rows,
+            } => {
+                let main =
+                    |row: usize| &main_rows[row * self.main_width..(row + 1) * self.main_width];
+                main_frame.current_mut().copy_from_slice(main(row));
+                main_frame.next_mut().copy_from_slice(main(next));
+                let aux = |row: usize| &aux_rows[row * self.aux_width..(row + 1) * self.aux_width];
+                aux_frame.current_mut().copy_from_slice(aux(row));
+                aux_frame.next_mut().copy_from_slice(aux(next));
+                Ok(())
+            }
+            FrameRows::Shared {
+                first_row,
+                main,
+                aux,
+            } => {
+                // the window wraps around the end of the LDE
+                let lde_row = |row: usize| (first_row + row) % main.num_rows();
+                main.read_row_into(lde_row(row), main_frame.current_mut());
+                main.read_row_into(lde_row(next), main_frame.next_mut());
+                // each segment stores its elements in base field coordinates
+                let read_aux = |row: usize, mut values: &mut [E]| -> Result<(), ProverError> {
+                    for segment in aux.iter() {
+                        let (segment_values, rest) = std::mem::take(&mut values)
+                            .split_at_mut(segment.num_cols() / E::EXTENSION_DEGREE);
+                        segment.read_row_elements(lde_row(row), segment_values)?;
+                        values = rest;
+                    }
+                    Ok(())
+                };
+                read_aux(row, aux_frame.current_mut())?;
+                read_aux(next, aux_frame.next_mut())
+            }
+        }
+    }
 }
 
-#[wasm_bindgen]
-pub fn constraint_entry_point(msg: MessageEvent) -> Result<(), JsValue> {
-    set_once_logger();
-    if let Ok(work_item) =
-        from_uint8array::<ConstraintComputeWorkItem>(&Uint8Array::new(&msg.data()))
-    {
-        debug!(
-            "Constraint worker received work item: {:?}",
-            work_item.computation_fragment.fragment_offset
+#[cfg(test)]
+mod constraints_test {
+    use super::*;
+    use crate::utils::{field_extension_of, CompositionCoefficients};
+    use miden::{Assembler, ProgramInputs};
+    use miden_air::PublicInputs;
+    use miden_proof_format::field::QuadExtension;
+    use winter_crypto::hashers::Blake3_256;
+    use winter_prover::{
+        ConstraintEvaluationTable, ConstraintEvaluator, ProverChannel, StarkDomain, Trace, TraceLde,
+    };
+
+    fn check_fragments<E: FieldElement<BaseField = Felt>>() {
+        let program = Assembler::new()
+            .compile("begin repeat.20 swap dup.1 add end end")
+            .unwrap();
+        let inputs = ProgramInputs::new(&[0, 1], &[], vec![]).unwrap();
+        let mut trace = miden_processor::execute(&program, &inputs).unwrap();
+        let pub_inputs = PublicInputs::new(
+            program.hash(),
+            inputs.stack_init().to_vec(),
+            trace.program_outputs().clone(),
         );
-        let response = constraint_compute(&work_item)?;
-        let global_scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
-        global_scope.post_message(&response)?;
-    } else {
-        debug!("Constraint worker received invalid work item");
+        let proof_options = miden::ProofOptions::with_96_bit_security().0;
+        let air = ProcessorAir::new(trace.get_info(), pub_inputs.clone(), proof_options.clone());
+        let mut channel =
+            ProverChannel::<ProcessorAir, E, Blake3_256<Felt>>::new(air.clone(), vec![]);
+
+        // the trace LDE the proving worker commits to
+        let domain = StarkDomain::new(&air);
+        let main_lde = trace
+            .main_segment()
+            .interpolate_columns()
+            .evaluate_columns_over(&domain);
+        let mut trace_lde = TraceLde::<E>::new(main_lde, domain.trace_to_lde_blowup());
+        let mut aux_segments = Vec::new();
+        let mut aux_rand_elements = AuxTraceRandElements::new();
+        for i in 0..trace.layout().num_aux_segments() {
+            let rand_elements = channel.get_aux_trace_segment_rand_elements(i);
+            let aux_segment = trace
+                .build_aux_segment(&aux_segments, &rand_elements)
+                .unwrap();
+            trace_lde.add_aux_segment(
+                aux_segment
+                    .interpolate_columns()
+                    .evaluate_columns_over(&domain),
+            );
+            aux_rand_elements.add_segment_elements(rand_elements);
+            aux_segments.push(aux_segment);
+        }
+        let coefficients = channel.get_constraint_composition_coeffs();
+
+        // the numerators winterfell evaluates over the whole domain, one column per divisor
+        let evaluator =
+            ConstraintEvaluator::new(&air, aux_rand_elements.clone(), coefficients.clone());
+        let mut divisors = vec![evaluator.transition_constraints.divisor().clone()];
+        divisors.append(&mut evaluator.boundary_constraints.get_divisors());
+        #[cfg(not(debug_assertions))]
+        let mut table = ConstraintEvaluationTable::<E>::new(&domain, divisors.clone());
+        #[cfg(debug_assertions)]
+        let mut table = ConstraintEvaluationTable::<E>::new(
+            &domain,
+            divisors.clone(),
+            &evaluator.transition_constraints,
+        );
+        let mut fragments = table.fragments(1);
+        evaluator.evaluate_fragment(&trace_lde, &domain, &mut fragments[0]);
+        let full = &fragments[0];
+
+        let num_fragments = 4;
+        let lde_size = air.lde_domain_size();
+        let ce_domain_size = air.ce_domain_size();
+        let fragment_rows = ce_domain_size / num_fragments;
+        let lde_shift = (lde_size / ce_domain_size).trailing_zeros();
+        let window_rows = (fragment_rows << lde_shift) + trace_lde.blowup;
+        let g = Felt::get_root_of_unity(ce_domain_size.trailing_zeros());
+        for fragment_offset in 0..num_fragments {
+            let fragment = ComputationFragment {
+                fragment_offset,
+                num_fragments,
+            };
+            let first_row = (fragment_offset * fragment_rows) << lde_shift;
+            let window = TraceLdeWindow::new(&trace_lde, first_row, window_rows);
+
+            // a worker only allocates its share of the LDE and of the evaluation domain
+            assert_eq!(window.num_rows, lde_size / num_fragments + trace_lde.blowup);
+            assert_eq!(
+                window.main_rows.len(),
+                window.num_rows * trace_lde.main_segment_lde.num_cols()
+            );
+            let trace_rows = TraceLdeRows::Copied(window);
+            let evaluations = evaluate_fragment(
+                &air,
+                &aux_rand_elements,
+                &coefficients,
+                &trace_rows,
+                &fragment,
+            )
+            .unwrap();
+            assert_eq!(evaluations.len(), ce_domain_size / num_fragments);
+
+            // both divide into the same composition polynomial
+            for (i, evaluation) in evaluations.iter().enumerate() {
+                let step = fragment_offset * fragment_rows + i;
+                let x = domain.offset() * g.exp(step as u64);
+                let expected = divisors
+                    .iter()
+                    .enumerate()
+                    .fold(E::ZERO, |acc, (col, divisor)| {
+                        acc + full.evaluations[col][step] / E::from(divisor.evaluate_at(x))
+                    });
+                assert_eq!(*evaluation / E::from(divisors[0].evaluate_at(x)), expected);
+            }
+
+            // a pool worker gets the same evaluations from the job posted for the fragment
+            let work_item = ConstraintComputeWorkItem {
+                trace_info: air.trace_info().clone(),
+                public_inputs: pub_inputs.clone(),
+                proof_options: proof_options.clone(),
+                field_extension: field_extension_of::<E>(),
+                aux_rand_elements: aux_rand_elements
+                    .0
+                    .iter()
+                    .map(|elements| to_base_elements(elements))
+                    .collect(),
+                constraint_coeffs: CompositionCoefficients::new(&coefficients),
+                trace_rows,
+                evaluation_table: None,
+                computation_fragment: fragment,
+            };
+            let result = constraint_compute(&work_item).unwrap();
+            assert_eq!(result.frag_index, fragment_offset * fragment_rows);
+            assert_eq!(result.evaluations, to_base_elements(&evaluations));
+        }
+    }
+
+    #[test]
+    fn test_fragments_match_constraint_evaluator() {
+        check_fragments::<Felt>();
+        check_fragments::<QuadExtension<Felt>>();
     }
-    Ok(())
 }
@@ -1,3 +0,0 @@
-pub mod convert_inputs;
-pub mod convert_proof;
-pub mod sdk;
@@ -1,3 +0,0 @@
-pub mod sdk {
-    include!(concat!(env!("OUT_DIR"), "/sdk.rs"));
-}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use js_sys::{Object, Reflect};
+use miden_proof_format::convert::SdkConversionError;
+use wasm_bindgen::JsValue;
+
+/// Errors raised while proving, reported to JS as `{ code, message }` objects
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ProverError {
+    /// A message passed to the prover could not be decoded
+    Decode(String),
+    /// A prover argument is out of range, e.g. a zero hashing chunk size
+    InvalidInput(String),
+    /// The MASM program could not be assembled
+    Assembly(String),
+    /// The program failed to execute against its inputs
+    Execution(String),
+    /// The execution trace could not be proven
+    Proving(String),
+    /// The generated proof did not verify
+    Verification(String),
+    /// A web worker could not be spawned, messaged or returned a malformed result
+    Worker(String),
+    /// The proof was cancelled by the caller
+    Cancelled,
+}
+
+impl ProverError {
+    /// Stable identifier of the error kind for the SDK to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProverError::Decode(_) => "DECODE_ERROR",
+            ProverError::InvalidInput(_) => "INVALID_INPUT",
+            ProverError::Assembly(_) => "ASSEMBLY_ERROR",
+            ProverError::Execution(_) => "EXECUTION_ERROR",
+            ProverError::Proving(_) => "PROVING_ERROR",
+            ProverError::Verification(_) => "VERIFICATION_ERROR",
+            ProverError::Worker(_) => "WORKER_ERROR",
+            ProverError::Cancelled => "CANCELLED",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ProverError::Decode(msg)
+            | ProverError::InvalidInput(msg)
+            | ProverError::Assembly(msg)
+            | ProverError::Execution(msg)
+            | ProverError::Proving(msg)
+            | ProverError::Verification(msg)
+            | ProverError::Worker(msg) => msg,
+            ProverError::Cancelled => "proving was cancelled",
+        }
+    }
+}
+
+impl fmt::Display for ProverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+impl From<SdkConversionError> for ProverError {
+    fn from(err: SdkConversionError) -> Self {
+        match err {
+            SdkConversionError::Assembly(msg) => ProverError::Assembly(msg),
+            SdkConversionError::InvalidInputs(msg) => ProverError::InvalidInput(msg),
+            err => ProverError::Decode(err.to_string()),
+        }
+    }
+}
+
+// every JS exception raised inside the prover comes from posting to or spawning a worker
+impl From<JsValue> for ProverError {
+    fn from(value: JsValue) -> Self {
+        ProverError::Worker(value.as_string().unwrap_or_else(|| format!("{:?}", value)))
+    }
+}
+
+impl From<ProverError> for JsValue {
+    fn from(err: ProverError) -> Self {
+        let object = Object::new();
+        // setting properties on a fresh object cannot fail
+        let _ = Reflect::set(&object, &"code".into(), &err.code().into());
+        let _ = Reflect::set(&object, &"message".into(), &err.message().into());
+        object.into()
+    }
+}
+
+#[cfg(test)]
+mod error_test {
+    use miden_proof_format::sdk;
+    use prost::Message;
+
+    use super::*;
+    use crate::utils::{ProvingWorkItem, WorkPartition};
+
+    fn work_item(program: Vec<u8>, program_inputs: sdk::MidenProgramInputs) -> ProvingWorkItem {
+        let options = miden::ProofOptions::with_96_bit_security();
+        ProvingWorkItem {
+            program,
+            program_inputs: program_inputs.encode_to_vec(),
+            proof_options: sdk::ProofOptions::from(&options.0).encode_to_vec(),
+            partition: WorkPartition::default(),
+            is_sequential: false,
+        }
+    }
+
+    fn program(source: &str) -> Vec<u8> {
+        sdk::MidenProgram {
+            program: source.to_string(),
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_invalid_masm_is_an_assembly_error() {
+        let item = work_item(
+            program("begin\n    push.1\n    not_an_instruction\nend"),
+            sdk::MidenProgramInputs::default(),
+        );
+        let err = item.decode().err().unwrap();
+        assert_eq!(err.code(), "ASSEMBLY_ERROR");
+        // the assembler message points at the offending instruction
+        assert!(err.message().contains("not_an_instruction"), "{}", err);
+    }
+
+    #[test]
+    fn test_undecodable_program_is_a_decode_error() {
+        let item = work_item(vec![0xff; 16], sdk::MidenProgramInputs::default());
+        assert_eq!(item.decode().err().unwrap().code(), "DECODE_ERROR");
+    }
+
+    #[test]
+    fn test_rejected_inputs_are_an_input_error() {
+        let inputs = sdk::MidenProgramInputs {
+            advice_map: vec![sdk::AdviceMapEntry {
+                key: vec![0; 31],
+                values: vec![1],
+            }],
+            ..Default::default()
+        };
+        let item = work_item(program("begin push.1 end"), inputs);
+        assert_eq!(item.decode().err().unwrap().code(), "INVALID_INPUT");
+    }
+
+    #[test]
+    fn test_sdk_conversion_errors() {
+        let msg = || "msg".to_string();
+        assert_eq!(
+            ProverError::from(SdkConversionError::InvalidInputs(msg())),
+            ProverError::InvalidInput(msg())
+        );
+        assert_eq!(
+            ProverError::from(SdkConversionError::Assembly(msg())),
+            ProverError::Assembly(msg())
+        );
+        assert_eq!(
+            ProverError::from(SdkConversionError::InvalidFieldElement).code(),
+            "DECODE_ERROR"
+        );
+    }
+}
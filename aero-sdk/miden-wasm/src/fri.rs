@@ -0,0 +1,308 @@
+use crate::error::ProverError;
+use crate::utils::{
+    elements_from_base, to_base_elements, unwrap_elements, FriFoldResult, FriFoldWorkItem, RowBatch,
+};
+use log::debug;
+use miden_core::{Felt, FieldElement, StarkField};
+use miden_proof_format::{field::FieldExtension, with_field_extension};
+use winter_crypto::{ElementHasher, MerkleTree};
+use winter_fri::{folding::fold_positions, FriProof, FriProofLayer};
+use winter_math::{fft, polynom};
+use winter_utils::group_slice_elements;
+
+/// A committed FRI layer, the same as the one `FriProver` keeps internally.
+///
+/// The layers are built by the proving worker so that hashing their Merkle leaves can be
+/// dispatched to the worker pool; `FriProver` hashes them on the calling thread.
+pub struct FriLayer<H: ElementHasher<BaseField = Felt>, E: FieldElement<BaseField = Felt>> {
+    pub tree: MerkleTree<H>,
+    /// Layer evaluations in transposed form, each leaf holds `folding_factor` values
+    pub evaluations: Vec<E>,
+}
+
+/// Queries the layers at `positions` the way `FriProver::build_proof` does
+pub fn build_fri_proof<H, E>(
+    layers: &[FriLayer<H, E>],
+    positions: &[usize],
+    folding_factor: usize,
+) -> Result<FriProof, ProverError>
+where
+    H: ElementHasher<BaseField = Felt>,
+    E: FieldElement<BaseField = Felt>,
+{
+    match folding_factor {
+        4 => build_fri_proof_with::<H, E, 4>(layers, positions),
+        8 => build_fri_proof_with::<H, E, 8>(layers, positions),
+        16 => build_fri_proof_with::<H, E, 16>(layers, positions),
+        factor => Err(unsupported_folding_factor(factor)),
+    }
+}
+
+/// Folds the rows of a FRI layer posted to a pool worker
+pub fn fold_fri_layer(work_item: &FriFoldWorkItem) -> Result<FriFoldResult, ProverError> {
+    let folding_factor = work_item.folding_factor;
+    if !matches!(folding_factor, 4 | 8 | 16) {
+        return Err(unsupported_folding_factor(folding_factor));
+    }
+    let result = with_field_extension!(FieldExtension::from(work_item.field_extension), |E| {
+        fold_fri_layer_in::<E>(work_item)
+    })?;
+    debug!(
+        "done folding FRI layer rows from row {}",
+        work_item.first_row
+    );
+    Ok(result)
+}
+
+fn fold_fri_layer_in<E: FieldElement<BaseField = Felt>>(
+    work_item: &FriFoldWorkItem,
+) -> Result<FriFoldResult, ProverError> {
+    let folding_factor = work_item.folding_factor;
+    let (first_row, layer_rows) = (work_item.first_row, work_item.layer_rows);
+    let domain_offset = work_item.domain_offset.0;
+    let alpha = match elements_from_base::<E>(&unwrap_elements(&work_item.alpha))?[..] {
+        [alpha] => alpha,
+        _ => {
+            return Err(ProverError::Worker(format!(
+                "FRI folding coefficient of {} base field elements is not a single element",
+                work_item.alpha.len()
+            )))
+        }
+    };
+    let evaluations = match &work_item.rows {
+        RowBatch::Copied(rows) => {
+            let rows: Vec<Felt> = rows.iter().flatten().map(|e| e.0).collect();
+            let rows = elements_from_base::<E>(&rows)?;
+            if rows.len() % folding_factor != 0 {
+                return Err(ProverError::Worker(format!(
+                    "FRI layer rows of {} elements do not split into rows of {}",
+                    rows.len(),
+                    folding_factor
+                )));
+            }
+            fold_rows(
+                &rows,
+                folding_factor,
+                first_row,
+                layer_rows,
+                domain_offset,
+                alpha,
+            )?
+        }
+        RowBatch::Shared {
+            matrix,
+            first_row,
+            num_rows,
+        } => {
+            // each row is read from the shared layer straight into the interpolation buffer
+            let view = matrix.view()?;
+            view.check_rows(*first_row, *num_rows)?;
+            if view.num_cols() != folding_factor * E::EXTENSION_DEGREE {
+                return Err(ProverError::Worker(format!(
+                    "shared FRI layer of {} columns is not folded by {}",
+                    view.num_cols(),
+                    folding_factor
+                )));
+            }
+            fold_rows_with(
+                *num_rows,
+                folding_factor,
+                *first_row,
+                layer_rows,
+                domain_offset,
+                alpha,
+                |row_idx, row| view.read_row_elements(*first_row + row_idx, row),
+            )?
+        }
+    };
+
+    // with a shared next layer only the row range is posted back
+    if let Some(next_layer) = &work_item.next_layer {
+        next_layer.view()?.write_elements(first_row, &evaluations)?;
+        return Ok(FriFoldResult {
+            first_row,
+            evaluations: vec![],
+        });
+    }
+
+    Ok(FriFoldResult {
+        first_row,
+        evaluations: to_base_elements(&evaluations),
+    })
+}
+
+/// Folds rows `[first_row, first_row + rows.len() / folding_factor)` of a transposed FRI layer
+/// of `layer_rows` rows into the next layer, the same values `apply_drp` computes for them when
+/// folding the whole layer.
+///
+/// Row `i` holds the evaluations over the coset of `x_i = domain_offset * g^i`, with `g`
+/// generating the layer domain. They are interpolated into a polynomial of degree
+/// `folding_factor - 1` which is evaluated at `alpha / x_i`.
+pub fn fold_rows<E: FieldElement<BaseField = Felt>>(
+    rows: &[E],
+    folding_factor: usize,
+    first_row: usize,
+    layer_rows: usize,
+    domain_offset: Felt,
+    alpha: E,
+) -> Result<Vec<E>, ProverError> {
+    fold_rows_with(
+        rows.len() / folding_factor,
+        folding_factor,
+        first_row,
+        layer_rows,
+        domain_offset,
+        alpha,
+        |row_idx, row| {
+            row.copy_from_slice(&rows[row_idx * folding_factor..(row_idx + 1) * folding_factor]);
+            Ok(())
+        },
+    )
+}
+
+/// Folds `num_rows` rows from `first_row` on like [`fold_rows`], reading row `first_row + i`
+/// with `read_row(i, row)`
+fn fold_rows_with<E: FieldElement<BaseField = Felt>>(
+    num_rows: usize,
+    folding_factor: usize,
+    first_row: usize,
+    layer_rows: usize,
+    domain_offset: Felt,
+    alpha: E,
+    mut read_row: impl FnMut(usize, &mut [E]) -> Result<(), ProverError>,
+) -> Result<Vec<E>, ProverError> {
+    let inv_twiddles = fft::get_inv_twiddles::<Felt>(folding_factor);
+    let g = Felt::get_root_of_unity((layer_rows * folding_factor).trailing_zeros());
+    let inv_g = g.inv();
+    let mut inv_x = (domain_offset * g.exp(first_row as u64)).inv();
+    let mut poly = E::zeroed_vector(folding_factor);
+    (0..num_rows)
+        .map(|row_idx| {
+            read_row(row_idx, &mut poly)?;
+            fft::interpolate_poly(&mut poly, &inv_twiddles);
+            let folded = polynom::eval(&poly, alpha * E::from(inv_x));
+            inv_x *= inv_g;
+            Ok(folded)
+        })
+        .collect()
+}
+
+pub fn unsupported_folding_factor(folding_factor: usize) -> ProverError {
+    ProverError::Proving(format!(
+        "FRI folding factor {} is not supported",
+        folding_factor
+    ))
+}
+
+fn build_fri_proof_with<H, E, const N: usize>(
+    layers: &[FriLayer<H, E>],
+    positions: &[usize],
+) -> Result<FriProof, ProverError>
+where
+    H: ElementHasher<BaseField = Felt>,
+    E: FieldElement<BaseField = Felt>,
+{
+    let (last_layer, layers) = layers
+        .split_last()
+        .ok_or_else(|| ProverError::Proving("FRI layers have not been built".to_string()))?;
+    let mut positions = positions.to_vec();
+    let mut domain_size = layers.first().unwrap_or(last_layer).evaluations.len();
+
+    // every layer but the last one is queried at the folded positions
+    let mut proof_layers = Vec::with_capacity(layers.len());
+    for layer in layers {
+        positions = fold_positions(&positions, domain_size, N);
+        let proof = layer.tree.prove_batch(&positions).map_err(|e| {
+            ProverError::Proving(format!("Cannot open FRI layer commitment: {}", e))
+        })?;
+        let evaluations: &[[E; N]] = group_slice_elements(&layer.evaluations);
+        let queried_values = positions
+            .iter()
+            .map(|&position| evaluations[position])
+            .collect();
+        proof_layers.push(FriProofLayer::new::<E, H, N>(queried_values, proof));
+        domain_size /= N;
+    }
+
+    // the last layer is sent as the remainder, un-transposed
+    let last_values = &last_layer.evaluations;
+    let mut remainder = E::zeroed_vector(last_values.len());
+    let n = last_values.len() / N;
+    for i in 0..n {
+        for j in 0..N {
+            remainder[i + n * j] = last_values[i * N + j];
+        }
+    }
+
+    Ok(FriProof::new(proof_layers, remainder, 1))
+}
+
+#[cfg(test)]
+mod fri_test {
+    use super::*;
+    use crate::utils::{field_extension_of, FeltWrapper};
+    use miden_proof_format::field::QuadExtension;
+    use winter_fri::folding::apply_drp;
+    use winter_utils::transpose_slice;
+
+    fn elements<E: FieldElement<BaseField = Felt>>(size: usize, seed: u64) -> Vec<E> {
+        let coordinates: Vec<Felt> = (0..(size * E::EXTENSION_DEGREE) as u64)
+            .map(|i| Felt::new(i * i * 7919 + seed))
+            .collect();
+        elements_from_base(&coordinates).unwrap()
+    }
+
+    fn check_fold<E: FieldElement<BaseField = Felt>, const N: usize>() {
+        let evaluations = elements::<E>(64 * N, 13);
+        let transposed = transpose_slice::<_, N>(&evaluations);
+        let alpha = elements::<E>(1, 3_141_592_653)[0];
+        let expected = apply_drp(&transposed, Felt::GENERATOR, alpha);
+
+        // folding the layer in chunks gives the values of folding it at once
+        let rows: Vec<E> = transposed.iter().flatten().copied().collect();
+        let mut folded = Vec::new();
+        for (chunk_idx, chunk) in rows.chunks(16 * N).enumerate() {
+            folded.extend(
+                fold_rows(
+                    chunk,
+                    N,
+                    chunk_idx * 16,
+                    transposed.len(),
+                    Felt::GENERATOR,
+                    alpha,
+                )
+                .unwrap(),
+            );
+        }
+        assert_eq!(folded, expected);
+
+        // a pool worker folds rows posted in base field coordinates the same way
+        let work_item = FriFoldWorkItem {
+            rows: RowBatch::Copied(
+                transposed[16..32]
+                    .iter()
+                    .map(|row| to_base_elements(row))
+                    .collect(),
+            ),
+            first_row: 16,
+            layer_rows: transposed.len(),
+            folding_factor: N,
+            field_extension: field_extension_of::<E>(),
+            domain_offset: FeltWrapper(Felt::GENERATOR),
+            alpha: to_base_elements(&[alpha]),
+            next_layer: None,
+        };
+        let result = fold_fri_layer(&work_item).unwrap();
+        assert_eq!(result.first_row, 16);
+        assert_eq!(result.evaluations, to_base_elements(&expected[16..32]));
+    }
+
+    #[test]
+    fn test_fold_rows_matches_apply_drp() {
+        check_fold::<Felt, 4>();
+        check_fold::<Felt, 8>();
+        check_fold::<Felt, 16>();
+        check_fold::<QuadExtension<Felt>, 4>();
+        check_fold::<QuadExtension<Felt>, 8>();
+    }
+}
@@ -1,43 +1,49 @@
-use crate::utils::{
-    from_uint8array, set_once_logger, to_uint8array, HashingResult, HashingWorkItem,
-};
-use js_sys::Uint8Array;
+use crate::error::ProverError;
+use crate::utils::{HashingResult, HashingWorkItem, RowBatch};
 use log::debug;
-use miden_core::Felt;
-use wasm_bindgen::prelude::*;
-use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
-use winter_crypto::hashers::Blake2s_256;
+use miden_core::{Felt, FieldElement};
+use miden_proof_format::{hash::HashFunction, with_hasher};
 use winter_crypto::ElementHasher;
+use winter_utils::Serializable;
 
-pub fn blake2_hash_elements(work_item: &HashingWorkItem) -> Result<Uint8Array, JsValue> {
-    let mut hashes = vec![];
-    for row in work_item.data.iter() {
-        let converted_row: Vec<Felt> = row.iter().map(|f| f.clone().into()).collect();
-        let r = Blake2s_256::hash_elements(&converted_row[..]);
-        hashes.push(r.0);
-    }
+pub fn hash_elements(work_item: &HashingWorkItem) -> Result<HashingResult, ProverError> {
+    let hashes = with_hasher!(HashFunction::from(work_item.hash_fn), |H| {
+        hash_rows::<H>(work_item)
+    })?;
     debug!("done processing hashes for batch {}", work_item.batch_idx);
 
-    let response = HashingResult {
+    Ok(HashingResult {
         batch_idx: work_item.batch_idx,
         hashes,
-    };
-    Ok(to_uint8array(&response))
+    })
 }
 
-#[wasm_bindgen]
-pub fn hashing_entry_point(msg: MessageEvent) -> Result<(), JsValue> {
-    set_once_logger();
-    if let Ok(work_item) = from_uint8array::<HashingWorkItem>(&Uint8Array::new(&msg.data())) {
-        debug!(
-            "Hashing worker received work item: {:?}",
-            work_item.batch_idx
-        );
-        let response = blake2_hash_elements(&work_item)?;
-        let global_scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
-        global_scope.post_message(&response)?;
-    } else {
-        debug!("Hashing worker received invalid work item");
+fn hash_rows<H: ElementHasher<BaseField = Felt>>(
+    work_item: &HashingWorkItem,
+) -> Result<Vec<Vec<u8>>, ProverError> {
+    let mut hashes = vec![];
+    match &work_item.rows {
+        RowBatch::Copied(rows) => {
+            for row in rows.iter() {
+                let converted_row: Vec<Felt> = row.iter().map(|f| f.clone().into()).collect();
+                let r = H::hash_elements(&converted_row[..]);
+                hashes.push(r.to_bytes());
+            }
+        }
+        RowBatch::Shared {
+            matrix,
+            first_row,
+            num_rows,
+        } => {
+            // rows are read in place, only the row being hashed is copied out of the view
+            let view = matrix.view()?;
+            view.check_rows(*first_row, *num_rows)?;
+            let mut row = vec![Felt::ZERO; view.num_cols()];
+            for row_idx in *first_row..*first_row + *num_rows {
+                view.read_row_into(row_idx, &mut row);
+                hashes.push(H::hash_elements(&row).to_bytes());
+            }
+        }
     }
-    Ok(())
+    Ok(hashes)
 }
@@ -0,0 +1,194 @@
+use crate::constraints_worker::constraint_compute;
+use crate::error::ProverError;
+use crate::fri::fold_fri_layer;
+use crate::hashing_worker::hash_elements;
+use crate::lde_worker::extend_columns;
+use crate::shared::{register_buffer, unregister_buffer, BufferId};
+use crate::utils::{
+    from_uint8array, set_once_logger, to_uint8array, ConstraintComputeResult,
+    ConstraintComputeWorkItem, FriFoldResult, FriFoldWorkItem, HashingResult, HashingWorkItem,
+    LdeResult, LdeWorkItem,
+};
+use js_sys::{Reflect, SharedArrayBuffer, Uint8Array};
+use log::debug;
+use wasm_bindgen::prelude::*;
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
+
+/// Identifies a job dispatched to the pool, unique for the lifetime of a `WorkerPool`
+pub type JobId = u32;
+
+/// Work any pool worker can run.
+///
+/// A new parallel stage adds a variant here and its output to [`JobOutput`], the pool and the
+/// worker script stay the same.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub enum WorkerJob {
+    /// Hash a batch of trace rows into Merkle leaves
+    HashRows(HashingWorkItem),
+    /// Evaluate the constraints over one fragment of the evaluation domain
+    EvaluateConstraints(ConstraintComputeWorkItem),
+    /// Interpolate a range of main trace columns and extend them over the LDE domain
+    ExtendColumns(LdeWorkItem),
+    /// Fold a range of rows of a FRI layer into the next layer
+    FoldFriLayer(FriFoldWorkItem),
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub enum JobOutput {
+    RowHashes(HashingResult),
+    ConstraintEvaluations(ConstraintComputeResult),
+    ExtendedColumns(LdeResult),
+    FoldedFriLayer(FriFoldResult),
+}
+
+/// Message posted by the pool to a worker
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct JobRequest {
+    pub job_id: JobId,
+    pub job: WorkerJob,
+}
+
+/// Message posted back by a worker once its job is done.
+///
+/// `job_id` is `None` when the request itself could not be decoded.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct JobResponse {
+    pub job_id: Option<JobId>,
+    pub output: Result<JobOutput, ProverError>,
+}
+
+impl WorkerJob {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkerJob::HashRows(_) => "hash_rows",
+            WorkerJob::EvaluateConstraints(_) => "evaluate_constraints",
+            WorkerJob::ExtendColumns(_) => "extend_columns",
+            WorkerJob::FoldFriLayer(_) => "fold_fri_layer",
+        }
+    }
+
+    pub fn run(&self) -> Result<JobOutput, ProverError> {
+        match self {
+            WorkerJob::HashRows(work_item) => hash_elements(work_item).map(JobOutput::RowHashes),
+            WorkerJob::EvaluateConstraints(work_item) => {
+                constraint_compute(work_item).map(JobOutput::ConstraintEvaluations)
+            }
+            WorkerJob::ExtendColumns(work_item) => {
+                extend_columns(work_item).map(JobOutput::ExtendedColumns)
+            }
+            WorkerJob::FoldFriLayer(work_item) => {
+                fold_fri_layer(work_item).map(JobOutput::FoldedFriLayer)
+            }
+        }
+    }
+}
+
+impl JobOutput {
+    pub fn into_row_hashes(self) -> Result<HashingResult, ProverError> {
+        match self {
+            JobOutput::RowHashes(result) => Ok(result),
+            output => Err(unexpected_output("row hashes", &output)),
+        }
+    }
+
+    pub fn into_constraint_evaluations(self) -> Result<ConstraintComputeResult, ProverError> {
+        match self {
+            JobOutput::ConstraintEvaluations(result) => Ok(result),
+            output => Err(unexpected_output("constraint evaluations", &output)),
+        }
+    }
+
+    pub fn into_extended_columns(self) -> Result<LdeResult, ProverError> {
+        match self {
+            JobOutput::ExtendedColumns(result) => Ok(result),
+            output => Err(unexpected_output("extended columns", &output)),
+        }
+    }
+
+    pub fn into_folded_fri_layer(self) -> Result<FriFoldResult, ProverError> {
+        match self {
+            JobOutput::FoldedFriLayer(result) => Ok(result),
+            output => Err(unexpected_output("folded FRI layer", &output)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            JobOutput::RowHashes(_) => "row hashes",
+            JobOutput::ConstraintEvaluations(_) => "constraint evaluations",
+            JobOutput::ExtendedColumns(_) => "extended columns",
+            JobOutput::FoldedFriLayer(_) => "folded FRI layer",
+        }
+    }
+}
+
+fn unexpected_output(expected: &str, output: &JobOutput) -> ProverError {
+    ProverError::Worker(format!(
+        "expected {} from worker, got {}",
+        expected,
+        output.name()
+    ))
+}
+
+#[wasm_bindgen]
+pub fn pool_worker_entry_point(msg: MessageEvent) -> Result<(), JsValue> {
+    set_once_logger();
+    let data = msg.data();
+    if !data.is_instance_of::<Uint8Array>() {
+        handle_control_message(&data);
+        return Ok(());
+    }
+    // failures are posted back so the prover can abort instead of waiting for this job
+    let response = run_request(&data.unchecked_into());
+    let global_scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+    global_scope.post_message(&to_uint8array(&response))?;
+    Ok(())
+}
+
+/// Runs the job of an encoded [`JobRequest`], a request that cannot be decoded is answered
+/// without a job id
+pub(crate) fn run_request(data: &Uint8Array) -> JobResponse {
+    let response = match from_uint8array::<JobRequest>(data) {
+        Ok(request) => {
+            debug!(
+                "Pool worker received job {} ({})",
+                request.job_id,
+                request.job.name()
+            );
+            JobResponse {
+                job_id: Some(request.job_id),
+                output: request.job.run(),
+            }
+        }
+        Err(err) => JobResponse {
+            job_id: None,
+            output: Err(err),
+        },
+    };
+    if let Err(err) = &response.output {
+        debug!("Pool worker failed: {}", err);
+    }
+    response
+}
+
+// shared buffers are attached and detached with `{ attach, buffer }` and `{ detach }` objects
+fn handle_control_message(data: &JsValue) {
+    let field = |name: &str| {
+        Reflect::get(data, &name.into())
+            .ok()
+            .and_then(|v| v.as_f64())
+    };
+    if let Some(buffer_id) = field("attach") {
+        match Reflect::get(data, &"buffer".into()).map(|b| b.dyn_into::<SharedArrayBuffer>()) {
+            Ok(Ok(buffer)) => register_buffer(buffer_id as BufferId, buffer),
+            _ => debug!(
+                "Pool worker received shared buffer {} without a buffer",
+                buffer_id
+            ),
+        }
+    } else if let Some(buffer_id) = field("detach") {
+        unregister_buffer(buffer_id as BufferId);
+    } else {
+        debug!("Pool worker received unknown message: {:?}", data);
+    }
+}
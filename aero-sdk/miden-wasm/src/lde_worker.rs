@@ -0,0 +1,77 @@
+use crate::error::ProverError;
+use crate::utils::{elements_from_base, to_base_elements, unwrap_elements, LdeResult, LdeWorkItem};
+use log::debug;
+use miden_core::{Felt, FieldElement};
+use miden_proof_format::{field::FieldExtension, with_field_extension};
+use winter_math::fft;
+
+/// Interpolates the columns of the work item and evaluates them over the LDE domain, the same
+/// way `Matrix::interpolate_columns` and `Matrix::evaluate_columns_over` do for the whole trace
+/// segment.
+///
+/// With a shared LDE segment the evaluations are written straight into it, only the polynomials
+/// are posted back.
+pub fn extend_columns(work_item: &LdeWorkItem) -> Result<LdeResult, ProverError> {
+    let result = with_field_extension!(FieldExtension::from(work_item.field_extension), |E| {
+        extend_columns_in::<E>(work_item)
+    })?;
+    debug!(
+        "done extending {} columns from column {}",
+        work_item.columns.len(),
+        work_item.first_column
+    );
+    Ok(result)
+}
+
+fn extend_columns_in<E: FieldElement<BaseField = Felt>>(
+    work_item: &LdeWorkItem,
+) -> Result<LdeResult, ProverError> {
+    let trace_length = work_item
+        .columns
+        .first()
+        .map_or(0, |column| column.len() / E::EXTENSION_DEGREE);
+    let inv_twiddles = fft::get_inv_twiddles::<Felt>(trace_length);
+    let twiddles = fft::get_twiddles::<Felt>(trace_length);
+    let shared_lde = work_item.lde.as_ref().map(|lde| lde.view()).transpose()?;
+
+    let mut polys = Vec::with_capacity(work_item.columns.len());
+    let mut lde = Vec::new();
+    for (i, column) in work_item.columns.iter().enumerate() {
+        let mut poly = elements_from_base::<E>(&unwrap_elements(column))?;
+        if poly.len() != trace_length {
+            return Err(ProverError::Worker(format!(
+                "trace column of {} elements does not match the trace length {}",
+                poly.len(),
+                trace_length
+            )));
+        }
+        fft::interpolate_poly(&mut poly, &inv_twiddles);
+        let evaluations = fft::evaluate_poly_with_offset(
+            &poly,
+            &twiddles,
+            work_item.domain_offset.0,
+            work_item.blowup_factor,
+        );
+        polys.push(to_base_elements(&poly));
+        match &shared_lde {
+            Some(shared_lde) => {
+                if evaluations.len() != shared_lde.num_rows() {
+                    return Err(ProverError::Worker(format!(
+                        "extended column of {} rows does not fit a shared LDE of {} rows",
+                        evaluations.len(),
+                        shared_lde.num_rows()
+                    )));
+                }
+                let col_idx = work_item.first_column + i;
+                shared_lde.write_elements(col_idx * evaluations.len(), &evaluations)?;
+            }
+            None => lde.push(to_base_elements(&evaluations)),
+        }
+    }
+
+    Ok(LdeResult {
+        first_column: work_item.first_column,
+        polys,
+        lde,
+    })
+}
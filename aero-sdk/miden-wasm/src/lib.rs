@@ -1,59 +1,39 @@
 #![feature(once_cell)]
-use futures::Future;
-use js_sys::Uint8Array;
+use js_sys::{Function, Uint8Array};
 use log::debug;
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use miden_air::PublicInputs;
+use miden_core::StarkField;
+use miden_proof_format::sdk;
+use prost::Message;
 use std::{cell::RefCell, rc::Rc};
 use utils::set_once_logger;
 use wasm_bindgen::prelude::*;
-use web_sys::{MessageEvent, Worker};
+use web_sys::{ErrorEvent, MessageEvent, Worker};
 
 macro_rules! console_log {
     ($($t:tt)*) => (crate::log(&format_args!($($t)*).to_string()))
 }
 
+pub mod collector;
 pub mod constraints_worker;
-pub mod convert;
+pub mod error;
+pub mod fri;
 pub mod hashing_worker;
+pub mod jobs;
+pub mod lde_worker;
 pub mod pool;
 pub mod proving_worker;
+pub mod shared;
 pub mod utils;
-use crate::convert::sdk::sdk;
-use crate::utils::{from_uint8array, to_uint8array, ProverOutput, ProvingWorkItem};
+use crate::collector::{Abort, Collector};
+use crate::error::ProverError;
+use crate::pool::PoolConfig;
+use crate::utils::{
+    from_uint8array, to_uint8array, ProverMessage, ProverOutput, ProverProgress, ProvingWorkItem,
+    WorkPartition,
+};
 use proving_worker::{proving_seq_entry_point, MidenProverAsyncWorker};
 
-pub struct ResultFuture<T> {
-    pub result: Rc<RefCell<Option<T>>>,
-}
-
-impl<T> Future for ResultFuture<T> {
-    type Output = ();
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let r = (*self.result).borrow();
-        if r.is_some() {
-            return Poll::Ready(());
-        } else {
-            // wait every second
-            let wait_fn = {
-                let waker = Rc::new(cx.waker().clone());
-                Closure::wrap(Box::new(move || {
-                    waker.as_ref().clone().wake();
-                }) as Box<dyn Fn()>)
-            };
-            let _ = web_sys::window()
-                .unwrap()
-                .set_timeout_with_callback_and_timeout_and_arguments_0(
-                    wait_fn.as_ref().unchecked_ref(),
-                    200,
-                );
-            wait_fn.forget();
-            return Poll::Pending;
-        }
-    }
-}
-
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
@@ -68,112 +48,316 @@ pub fn start() -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Verifies a protobuf encoded `StarkProof` against its protobuf encoded `MidenPublicInputs`.
+///
+/// Returns `false` when the proof is well formed but does not verify, and a `DECODE_ERROR`
+/// when the messages cannot be decoded.
+#[wasm_bindgen]
+pub fn verify(proof_bytes: Vec<u8>, public_inputs_bytes: Vec<u8>) -> Result<bool, JsValue> {
+    Ok(verify_bytes(&proof_bytes, &public_inputs_bytes)?)
+}
+
+fn verify_bytes(proof_bytes: &[u8], public_inputs_bytes: &[u8]) -> Result<bool, ProverError> {
+    let sdk_proof = sdk::StarkProof::decode(proof_bytes)
+        .map_err(|e| ProverError::Decode(format!("Could not decode proof due to {}", e)))?;
+    let sdk_pub_inputs = sdk::MidenPublicInputs::decode(public_inputs_bytes)
+        .map_err(|e| ProverError::Decode(format!("Could not decode public inputs due to {}", e)))?;
+    let proof = miden::StarkProof::try_from(sdk_proof)
+        .map_err(|e| ProverError::Decode(format!("Could not convert proof due to {}", e)))?;
+    let pub_inputs = PublicInputs::try_from(sdk_pub_inputs).map_err(|e| {
+        ProverError::Decode(format!("Could not convert public inputs due to {}", e))
+    })?;
+
+    let stack_inputs: Vec<u64> = pub_inputs
+        .stack_inputs
+        .iter()
+        // stack inputs are stored in reverse order
+        .rev()
+        .map(|e| e.as_int())
+        .collect();
+
+    match miden::verify(
+        pub_inputs.program_hash,
+        &stack_inputs,
+        &pub_inputs.outputs,
+        proof,
+    ) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            debug!("Proof did not verify: {}", e);
+            Ok(false)
+        }
+    }
+}
+
 #[wasm_bindgen(getter_with_clone)]
 pub struct MidenProver {
-    prover_worker: Worker,
-    prover_output: Rc<RefCell<Option<ProverOutput>>>,
+    prover_worker: RefCell<Worker>,
+    prover_output: Collector<Result<ProverOutput, ProverError>>,
+    progress_callback: Rc<RefCell<Option<Function>>>,
+    // abort cell of the prover running on the main thread when `local_run` is set
+    local_abort: RefCell<Option<Abort>>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+    on_error: Closure<dyn FnMut(ErrorEvent)>,
+    pool_config: PoolConfig,
 }
 
 #[wasm_bindgen]
 impl MidenProver {
+    /// Creates a prover whose workers are sized and located by `pool_config`.
+    ///
+    /// `pool_config` is an optional `{ workers, provingWorkerUrl, poolWorkerUrl }` object, see
+    /// [`PoolConfig`].
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<MidenProver, JsValue> {
-        let proving_worker = Worker::new("./proving_worker.js")?;
-        proving_worker.post_message(&JsValue::from_str("wake worker up"))?;
+    pub fn new(pool_config: JsValue) -> Result<MidenProver, JsValue> {
+        let pool_config = PoolConfig::from_js(&pool_config)?;
+        let prover_output = Collector::new(Abort::default());
+        let progress_callback = Rc::new(RefCell::new(None));
+        // kept alive for the lifetime of the prover and shared by every proving worker it spawns
+        let on_message =
+            Self::get_on_msg_callback(prover_output.clone(), progress_callback.clone());
+        let on_error = Self::get_on_error_callback(prover_output.clone());
+        let prover_worker = Self::spawn_proving_worker(&pool_config, &on_message, &on_error)?;
         Ok(MidenProver {
-            prover_worker: proving_worker,
-            prover_output: Rc::new(RefCell::new(None)),
+            prover_worker: RefCell::new(prover_worker),
+            prover_output,
+            progress_callback,
+            local_abort: RefCell::new(None),
+            on_message,
+            on_error,
+            pool_config,
         })
     }
 
+    /// Registers a callback invoked with `{ stage, completed, total }` as the proof advances
+    #[wasm_bindgen]
+    pub fn set_progress_callback(&self, callback: Option<Function>) {
+        self.progress_callback.replace(callback);
+    }
+
+    /// Proves the program on the worker pool.
+    ///
+    /// `chunk_size` rows are hashed per job and the constraints are evaluated in
+    /// `num_fragments` fragments, both are derived from the trace and pool sizes when undefined.
+    /// Quadratic and cubic field extension proofs are proven on the pool as well.
     #[wasm_bindgen]
     pub async fn prove(
-        &mut self,
+        &self,
         program: Vec<u8>,
         program_inputs: Vec<u8>,
         proof_options: Vec<u8>,
-        chunk_size: usize,
+        chunk_size: Option<usize>,
+        num_fragments: Option<usize>,
         local_run: bool,
     ) -> Result<ProverOutput, JsValue> {
-        self.set_onmessage_handler();
         let work_item = ProvingWorkItem {
             program,
             program_inputs,
             proof_options,
-            chunk_size,
+            partition: WorkPartition {
+                chunk_size,
+                num_fragments,
+            },
             is_sequential: false,
         };
-        let payload = to_uint8array(&work_item);
-        if local_run {
-            let mut miden_prover = MidenProverAsyncWorker::new()?;
-            let output = proving_seq_entry_point(&mut miden_prover, payload).await?;
-
-            let deser_output: ProverOutput = from_uint8array(&output).unwrap();
-            Ok(deser_output)
-        } else {
-            self.prover_worker.post_message(&payload)?;
-            ResultFuture {
-                result: self.prover_output.clone(),
-            }
-            .await;
-            let output = self.prover_output.borrow_mut().take().unwrap();
-            Ok(output)
-        }
+        self.run(work_item, local_run).await
     }
 
     #[wasm_bindgen]
     pub async fn prove_sequential(
-        &mut self,
+        &self,
         program: Vec<u8>,
         program_inputs: Vec<u8>,
         proof_options: Vec<u8>,
         local_run: bool,
     ) -> Result<ProverOutput, JsValue> {
-        self.set_onmessage_handler();
         let work_item = ProvingWorkItem {
             program,
             program_inputs,
             proof_options,
-            chunk_size: 1024,
+            partition: WorkPartition::default(),
             is_sequential: true,
         };
+        self.run(work_item, local_run).await
+    }
+
+    /// Cancels the running proof, which then rejects with a `CANCELLED` error.
+    ///
+    /// The proving worker and the workers it spawned are terminated and replaced by a fresh
+    /// one. A proof running on the main thread stops at its next stage or worker result.
+    #[wasm_bindgen]
+    pub fn cancel(&self) -> Result<(), JsValue> {
+        if let Some(abort) = self.local_abort.borrow().as_ref() {
+            abort.abort(ProverError::Cancelled);
+            return Ok(());
+        }
+        self.restart_proving_worker()?;
+        self.prover_output.push(Err(ProverError::Cancelled));
+        Ok(())
+    }
+
+    /// Replaces the proving worker, terminating it along with the workers it spawned
+    fn restart_proving_worker(&self) -> Result<(), JsValue> {
+        self.prover_worker.borrow().terminate();
+        self.prover_worker.replace(Self::spawn_proving_worker(
+            &self.pool_config,
+            &self.on_message,
+            &self.on_error,
+        )?);
+        Ok(())
+    }
+
+    /// Spawns the proving worker, its first message hands over the pool configuration
+    fn spawn_proving_worker(
+        pool_config: &PoolConfig,
+        on_message: &Closure<dyn FnMut(MessageEvent)>,
+        on_error: &Closure<dyn FnMut(ErrorEvent)>,
+    ) -> Result<Worker, JsValue> {
+        let proving_worker = Worker::new(&pool_config.proving_worker_url)?;
+        proving_worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        proving_worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        proving_worker.post_message(&JsValue::from_str(&pool_config.to_json()))?;
+        Ok(proving_worker)
+    }
+
+    async fn run(
+        &self,
+        work_item: ProvingWorkItem,
+        local_run: bool,
+    ) -> Result<ProverOutput, JsValue> {
         let payload = to_uint8array(&work_item);
         if local_run {
-            let mut miden_prover = MidenProverAsyncWorker::new()?;
-            let output = proving_seq_entry_point(&mut miden_prover, payload).await?;
-
-            let deser_output: ProverOutput = from_uint8array(&output).unwrap();
-            Ok(deser_output)
+            let mut miden_prover =
+                MidenProverAsyncWorker::with_pool_config(self.pool_config.clone());
+            let progress_callback = self.progress_callback.clone();
+            miden_prover.set_progress_sink(Rc::new(move |progress: &ProverProgress| {
+                report_progress(&progress_callback, progress)
+            }));
+            self.local_abort.replace(Some(miden_prover.abort_handle()));
+            let output = proving_seq_entry_point(&mut miden_prover, payload).await;
+            self.local_abort.replace(None);
+            Ok(output?)
         } else {
-            self.prover_worker.post_message(&payload)?;
-            ResultFuture {
-                result: self.prover_output.clone(),
+            self.prover_output.clear();
+            self.prover_worker.borrow().post_message(&payload)?;
+            self.prover_output.collect(1).await?;
+            let output = self.prover_output.take().into_iter().next().unwrap();
+            // a worker failure may leave the proving worker or its pool in a broken state
+            if let Err(ProverError::Worker(_)) = &output {
+                self.restart_proving_worker()?;
             }
-            .await;
-            let output = self.prover_output.borrow_mut().take().unwrap();
-            Ok(output)
+            Ok(output?)
         }
     }
 
-    fn set_onmessage_handler(&mut self) {
-        let callback = self.get_on_msg_callback();
-        self.prover_worker
-            .set_onmessage(Some(callback.as_ref().unchecked_ref()));
+    /// Message passing by the main thread
+    fn get_on_msg_callback(
+        prover_output: Collector<Result<ProverOutput, ProverError>>,
+        progress_callback: Rc<RefCell<Option<Function>>>,
+    ) -> Closure<dyn FnMut(MessageEvent)> {
+        Closure::new(move |event: MessageEvent| {
+            let data: Uint8Array = Uint8Array::new(&event.data());
+            match from_uint8array::<ProverMessage>(&data) {
+                Ok(ProverMessage::Progress(progress)) => {
+                    report_progress(&progress_callback, &progress)
+                }
+                Ok(ProverMessage::Done(output)) => {
+                    debug!("Main thread got prover output");
+                    prover_output.push(output);
+                }
+                Err(err) => {
+                    prover_output.push(Err(err));
+                }
+            }
+        })
+    }
 
-        // Clean up closure to prevent memory leak
-        callback.forget();
+    /// Rejects the running proof when the proving worker throws, e.g. on a wasm trap
+    fn get_on_error_callback(
+        prover_output: Collector<Result<ProverOutput, ProverError>>,
+    ) -> Closure<dyn FnMut(ErrorEvent)> {
+        Closure::new(move |event: ErrorEvent| {
+            event.prevent_default();
+            prover_output.push(Err(ProverError::Worker(format!(
+                "proving worker raised: {}",
+                event.message()
+            ))));
+        })
     }
+}
 
-    /// Message passing by the main thread
-    fn get_on_msg_callback(&self) -> Closure<dyn FnMut(MessageEvent)> {
-        let prover_output = self.prover_output.clone();
-        let callback = Closure::new(move |event: MessageEvent| {
-            debug!("Main thread got prover output");
-            let data: Uint8Array = Uint8Array::new(&event.data());
-            let output: ProverOutput = from_uint8array(&data).unwrap();
-            prover_output.replace(Some(output));
-        });
+// the proving worker outlives the wasm object unless it is stopped explicitly
+impl Drop for MidenProver {
+    fn drop(&mut self) {
+        self.prover_worker.borrow().terminate();
+    }
+}
+
+fn report_progress(callback: &Rc<RefCell<Option<Function>>>, progress: &ProverProgress) {
+    if let Some(callback) = callback.borrow().as_ref() {
+        if let Err(err) = callback.call1(&JsValue::NULL, &progress.into()) {
+            debug!("progress callback failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod verify_test {
+    use miden_core::Felt;
+    use miden_proof_format::ProofData;
+
+    use super::*;
+
+    fn fib_proof() -> (sdk::StarkProof, sdk::MidenPublicInputs) {
+        ProofData::from_file(&format!(
+            "{}/../../proofs/fib.bin",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap()
+        .to_sdk()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_valid_proof() {
+        let (proof, pub_inputs) = fib_proof();
+        assert_eq!(
+            verify_bytes(&proof.encode_to_vec(), &pub_inputs.encode_to_vec()),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_verify_tampered_proof() {
+        let (proof, pub_inputs) = fib_proof();
+        let mut pub_inputs = PublicInputs::try_from(pub_inputs).unwrap();
+        pub_inputs.stack_inputs = vec![Felt::new(2), Felt::new(3)];
+        let pub_inputs = sdk::MidenPublicInputs::from(pub_inputs);
+        assert_eq!(
+            verify_bytes(&proof.encode_to_vec(), &pub_inputs.encode_to_vec()),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_verify_garbage() {
+        let err = verify_bytes(&[0xff; 32], &[0xff; 32]).unwrap_err();
+        assert_eq!(err.code(), "DECODE_ERROR");
+    }
 
-        callback
+    #[test]
+    fn test_verify_corrupted_context() {
+        // the winterfell constructors assert these, a panic would abort the wasm instance
+        let corruptions: [fn(&mut sdk::Context); 2] = [
+            |context| context.trace_length = 7,
+            |context| context.trace_layout.as_mut().unwrap().main_segment_width = 0,
+        ];
+        for corrupt in corruptions {
+            let (mut proof, pub_inputs) = fib_proof();
+            corrupt(proof.context.as_mut().unwrap());
+            let err =
+                verify_bytes(&proof.encode_to_vec(), &pub_inputs.encode_to_vec()).unwrap_err();
+            assert_eq!(err.code(), "DECODE_ERROR");
+        }
     }
 }
@@ -1,160 +1,523 @@
 // Silences warnings from the compiler about Work.func and child_entry_point
 // being unused when the target is not wasm.
 #![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+use js_sys::{Date, Function, Object, Reflect, SharedArrayBuffer, Uint8Array};
 use log::debug;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
 use wasm_bindgen::prelude::*;
-use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, Worker, WorkerNavigator};
+use web_sys::{ErrorEvent, MessageEvent, Worker};
 
-use crate::utils::{to_uint8array, ConstraintComputeWorkItem, FeltWrapper, HashingWorkItem};
+use crate::error::ProverError;
+#[cfg(test)]
+use crate::jobs::run_request;
+use crate::jobs::{JobId, JobOutput, JobRequest, JobResponse, WorkerJob};
+use crate::shared::{register_buffer, unregister_buffer, BufferId};
+use crate::utils::{from_uint8array, to_uint8array};
 
-#[derive(Debug, Clone)]
+#[wasm_bindgen]
+extern "C" {
+    // global functions of both `Window` and worker scopes
+    #[wasm_bindgen(js_name = setInterval)]
+    fn set_interval(handler: &Function, timeout: u32) -> JsValue;
+    #[wasm_bindgen(js_name = clearInterval)]
+    fn clear_interval(handle: &JsValue);
+}
+
+// how often pending jobs are checked against their deadline at most
+const WATCHDOG_INTERVAL_MS: u32 = 1000;
+
+/// Sizing and script locations of the worker pool, passed from JS as a plain object.
+///
+/// A missing worker count defaults to `navigator.hardwareConcurrency` and missing URLs to the
+/// paths the bundled SDK emits next to the page. URLs may be `blob:` URLs created by the page
+/// when the scripts are not served from a fixed path.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PoolConfig {
+    pub workers: Option<usize>,
+    pub proving_worker_url: String,
+    pub pool_worker_url: String,
+    /// Milliseconds a job may take once dispatched before its worker is restarted, 0 waits
+    /// forever. Disabled by default as the time a job takes grows with the trace length
+    pub job_timeout_ms: u32,
+    /// Times a job is resubmitted after its worker failed before the proof is aborted
+    pub job_retries: u32,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            workers: None,
+            proving_worker_url: "./proving_worker.js".to_string(),
+            pool_worker_url: "./pool_worker.js".to_string(),
+            job_timeout_ms: 0,
+            job_retries: 2,
+        }
+    }
+}
+
+impl PoolConfig {
+    /// Reads the configuration from a JS object, `undefined` and `null` give the defaults
+    pub fn from_js(value: &JsValue) -> Result<PoolConfig, ProverError> {
+        if value.is_undefined() || value.is_null() {
+            return Ok(PoolConfig::default());
+        }
+        let json = js_sys::JSON::stringify(value)
+            .map_err(|_| ProverError::Decode("pool config is not a plain object".to_string()))?;
+        Self::from_json(&String::from(json))
+    }
+
+    pub fn from_json(json: &str) -> Result<PoolConfig, ProverError> {
+        serde_json::from_str(json)
+            .map_err(|e| ProverError::Decode(format!("invalid pool config: {}", e)))
+    }
+
+    pub fn to_json(&self) -> String {
+        // a struct of strings and integers always serializes
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Workers the proving worker dispatches jobs to.
+///
+/// Every worker runs the same script and any [`WorkerJob`], jobs are handed out round robin and
+/// their results routed back by job id. Workers are spawned the first time a job is dispatched
+/// to them, so a small trace never pays for a full pool. Clones share the spawned workers.
+///
+/// A worker that throws, posts back a malformed response or exceeds the job timeout is
+/// restarted and its pending jobs are resubmitted to other workers, until a job runs out of
+/// retries and fails with a `WORKER_ERROR`. Errors returned by the job itself are not retried.
+#[derive(Clone)]
 pub struct WorkerPool {
-    state: PoolState,
+    state: Rc<RefCell<PoolState>>,
 }
 
-#[derive(Debug, Clone)]
+/// Called once with the output of a job
+pub type JobCallback = Box<dyn FnOnce(Result<JobOutput, ProverError>)>;
+
 struct PoolState {
-    workers: Vec<Worker>,
-    constraint_workers: Vec<Worker>,
+    // handed to the worker callbacks, which must not keep the pool alive
+    this: Weak<RefCell<PoolState>>,
+    config: PoolConfig,
+    concurrency: usize,
+    workers: Vec<PoolWorker>,
+    next_worker: usize,
+    next_job_id: JobId,
+    next_buffer_id: BufferId,
+    // shared buffers posted to every worker, including the ones spawned later
+    attached: HashMap<BufferId, SharedArrayBuffer>,
+    pending: HashMap<JobId, PendingJob>,
+    watchdog: Option<Watchdog>,
+    // jobs run on the calling thread instead of spawned workers
+    #[cfg(test)]
+    inline: bool,
+}
+
+struct PoolWorker {
+    worker: Worker,
+    // kept alive as long as the worker is, they only hold a weak reference to the pool
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(ErrorEvent)>,
+}
+
+struct PendingJob {
+    worker_idx: usize,
+    name: &'static str,
+    // kept to resubmit the job when its worker fails
+    payload: Uint8Array,
+    retries: u32,
+    // `Date.now()` after which the job is considered stuck
+    deadline: Option<f64>,
+    on_done: JobCallback,
+}
+
+/// Interval checking pending jobs against their deadline, cleared once no job is pending
+struct Watchdog {
+    handle: JsValue,
+    _on_tick: Closure<dyn FnMut()>,
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        clear_interval(&self.handle);
+    }
 }
 
 impl WorkerPool {
     fn get_hardware_concurrency() -> usize {
-        let global = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
-        let navigator: WorkerNavigator = global.navigator();
-        navigator.hardware_concurrency() as usize
+        // `navigator` is a `WorkerNavigator` in the proving worker and a `Navigator` when proving
+        // on the main thread, both expose `hardwareConcurrency`
+        Reflect::get(&js_sys::global(), &"navigator".into())
+            .and_then(|navigator| Reflect::get(&navigator, &"hardwareConcurrency".into()))
+            .ok()
+            .and_then(|concurrency| concurrency.as_f64())
+            .map_or(1, |concurrency| (concurrency as usize).max(1))
     }
 
-    pub fn new() -> Result<WorkerPool, JsValue> {
-        let concurrency = Self::get_hardware_concurrency();
+    pub fn new(config: PoolConfig) -> WorkerPool {
+        let concurrency = config
+            .workers
+            .unwrap_or_else(Self::get_hardware_concurrency)
+            .max(1);
         debug!("creating worker pool with concurrency {}", concurrency);
-        let mut pool = WorkerPool {
-            state: PoolState {
-                workers: Vec::with_capacity(concurrency),
-                constraint_workers: Vec::with_capacity(concurrency),
+        WorkerPool {
+            state: Rc::new_cyclic(|this| {
+                RefCell::new(PoolState {
+                    this: this.clone(),
+                    config,
+                    concurrency,
+                    workers: Vec::with_capacity(concurrency),
+                    next_worker: 0,
+                    next_job_id: 0,
+                    next_buffer_id: 0,
+                    attached: HashMap::new(),
+                    pending: HashMap::new(),
+                    watchdog: None,
+                    #[cfg(test)]
+                    inline: false,
+                })
+            }),
+        }
+    }
+
+    /// A pool of `concurrency` workers running every job on the calling thread once it yields,
+    /// for browser tests which have no pool worker script to spawn
+    #[cfg(test)]
+    pub(crate) fn inline(concurrency: usize) -> WorkerPool {
+        let pool = Self::new(PoolConfig {
+            workers: Some(concurrency),
+            ..PoolConfig::default()
+        });
+        pool.state.borrow_mut().inline = true;
+        pool
+    }
+
+    /// Number of workers jobs are spread across
+    pub fn concurrency(&self) -> usize {
+        self.state.borrow().concurrency
+    }
+
+    /// Dispatches `job` to the next worker, `on_done` is called with its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error that may happen while a JS web worker is created and the job is
+    /// posted to it.
+    pub fn submit(
+        &self,
+        job: WorkerJob,
+        on_done: impl FnOnce(Result<JobOutput, ProverError>) + 'static,
+    ) -> Result<JobId, JsValue> {
+        let mut state = self.state.borrow_mut();
+        let job_id = state.next_job_id;
+        state.next_job_id = state.next_job_id.wrapping_add(1);
+        let name = job.name();
+        let payload = to_uint8array(&JobRequest { job_id, job });
+        let worker_idx = state.next_worker_idx(None);
+        debug!(
+            "running job {} ({}) on worker idx: {}",
+            job_id, name, worker_idx
+        );
+        state.post_job(worker_idx, &payload)?;
+        let deadline = state.deadline();
+        state.pending.insert(
+            job_id,
+            PendingJob {
+                worker_idx,
+                name,
+                payload,
+                retries: 0,
+                deadline,
+                on_done: Box::new(on_done),
             },
-        };
-        for _ in 0..concurrency {
-            let worker = pool.spawn("./hashing_worker.js")?;
-            pool.state.push(worker);
-            let constraint_worker = pool.spawn("./constraints_worker.js")?;
-            pool.state.push_constraint_worker(constraint_worker);
+        );
+        if state.watchdog.is_none() && state.config.job_timeout_ms > 0 {
+            state.watchdog = Some(Self::start_watchdog(&state));
         }
+        Ok(job_id)
+    }
 
-        Ok(pool)
+    /// Shares `buffer` with the workers, jobs refer to it by the returned id
+    pub fn attach(&self, buffer: &SharedArrayBuffer) -> Result<BufferId, JsValue> {
+        let mut state = self.state.borrow_mut();
+        let buffer_id = state.next_buffer_id;
+        state.next_buffer_id = state.next_buffer_id.wrapping_add(1);
+        let message = attach_message(buffer_id, buffer);
+        for pool_worker in state.workers.iter() {
+            pool_worker.worker.post_message(&message)?;
+        }
+        state.attached.insert(buffer_id, buffer.clone());
+        register_buffer(buffer_id, buffer.clone());
+        Ok(buffer_id)
+    }
+
+    /// Releases a buffer shared by [`WorkerPool::attach`]
+    pub fn detach(&self, buffer_id: BufferId) {
+        let mut state = self.state.borrow_mut();
+        state.attached.remove(&buffer_id);
+        unregister_buffer(buffer_id);
+        let message = Object::new();
+        // setting properties on a fresh object cannot fail
+        let _ = Reflect::set(&message, &"detach".into(), &buffer_id.into());
+        for pool_worker in state.workers.iter() {
+            if let Err(err) = pool_worker.worker.post_message(&message) {
+                debug!("failed to detach shared buffer {}: {:?}", buffer_id, err);
+            }
+        }
+    }
+
+    /// Terminates every spawned worker and drops the pending jobs, jobs submitted afterwards
+    /// run on freshly spawned workers
+    pub fn terminate(&self) {
+        let mut state = self.state.borrow_mut();
+        for pool_worker in state.workers.drain(..) {
+            pool_worker.worker.terminate();
+        }
+        state.pending.clear();
+        state.watchdog = None;
+        state.next_worker = 0;
     }
 
     /// Unconditionally spawns a new worker
     ///
-    /// The worker isn't registered with this `WorkerPool` but is capable of
-    /// executing work for this wasm module.
-    ///
     /// # Errors
     ///
     /// Returns any error that may happen while a JS web worker is created and a
     /// message is sent to it.
-    fn spawn(&self, worker_path: &str) -> Result<Worker, JsValue> {
+    fn spawn(
+        worker_path: &str,
+        state: Weak<RefCell<PoolState>>,
+        worker_idx: usize,
+    ) -> Result<PoolWorker, JsValue> {
         console_log!("spawning new worker, {}", worker_path);
-        // TODO: what do do about `./worker.js`:
-        //
-        // * the path is only known by the bundler. How can we, as a
-        //   library, know what's going on?
-        // * How do we not fetch a script N times? It internally then
-        //   causes another script to get fetched N times...
         let worker = Worker::new(worker_path)?;
+        let on_message = {
+            let state = state.clone();
+            Closure::new(move |event: MessageEvent| {
+                let data = Uint8Array::new(&event.data());
+                let response =
+                    from_uint8array::<JobResponse>(&data).unwrap_or_else(|err| JobResponse {
+                        job_id: None,
+                        output: Err(err),
+                    });
+                if let Some(state) = state.upgrade() {
+                    Self::complete(&state, worker_idx, response);
+                }
+            })
+        };
+        // uncaught exceptions, including wasm traps, never post a response for the job
+        let on_error = Closure::new(move |event: ErrorEvent| {
+            event.prevent_default();
+            if let Some(state) = state.upgrade() {
+                let err = ProverError::Worker(format!("worker raised: {}", event.message()));
+                Self::fail_worker(&state, worker_idx, err);
+            }
+        });
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
         worker.post_message(&JsValue::from_str("wake worker up"))?;
-        Ok(worker)
+        Ok(PoolWorker {
+            worker,
+            _on_message: on_message,
+            _on_error: on_error,
+        })
     }
 
-    fn worker(&self, worker_idx: usize) -> Result<&Worker, JsValue> {
-        let worker = &self.state.workers[worker_idx];
-        Ok(worker)
+    fn start_watchdog(state: &PoolState) -> Watchdog {
+        let this = state.this.clone();
+        let on_tick = Closure::new(move || {
+            if let Some(state) = this.upgrade() {
+                Self::check_deadlines(&state);
+            }
+        });
+        let interval = state.config.job_timeout_ms.min(WATCHDOG_INTERVAL_MS);
+        Watchdog {
+            handle: set_interval(on_tick.as_ref().unchecked_ref(), interval),
+            _on_tick: on_tick,
+        }
     }
 
-    fn constraint_worker(&self, worker_idx: usize) -> Result<&Worker, JsValue> {
-        let worker = &self.state.constraint_workers[worker_idx];
-        Ok(worker)
+    /// Routes a response to the callback of its job.
+    ///
+    /// A response without a job id is a worker failure since there is no telling which job it
+    /// belongs to.
+    fn complete(state: &Rc<RefCell<PoolState>>, worker_idx: usize, response: JobResponse) {
+        // callbacks run once the state is released, they may submit follow up jobs
+        match response.job_id {
+            Some(job_id) => {
+                let job = {
+                    let mut state = state.borrow_mut();
+                    let job = state.pending.remove(&job_id);
+                    state.stop_idle_watchdog();
+                    job
+                };
+                if let Some(job) = job {
+                    (job.on_done)(response.output);
+                }
+            }
+            None => {
+                let err = match response.output {
+                    Err(err) => err,
+                    Ok(_) => ProverError::Worker("worker response without a job id".to_string()),
+                };
+                Self::fail_worker(state, worker_idx, err);
+            }
+        }
     }
 
-    fn execute(
-        &self,
-        batch_idx: usize,
-        elements_table: Vec<Vec<FeltWrapper>>,
-        get_on_msg_callback: Closure<dyn FnMut(MessageEvent)>,
-    ) -> Result<(), JsValue> {
-        debug!(
-            "batch_idx: {}, concurrency: {}",
-            batch_idx,
-            self.state.concurrency()
-        );
-        let worker_idx = batch_idx % self.state.concurrency();
-        debug!("running on worker idx: {}", worker_idx);
-        let worker = self.worker(worker_idx)?;
+    /// Restarts the workers holding a job past its deadline
+    fn check_deadlines(state: &Rc<RefCell<PoolState>>) {
+        let now = Date::now();
+        let mut stuck: Vec<(usize, JobId)> = state
+            .borrow()
+            .pending
+            .iter()
+            .filter(|(_, job)| job.deadline.map_or(false, |deadline| deadline < now))
+            .map(|(job_id, job)| (job.worker_idx, *job_id))
+            .collect();
+        stuck.sort_unstable();
+        stuck.dedup_by_key(|(worker_idx, _)| *worker_idx);
+        let timeout = state.borrow().config.job_timeout_ms;
+        for (worker_idx, job_id) in stuck {
+            let err = ProverError::Worker(format!("job {} timed out after {} ms", job_id, timeout));
+            Self::fail_worker(state, worker_idx, err);
+        }
+    }
 
-        let work_item = HashingWorkItem {
-            data: elements_table,
-            batch_idx,
+    /// Restarts a failed worker and resubmits its pending jobs to the other workers, the jobs
+    /// out of retries fail with `err`
+    fn fail_worker(state: &Rc<RefCell<PoolState>>, worker_idx: usize, err: ProverError) {
+        debug!("worker idx: {} failed: {}", worker_idx, err);
+        let failed: Vec<(PendingJob, ProverError)> = {
+            let mut state = state.borrow_mut();
+            let job_ids: Vec<JobId> = state
+                .pending
+                .iter()
+                .filter(|(_, job)| job.worker_idx == worker_idx)
+                .map(|(job_id, _)| *job_id)
+                .collect();
+            let mut failed = Vec::new();
+            let failed = if let Err(restart_err) = state.restart_worker(worker_idx) {
+                for job_id in job_ids {
+                    let job = state.pending.remove(&job_id).unwrap();
+                    failed.push((job, ProverError::from(restart_err.clone())));
+                }
+                failed
+            } else {
+                for job_id in job_ids {
+                    let mut job = state.pending.remove(&job_id).unwrap();
+                    if job.retries >= state.config.job_retries {
+                        let err = ProverError::Worker(format!(
+                            "job {} ({}) failed after {} retries: {}",
+                            job_id,
+                            job.name,
+                            job.retries,
+                            err.message()
+                        ));
+                        failed.push((job, err));
+                        continue;
+                    }
+                    let retry_idx = state.next_worker_idx(Some(worker_idx));
+                    debug!(
+                        "retrying job {} ({}) on worker idx: {}",
+                        job_id, job.name, retry_idx
+                    );
+                    match state.post_job(retry_idx, &job.payload) {
+                        Ok(()) => {
+                            job.worker_idx = retry_idx;
+                            job.retries += 1;
+                            job.deadline = state.deadline();
+                            state.pending.insert(job_id, job);
+                        }
+                        Err(post_err) => failed.push((job, ProverError::from(post_err))),
+                    }
+                }
+                failed
+            };
+            state.stop_idle_watchdog();
+            failed
         };
-        let payload = to_uint8array(&work_item);
-        worker.post_message(&payload)?;
-        worker.set_onmessage(Some(get_on_msg_callback.as_ref().unchecked_ref()));
-        get_on_msg_callback.forget();
-        Ok(())
+        for (job, err) in failed {
+            (job.on_done)(Err(err));
+        }
     }
+}
 
-    fn execute_constraint(
-        &self,
-        constraint_work_item: ConstraintComputeWorkItem,
-        get_on_msg_callback: Closure<dyn FnMut(MessageEvent)>,
-    ) -> Result<(), JsValue> {
-        debug!(
-            "fragment_offset: {}, concurrency: {}",
-            constraint_work_item.computation_fragment.fragment_offset,
-            self.state.concurrency()
-        );
-        let worker_idx =
-            constraint_work_item.computation_fragment.fragment_offset % self.state.concurrency();
-        debug!("running on worker idx: {}", worker_idx);
-        let worker = self.constraint_worker(worker_idx)?;
-        let payload = to_uint8array(&constraint_work_item);
-        worker.post_message(&payload)?;
-        worker.set_onmessage(Some(get_on_msg_callback.as_ref().unchecked_ref()));
-        get_on_msg_callback.forget();
-        Ok(())
+impl PoolState {
+    /// Picks the next worker round robin, skipping `avoid` when there is another one
+    fn next_worker_idx(&mut self, avoid: Option<usize>) -> usize {
+        let mut worker_idx = self.next_worker;
+        if avoid == Some(worker_idx) && self.concurrency > 1 {
+            worker_idx = (worker_idx + 1) % self.concurrency;
+        }
+        self.next_worker = (worker_idx + 1) % self.concurrency;
+        worker_idx
     }
-}
 
-impl WorkerPool {
-    pub fn run(
-        &self,
-        batch_idx: usize,
-        elements_table: Vec<Vec<FeltWrapper>>,
-        get_on_msg_callback: Closure<dyn FnMut(MessageEvent)>,
-    ) -> Result<(), JsValue> {
-        self.execute(batch_idx, elements_table, get_on_msg_callback)?;
-        Ok(())
+    /// Clears the watchdog when no job is left to check, the next submitted job arms it again
+    fn stop_idle_watchdog(&mut self) {
+        if self.pending.is_empty() {
+            self.watchdog = None;
+        }
     }
 
-    pub fn run_constraint(
-        &self,
-        constraint_work_item: ConstraintComputeWorkItem,
-        get_on_msg_callback: Closure<dyn FnMut(MessageEvent)>,
-    ) -> Result<(), JsValue> {
-        self.execute_constraint(constraint_work_item, get_on_msg_callback)?;
-        Ok(())
+    fn deadline(&self) -> Option<f64> {
+        (self.config.job_timeout_ms > 0).then(|| Date::now() + self.config.job_timeout_ms as f64)
     }
-}
 
-impl PoolState {
-    fn push(&mut self, worker: Worker) {
-        self.workers.push(worker);
+    /// Posts a job to a worker, spawning the workers up to it first
+    fn post_job(&mut self, worker_idx: usize, payload: &Uint8Array) -> Result<(), JsValue> {
+        #[cfg(test)]
+        if self.inline {
+            let (state, payload) = (self.this.clone(), payload.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                let response = run_request(&payload);
+                if let Some(state) = state.upgrade() {
+                    WorkerPool::complete(&state, worker_idx, response);
+                }
+            });
+            return Ok(());
+        }
+        while self.workers.len() <= worker_idx {
+            let worker = self.spawn_worker(self.workers.len())?;
+            self.workers.push(worker);
+        }
+        self.workers[worker_idx].worker.post_message(payload)
     }
 
-    fn push_constraint_worker(&mut self, worker: Worker) {
-        self.constraint_workers.push(worker);
+    /// Replaces a worker whose state can no longer be trusted by a fresh one
+    fn restart_worker(&mut self, worker_idx: usize) -> Result<(), JsValue> {
+        if worker_idx >= self.workers.len() {
+            return Ok(());
+        }
+        let worker = self.spawn_worker(worker_idx)?;
+        let failed = std::mem::replace(&mut self.workers[worker_idx], worker);
+        failed.worker.terminate();
+        Ok(())
     }
 
-    fn concurrency(&self) -> usize {
-        self.workers.len()
+    /// Spawns a worker and shares the attached buffers with it
+    fn spawn_worker(&self, worker_idx: usize) -> Result<PoolWorker, JsValue> {
+        let worker =
+            WorkerPool::spawn(&self.config.pool_worker_url, self.this.clone(), worker_idx)?;
+        for (buffer_id, buffer) in self.attached.iter() {
+            worker
+                .worker
+                .post_message(&attach_message(*buffer_id, buffer))?;
+        }
+        Ok(worker)
     }
 }
+
+/// Control message sharing `buffer` with a worker, jobs are bincode encoded `Uint8Array`s
+fn attach_message(buffer_id: BufferId, buffer: &SharedArrayBuffer) -> JsValue {
+    let message = Object::new();
+    // setting properties on a fresh object cannot fail
+    let _ = Reflect::set(&message, &"attach".into(), &buffer_id.into());
+    let _ = Reflect::set(&message, &"buffer".into(), buffer);
+    message.into()
+}
@@ -1,63 +1,37 @@
-use crate::convert::convert_proof::*;
-use crate::convert::sdk::sdk;
-use crate::pool::WorkerPool;
+use crate::collector::{Abort, Collector};
+use crate::error::ProverError;
+use crate::fri::{build_fri_proof, unsupported_folding_factor, FriLayer};
+use crate::jobs::{JobOutput, WorkerJob};
+use crate::pool::{PoolConfig, WorkerPool};
+use crate::shared::{shared_memory_available, SharedMatrix, SharedMatrixRef};
 use crate::utils::{
-    from_uint8array, set_once_logger, to_uint8array, ComputationFragment, ConstraintComputeResult,
-    ConstraintComputeWorkItem, HashingResult, ProverOutput, ProvingWorkItem, TraceLdeWrapper,
+    elements_from_base, field_extension_of, from_uint8array, set_once_logger, to_base_elements,
+    to_uint8array, unwrap_elements, CompositionCoefficients, ComputationFragment,
+    ConstraintComputeResult, ConstraintComputeWorkItem, FeltWrapper, FriFoldResult,
+    FriFoldWorkItem, HashingWorkItem, LdeResult, LdeWorkItem, ProverMessage, ProverOutput,
+    ProverProgress, ProvingStage, ProvingWorkItem, RowBatch, TraceLdeRows, TraceLdeWindow,
+    WorkPartition,
 };
-use futures::Future;
 use js_sys::Uint8Array;
 use log::{debug, info};
 use miden::{verify, ExecutionTrace, Program, ProgramInputs, ProofOptions};
 use miden_air::{Felt, FieldElement, ProcessorAir, PublicInputs, StarkField};
 use miden_core::ProgramOutputs;
+use miden_proof_format::convert::convert_proof::IntoSdk;
+use miden_proof_format::{sdk, with_field_extension, with_hasher};
 use miden_prover::ExecutionProver;
 use prost::Message;
-use std::{
-    cell::RefCell,
-    pin::Pin,
-    rc::Rc,
-    task::{Context, Poll},
-};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::{console, DedicatedWorkerGlobalScope, MessageEvent};
-use winter_air::{Air, AuxTraceRandElements};
-use winter_crypto::{hashers::Blake2s_256, ByteDigest, MerkleTree};
+use winter_air::{proof::Queries, Air, AuxTraceRandElements};
+use winter_crypto::{ElementHasher, MerkleTree};
+use winter_fri::ProverChannel as _;
 use winter_prover::{
-    ConstraintEvaluationTable, ConstraintEvaluator, Matrix, Prover, ProverChannel, Serializable,
-    StarkDomain, StarkProof, Trace, TraceLde,
+    ConstraintCommitment, ConstraintEvaluationTable, DeepCompositionPoly, Matrix, Prover,
+    ProverChannel, Serializable, StarkDomain, StarkProof, Trace, TraceCommitment, TracePolyTable,
 };
-
-pub struct ResolvableFuture<T> {
-    pub result: Rc<RefCell<Vec<T>>>,
-    pub exepected_size: usize,
-}
-
-impl<T> Future for ResolvableFuture<T> {
-    type Output = ();
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let cur_len = (*self.result).borrow();
-        if cur_len.len() == self.exepected_size {
-            return Poll::Ready(());
-        } else {
-            // wait every second
-            let wait_fn = {
-                let waker = Rc::new(cx.waker().clone());
-                Closure::wrap(Box::new(move || {
-                    waker.as_ref().clone().wake();
-                }) as Box<dyn Fn()>)
-            };
-            let global = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
-            let _ = global.set_timeout_with_callback_and_timeout_and_arguments_0(
-                wait_fn.as_ref().unchecked_ref(),
-                200,
-            );
-            wait_fn.forget();
-            return Poll::Pending;
-        }
-    }
-}
+use winter_utils::{flatten_vector_elements, transpose_slice, Deserializable, SliceReader};
 
 #[wasm_bindgen]
 pub struct MidenProverAsyncWorker {
@@ -66,168 +40,145 @@ pub struct MidenProverAsyncWorker {
     program_inputs: Option<ProgramInputs>,
     program_outputs: Option<ProgramOutputs>,
     proof_options: Option<ProofOptions>,
-    channel: Option<ProverChannel<<ExecutionProver as Prover>::Air, Felt, Blake2s_256<Felt>>>,
+    pub_inputs_bytes: Option<Vec<u8>>,
     trace_polys: Option<Matrix<Felt>>,
-    trace_lde: Option<Matrix<Felt>>,
+    trace_lde: Option<SegmentLde<Felt>>,
     worker_pool: WorkerPool,
-    trace_row_hashes: Rc<RefCell<Vec<(usize, Vec<ByteDigest<32>>)>>>,
-    constraint_evaluations: Rc<RefCell<Vec<ConstraintComputeResult>>>,
-    chunk_size: Option<usize>,
+    trace_columns: Collector<LdeResult>,
+    trace_row_hashes: Collector<(usize, Vec<Vec<u8>>)>,
+    main_trace_row_hashes: Option<Vec<Vec<u8>>>,
+    shared_memory: bool,
+    constraint_evaluations: Collector<ConstraintComputeResult>,
+    fri_foldings: Collector<FriFoldResult>,
+    abort: Abort,
+    progress: Option<Rc<dyn Fn(&ProverProgress)>>,
+    partition: WorkPartition,
     prover: Option<ExecutionProver>,
     air: Option<ProcessorAir>,
 }
 
 #[wasm_bindgen]
 impl MidenProverAsyncWorker {
+    /// Creates the prover from the JSON encoded `PoolConfig` posted by `MidenProver`
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<MidenProverAsyncWorker, JsValue> {
+    pub fn new(pool_config: Option<String>) -> Result<MidenProverAsyncWorker, JsValue> {
+        let pool_config = match pool_config {
+            Some(json) => PoolConfig::from_json(&json)?,
+            None => PoolConfig::default(),
+        };
+        Ok(Self::with_pool_config(pool_config))
+    }
+
+    pub(crate) fn with_pool_config(pool_config: PoolConfig) -> MidenProverAsyncWorker {
+        Self::with_worker_pool(WorkerPool::new(pool_config))
+    }
+
+    fn with_worker_pool(worker_pool: WorkerPool) -> MidenProverAsyncWorker {
         set_once_logger();
-        let worker_pool = WorkerPool::new()?;
-        Ok(Self {
+        let abort = Abort::default();
+        Self {
             trace: None,
             program: None,
             program_inputs: None,
             proof_options: None,
             program_outputs: None,
-            channel: None,
+            pub_inputs_bytes: None,
             trace_polys: None,
             trace_lde: None,
             worker_pool,
-            trace_row_hashes: Rc::new(RefCell::new(Vec::new())),
-            constraint_evaluations: Rc::new(RefCell::new(Vec::new())),
-            chunk_size: None,
+            trace_columns: Collector::new(abort.clone()),
+            trace_row_hashes: Collector::new(abort.clone()),
+            main_trace_row_hashes: None,
+            shared_memory: false,
+            constraint_evaluations: Collector::new(abort.clone()),
+            fri_foldings: Collector::new(abort.clone()),
+            abort,
+            progress: None,
+            partition: WorkPartition::default(),
             prover: None,
             air: None,
-        })
+        }
     }
 
+    /// A prover without any proving state that keeps the worker pool and its spawned workers
     #[wasm_bindgen]
     pub fn reset(&mut self) -> Result<MidenProverAsyncWorker, JsValue> {
-        Ok(Self {
-            trace: None,
-            program: None,
-            program_inputs: None,
-            proof_options: None,
-            program_outputs: None,
-            channel: None,
-            trace_polys: None,
-            trace_lde: None,
-            worker_pool: self.worker_pool.clone(),
-            trace_row_hashes: Rc::new(RefCell::new(Vec::new())),
-            constraint_evaluations: Rc::new(RefCell::new(Vec::new())),
-            chunk_size: None,
-            prover: None,
-            air: None,
-        })
+        Ok(Self::with_worker_pool(self.worker_pool.clone()))
     }
 
-    async fn prove(&mut self, proving_work_item: ProvingWorkItem) -> Result<ProverOutput, JsValue> {
-        console::time_with_label("preparing_inputs");
-        let miden_program = sdk::MidenProgram::decode(&proving_work_item.program[..])
-            .expect("Cannot decode miden program");
-        let miden_program_inputs =
-            sdk::MidenProgramInputs::decode(&proving_work_item.program_inputs[..])
-                .expect("Cannot decode miden program inputs");
-        let proof_options = sdk::ProofOptions::decode(&proving_work_item.proof_options[..])
-            .expect("Cannot decode proof options");
+    /// Handle a cancellation is raised on to abort the proof
+    pub(crate) fn abort_handle(&self) -> Abort {
+        self.abort.clone()
+    }
+
+    pub(crate) fn set_progress_sink(&mut self, sink: Rc<dyn Fn(&ProverProgress)>) {
+        self.progress = Some(sink);
+    }
+
+    /// Stops the hashing and constraint workers, the next proof spawns new ones
+    pub(crate) fn terminate_workers(&self) {
+        self.worker_pool.terminate();
+    }
+
+    fn start_stage(&self, stage: ProvingStage) -> Result<(), ProverError> {
+        if let Some(err) = self.abort.take() {
+            return Err(err);
+        }
+        report_progress(&self.progress, stage, 0, 1);
+        console::time_with_label(stage.as_str());
+        Ok(())
+    }
 
-        self.program = Some(miden_program.into());
-        self.program_inputs = Some(miden_program_inputs.into());
-        self.proof_options = Some(proof_options.into());
+    fn prepare_inputs(&mut self, proving_work_item: &ProvingWorkItem) -> Result<(), ProverError> {
+        self.start_stage(ProvingStage::PreparingInputs)?;
+        proving_work_item.partition.validate()?;
+        self.partition = proving_work_item.partition;
+        let (program, program_inputs, proof_options) = proving_work_item.decode()?;
+        self.program = Some(program);
+        self.program_inputs = Some(program_inputs);
+        self.proof_options = Some(proof_options);
         console::time_end_with_label("preparing_inputs");
-        console::time_with_label("generating_trace");
+        Ok(())
+    }
+
+    async fn prove(
+        &mut self,
+        proving_work_item: ProvingWorkItem,
+    ) -> Result<ProverOutput, ProverError> {
+        self.prepare_inputs(&proving_work_item)?;
+        self.shared_memory = shared_memory_available();
+        if !self.shared_memory {
+            info!("Shared memory is not available, trace rows are copied to the workers");
+        }
+        self.start_stage(ProvingStage::GeneratingTrace)?;
 
         self.build_execution_trace()?;
         console::time_end_with_label("generating_trace");
-        console::time_with_label("prove_program_stage1");
+        self.start_stage(ProvingStage::ProveProgramStage1)?;
 
         // execute program and generate proof
-        self.prove_stage_1()?;
+        self.prove_stage_1().await?;
         console::time_end_with_label("prove_program_stage1");
-        console::time_with_label("prove_trace_hashes");
-        self.prove_trace_hashes(proving_work_item.chunk_size)
-            .await?;
+        self.start_stage(ProvingStage::ProveTraceHashes)?;
+        self.prove_trace_hashes().await?;
         console::time_end_with_label("prove_trace_hashes");
-        // build Merkle tree out of hashed rows
-        let mut trace_row_hashes = vec![];
-
-        self.trace_row_hashes.borrow_mut().sort_by_key(|v| v.0);
 
-        // Append the vecs to the result vec in order
-        for (_, vec) in self.trace_row_hashes.borrow().iter() {
-            trace_row_hashes.extend(vec.clone());
-        }
-
-        let main_trace_tree: MerkleTree<Blake2s_256<Felt>> =
-            MerkleTree::new(trace_row_hashes).expect("failed to construct trace Merkle tree");
-        debug!("Merkle root: {:x}", main_trace_tree.root());
-
-        let prover = ExecutionProver::new(
-            self.proof_options.clone().unwrap(),
-            self.program_inputs.clone().unwrap().stack_init().to_vec(),
-            self.program_outputs.clone().unwrap(),
-        );
-        console::time_with_label("prove_final_stage");
-        let proof = self.prove_epilogue(&prover, main_trace_tree).await?;
+        self.start_stage(ProvingStage::ProveFinalStage)?;
+        let hash_fn = self.proof_options.as_ref().unwrap().0.hash_fn();
+        let field_extension = self.proof_options.as_ref().unwrap().0.field_extension();
+        let proof = with_hasher!(hash_fn, |H| {
+            with_field_extension!(field_extension, |E| { self.prove_epilogue::<H, E>().await })
+        })?;
         console::time_end_with_label("prove_final_stage");
-
-        let pub_inputs = PublicInputs::new(
-            self.program.clone().unwrap().hash(),
-            self.program_inputs.clone().unwrap().stack_init().to_vec(),
-            self.program_outputs.clone().unwrap(),
-        );
-        let air = ProcessorAir::new(
-            proof.get_trace_info(),
-            pub_inputs.clone(),
-            proof.options().clone(),
-        );
-
-        let stack_inputs: Vec<u64> = self
-            .program_inputs
-            .clone()
-            .unwrap()
-            .stack_init()
-            .iter()
-            // for whatever reason miden reverses the stack
-            .rev()
-            .map(|e| e.as_int())
-            .collect();
-
-        console::time_with_label("verify_program");
-        verify(
-            self.program.clone().unwrap().hash(),
-            &stack_inputs[..],
-            &self.program_outputs.clone().unwrap(),
-            proof.clone(),
-        )
-        .map_err(|e| format!("Could not verify proof due to {}", e))?;
-        console::time_end_with_label("verify_program");
-
-        info!(
-            "proof size: {:.1} KB",
-            proof.to_bytes().len() as f64 / 1024f64
-        );
-        let sdk_proof: sdk::StarkProof = sdk::StarkProof::into_sdk(proof, &air);
-        info!(
-            "SDK Proof size: {:.1} KB",
-            sdk_proof.encode_to_vec().len() as f64 / 1024f64
-        );
-        let sdk_outputs: sdk::MidenProgramOutputs = self.program_outputs.clone().unwrap().into();
-        let sdk_pub_inputs: sdk::MidenPublicInputs = pub_inputs.into();
-        let js_output = ProverOutput {
-            proof: sdk_proof.encode_to_vec(),
-            program_outputs: sdk_outputs.encode_to_vec(),
-            public_inputs: sdk_pub_inputs.encode_to_vec(),
-        };
-        Ok(js_output)
+        self.finish_proof(proof)
     }
 
-    fn build_execution_trace(&mut self) -> Result<(), JsValue> {
+    fn build_execution_trace(&mut self) -> Result<(), ProverError> {
         let trace = miden_processor::execute(
             &self.program.clone().unwrap(),
             &self.program_inputs.clone().unwrap(),
         )
-        .map_err(|_| "Could not generate miden trace")?;
+        .map_err(|e| ProverError::Execution(format!("Could not generate miden trace: {:?}", e)))?;
         self.program_outputs = Some(trace.program_outputs().clone());
         self.trace = Some(trace);
         Ok(())
@@ -235,7 +186,7 @@ impl MidenProverAsyncWorker {
 
     // start the proving process, generate the main trace
     // before commitment will be dispatched to workers
-    fn prove_stage_1(&mut self) -> Result<(), JsValue> {
+    async fn prove_stage_1(&mut self) -> Result<(), ProverError> {
         self.prover = Some(ExecutionProver::new(
             self.proof_options.clone().unwrap(),
             self.program_inputs.clone().unwrap().stack_init().to_vec(),
@@ -248,6 +199,7 @@ impl MidenProverAsyncWorker {
         let pub_inputs = self.prover.as_ref().unwrap().get_pub_inputs(&trace);
         let mut pub_inputs_bytes = Vec::new();
         pub_inputs.write_into(&mut pub_inputs_bytes);
+        self.pub_inputs_bytes = Some(pub_inputs_bytes);
 
         // create an instance of AIR for the provided parameters. this takes a generic description
         // of the computation (provided via AIR type), and creates a description of a specific
@@ -258,181 +210,616 @@ impl MidenProverAsyncWorker {
             self.proof_options.clone().unwrap().0,
         ));
 
-        // create a channel which is used to simulate interaction between the prover and the
-        // verifier; the channel will be used to commit to values and to draw randomness that
-        // should come from the verifier.
-        self.channel = Some(ProverChannel::<
-            <ExecutionProver as Prover>::Air,
-            Felt,
-            Blake2s_256<Felt>,
-        >::new(self.air.clone().unwrap(), pub_inputs_bytes));
-
         // start building the trace commitments
         let domain = StarkDomain::new(&self.air.clone().unwrap());
-        let main_trace = trace.main_segment();
-        let trace_polys = main_trace.interpolate_columns();
-        self.trace_lde = Some(trace_polys.evaluate_columns_over(&domain));
+        let (trace_polys, trace_lde) = self
+            .extend_trace_columns(
+                trace.main_segment(),
+                &domain,
+                ProvingStage::ProveProgramStage1,
+            )
+            .await?;
+        self.trace_lde = Some(trace_lde);
         self.trace_polys = Some(trace_polys);
 
         Ok(())
     }
 
-    async fn prove_trace_hashes(&mut self, chunk_size: usize) -> Result<(), JsValue> {
+    /// Interpolates the trace columns and evaluates them over the LDE domain on the pool, each
+    /// worker extending a contiguous range of columns.
+    ///
+    /// When workers can share memory they write the extended columns straight into a shared
+    /// LDE segment, which the later stages read in place.
+    async fn extend_trace_columns<E: FieldElement<BaseField = Felt>>(
+        &self,
+        trace: &Matrix<E>,
+        domain: &StarkDomain<Felt>,
+        stage: ProvingStage,
+    ) -> Result<(Matrix<E>, SegmentLde<E>), ProverError> {
+        let num_cols = trace.num_cols();
+        let shared_lde = if self.shared_memory {
+            Some(SharedMatrix::new(
+                &self.worker_pool,
+                domain.lde_domain_size(),
+                num_cols * E::EXTENSION_DEGREE,
+            )?)
+        } else {
+            None
+        };
+        let columns_per_job =
+            (num_cols + self.worker_pool.concurrency() - 1) / self.worker_pool.concurrency();
+        let num_jobs = (num_cols + columns_per_job - 1) / columns_per_job;
+        self.trace_columns.clear();
+        for first_column in (0..num_cols).step_by(columns_per_job) {
+            let last_column = usize::min(first_column + columns_per_job, num_cols);
+            let columns = (first_column..last_column)
+                .map(|col_idx| to_base_elements(trace.get_column(col_idx)))
+                .collect();
+            let job = WorkerJob::ExtendColumns(LdeWorkItem {
+                first_column,
+                field_extension: field_extension_of::<E>(),
+                columns,
+                domain_offset: FeltWrapper(domain.offset()),
+                blowup_factor: domain.trace_to_lde_blowup(),
+                lde: shared_lde.as_ref().map(SharedMatrix::reference),
+            });
+            self.worker_pool
+                .submit(job, self.on_extended_columns(num_jobs, stage))?;
+        }
+        self.trace_columns.collect(num_jobs).await?;
+
+        let mut results = self.trace_columns.take();
+        results.sort_by_key(|result| result.first_column);
+        let mut polys = Vec::with_capacity(num_cols);
+        let mut lde = Vec::with_capacity(num_cols);
+        for result in results {
+            for poly in result.polys {
+                polys.push(read_worker_elements(&poly)?);
+            }
+            for column in result.lde {
+                lde.push(read_worker_elements(&column)?);
+            }
+        }
+        let lde = match shared_lde {
+            Some(shared_lde) => SegmentLde::Shared(shared_lde),
+            None => SegmentLde::Copied(Matrix::new(lde)),
+        };
+        Ok((Matrix::new(polys), lde))
+    }
+
+    async fn prove_trace_hashes(&mut self) -> Result<(), ProverError> {
         let trace_lde = self.trace_lde.as_ref().unwrap();
+        let hashes = self
+            .hash_rows(trace_lde.rows(), ProvingStage::ProveTraceHashes)
+            .await?;
+        self.main_trace_row_hashes = Some(hashes);
+        Ok(())
+    }
 
-        debug!("trace_lde: {:?}", trace_lde.num_rows());
-        self.trace_row_hashes = Rc::new(RefCell::new(vec![]));
-        self.chunk_size = Some(chunk_size);
-        // this is fine since trace length is a power of 2
-        let num_of_batches = trace_lde.num_rows() / chunk_size;
-        let mut dispatched_idx = 0;
+    /// Hashes the rows on the pool, in batches sized by the work partition, and returns the
+    /// serialized digests in row order.
+    ///
+    /// Rows of a shared matrix are hashed in place by the workers, other rows are copied into
+    /// the jobs.
+    async fn hash_rows(
+        &self,
+        rows: RowSource<'_>,
+        stage: ProvingStage,
+    ) -> Result<Vec<Vec<u8>>, ProverError> {
+        let (num_rows, num_cols) = rows.dimensions();
+        let chunk_size = self
+            .partition
+            .chunk_size(num_rows, self.worker_pool.concurrency())?;
+        self.trace_row_hashes.clear();
+        let num_of_batches = (num_rows + chunk_size - 1) / chunk_size;
+        let hash_fn = self.proof_options.as_ref().unwrap().0.hash_fn().into();
 
         for i in 0..num_of_batches {
-            let mut batch = vec![];
-            for _ in 0..chunk_size {
-                let mut row = vec![Felt::ZERO; trace_lde.num_cols()];
-                trace_lde.read_row_into(dispatched_idx, &mut row);
-                let converted = row.iter().map(|f| f.into()).collect();
-                batch.push(converted);
-                dispatched_idx += 1;
-            }
-            self.worker_pool.run(i, batch, self.get_on_msg_callback())?;
+            let batch_rows = i * chunk_size..usize::min((i + 1) * chunk_size, num_rows);
+            let batch = match &rows {
+                RowSource::Shared(matrix) => RowBatch::Shared {
+                    matrix: *matrix,
+                    first_row: batch_rows.start,
+                    num_rows: batch_rows.len(),
+                },
+                RowSource::Copied { read_row, .. } => {
+                    let mut batch = Vec::with_capacity(batch_rows.len());
+                    let mut row = vec![Felt::ZERO; num_cols];
+                    for row_idx in batch_rows {
+                        read_row(row_idx, &mut row);
+                        batch.push(row.iter().map(FeltWrapper::from).collect());
+                    }
+                    RowBatch::Copied(batch)
+                }
+            };
+            let job = WorkerJob::HashRows(HashingWorkItem {
+                rows: batch,
+                batch_idx: i,
+                hash_fn,
+            });
+            self.worker_pool
+                .submit(job, self.on_row_hashes(num_of_batches, stage))?;
         }
         // await all messages to process
-        let fut = ResolvableFuture {
-            result: self.trace_row_hashes.clone(),
-            exepected_size: num_of_batches,
-        };
+        self.trace_row_hashes.collect(num_of_batches).await?;
 
-        fut.await;
+        let mut batches = self.trace_row_hashes.take();
+        batches.sort_by_key(|v| v.0);
+        let hashes: Vec<_> = batches.into_iter().flat_map(|(_, hashes)| hashes).collect();
+        if hashes.len() != num_rows {
+            return Err(ProverError::Worker(format!(
+                "expected {} row hashes from workers, got {}",
+                num_rows,
+                hashes.len()
+            )));
+        }
+        Ok(hashes)
+    }
 
-        Ok(())
+    /// Builds a Merkle tree over the rows, hashing them on the pool
+    async fn commit_to_rows<H: ElementHasher<BaseField = Felt>>(
+        &self,
+        rows: RowSource<'_>,
+        stage: ProvingStage,
+    ) -> Result<MerkleTree<H>, ProverError> {
+        let hashes = self.hash_rows(rows, stage).await?;
+        build_merkle_tree::<H>(&hashes)
     }
 
-    async fn prove_epilogue(
-        &mut self,
-        prover: &ExecutionProver,
-        main_trace_tree: MerkleTree<Blake2s_256<Felt>>,
-    ) -> Result<StarkProof, JsValue> {
-        let mut channel = self.channel.take().unwrap();
-        let air = self.air.as_ref().unwrap();
-        let domain = StarkDomain::new(air);
-        let main_trace_lde = self.trace_lde.take().unwrap();
-        let main_trace_polys = self.trace_polys.take().unwrap();
+    /// Copies the columns of a matrix the proving worker computed into a buffer shared with the
+    /// workers, `None` when workers cannot share memory
+    fn share_columns<E: FieldElement<BaseField = Felt>>(
+        &self,
+        num_rows: usize,
+        columns: &[&[E]],
+    ) -> Result<Option<SharedMatrix>, ProverError> {
+        if !self.shared_memory {
+            return Ok(None);
+        }
+        let matrix = SharedMatrix::new(
+            &self.worker_pool,
+            num_rows,
+            columns.len() * E::EXTENSION_DEGREE,
+        )?;
+        let view = matrix.view()?;
+        for (col_idx, column) in columns.iter().enumerate() {
+            view.write_elements(col_idx * num_rows, column)?;
+        }
+        Ok(Some(matrix))
+    }
+
+    // replays `Prover::prove` after the main trace commitment, with the trace extension, the
+    // constraint evaluation, the FRI folding and every Merkle leaf hashed on the worker pool;
+    // the composition and DEEP composition polynomials are built and evaluated over the LDE
+    // domain here, winterfell only exposes them as whole polynomials
+    async fn prove_epilogue<H, E>(&mut self) -> Result<StarkProof, ProverError>
+    where
+        H: ElementHasher<BaseField = Felt>,
+        E: FieldElement<BaseField = Felt>,
+    {
+        // build Merkle tree out of hashed rows
+        let main_trace_tree = build_merkle_tree::<H>(&self.main_trace_row_hashes.take().unwrap())?;
+        debug!("Merkle root: {:?}", main_trace_tree.root());
+
+        // create a channel which is used to simulate interaction between the prover and the
+        // verifier; the channel will be used to commit to values and to draw randomness that
+        // should come from the verifier.
+        let mut channel = ProverChannel::<<ExecutionProver as Prover>::Air, E, H>::new(
+            self.air.clone().unwrap(),
+            self.pub_inputs_bytes.take().unwrap(),
+        );
+        let air = self.air.clone().unwrap();
+        let domain = StarkDomain::new(&air);
+        channel.commit_trace(*main_trace_tree.root());
+        let mut trace_commitment = TraceLdeCommitment::<H, E>::new(
+            self.trace_lde.take().unwrap(),
+            main_trace_tree,
+            domain.trace_to_lde_blowup(),
+        );
+        let mut trace_polys = TracePolyTable::<E>::new(self.trace_polys.take().unwrap());
         let mut trace = self.trace.take().unwrap();
-        let (trace_polys, trace_commitment, aux_trace_rand_elements) = prover
-            .commit_to_trace_and_validate(
-                &air,
-                &mut channel,
-                main_trace_tree,
-                main_trace_lde,
-                main_trace_polys,
-                &mut trace,
-            )
-            .map_err(|err| format!("Cannot run commit_to_trace_and_validate: {:?}", err))?;
 
-        console::time_with_label("constraint_evaluations");
+        // 1 ----- extend and commit to the auxiliary trace segments -----------------------------
+        self.start_stage(ProvingStage::CommitAuxTrace)?;
+        let mut aux_trace_segments = Vec::new();
+        let mut aux_trace_rand_elements = AuxTraceRandElements::new();
+        for i in 0..trace.layout().num_aux_segments() {
+            let rand_elements = channel.get_aux_trace_segment_rand_elements(i);
+            let aux_segment = trace
+                .build_aux_segment(&aux_trace_segments, &rand_elements)
+                .ok_or_else(|| {
+                    ProverError::Proving(format!("Cannot build auxiliary trace segment {}", i))
+                })?;
+            let (aux_segment_polys, aux_segment_lde) = self
+                .extend_trace_columns(&aux_segment, &domain, ProvingStage::CommitAuxTrace)
+                .await?;
+            let aux_segment_tree = self
+                .commit_to_rows::<H>(aux_segment_lde.rows(), ProvingStage::CommitAuxTrace)
+                .await?;
+            channel.commit_trace(*aux_segment_tree.root());
+            trace_commitment.add_segment(aux_segment_lde, aux_segment_tree)?;
+            trace_polys.add_aux_segment(aux_segment_polys);
+            aux_trace_rand_elements.add_segment_elements(rand_elements);
+            aux_trace_segments.push(aux_segment);
+        }
+        console::time_end_with_label("commit_aux_trace");
+
+        #[cfg(debug_assertions)]
+        trace.validate(&air, &aux_trace_segments, &aux_trace_rand_elements);
+
+        // 2 ----- evaluate constraints ----------------------------------------------------------
+        self.start_stage(ProvingStage::ConstraintEvaluations)?;
         let constraint_evaluations = self
             .evaluate_constraints(
                 &mut channel,
-                trace_commitment.trace_table(),
-                aux_trace_rand_elements.clone(),
+                &trace_commitment,
+                aux_trace_rand_elements,
                 &domain,
             )
             .await?;
         console::time_end_with_label("constraint_evaluations");
-        Ok(prover
-            .prove_after_constraint_eval(
-                &air,
-                channel,
-                constraint_evaluations,
-                trace_polys,
-                trace_commitment,
-            )
-            .map_err(|err| format!("Cannot run prove_after_build_trace_commitment: {:?}", err))?)
+
+        // 3 ----- commit to the constraint composition polynomial -------------------------------
+        self.start_stage(ProvingStage::CommitConstraints)?;
+        let composition_poly = constraint_evaluations.into_poly().map_err(|err| {
+            ProverError::Proving(format!("Cannot build composition polynomial: {:?}", err))
+        })?;
+        let composed_evaluations = composition_poly.evaluate(&domain);
+        let composed_columns: Vec<&[E]> = composed_evaluations.columns().collect();
+        let shared_composed_evaluations =
+            self.share_columns(composed_evaluations.num_rows(), &composed_columns)?;
+        let composed_rows = match &shared_composed_evaluations {
+            Some(shared) => RowSource::Shared(shared.reference()),
+            None => RowSource::matrix(&composed_evaluations),
+        };
+        let constraint_tree = self
+            .commit_to_rows::<H>(composed_rows, ProvingStage::CommitConstraints)
+            .await?;
+        // the workers no longer read the composed evaluations
+        drop(shared_composed_evaluations);
+        let constraint_commitment =
+            ConstraintCommitment::new(composed_evaluations, constraint_tree);
+        channel.commit_constraints(constraint_commitment.root());
+        console::time_end_with_label("commit_constraints");
+
+        // 4 ----- build DEEP composition polynomial ---------------------------------------------
+        let z = channel.get_ood_point();
+        let ood_trace_states = trace_polys.get_ood_frame(z);
+        channel.send_ood_trace_states(&ood_trace_states);
+        let ood_evaluations = composition_poly.evaluate_at(z);
+        channel.send_ood_constraint_evaluations(&ood_evaluations);
+
+        let deep_coefficients = channel.get_deep_composition_coeffs();
+        let mut deep_composition_poly = DeepCompositionPoly::new(&air, z, deep_coefficients);
+        deep_composition_poly.add_trace_polys(trace_polys, ood_trace_states);
+        deep_composition_poly.add_composition_poly(composition_poly, ood_evaluations);
+        deep_composition_poly.adjust_degree();
+        let deep_evaluations = deep_composition_poly.evaluate(&domain);
+
+        // 5 ----- compute FRI layers for the composition polynomial -----------------------------
+        self.start_stage(ProvingStage::BuildFriLayers)?;
+        let fri_options = air.options().to_fri_options();
+        let fri_layers = self
+            .build_fri_layers::<H, E>(&mut channel, deep_evaluations)
+            .await?;
+        console::time_end_with_label("build_fri_layers");
+
+        // 6 ----- determine query positions and build the proof ---------------------------------
+        let position_nonce = channel.grind_query_seed();
+        let query_positions = channel.get_query_positions();
+        let fri_proof =
+            build_fri_proof(&fri_layers, &query_positions, fri_options.folding_factor())?;
+        let trace_queries = trace_commitment.query(&query_positions)?;
+        let constraint_queries = constraint_commitment.query(&query_positions);
+        Ok(channel.build_proof(trace_queries, constraint_queries, fri_proof, position_nonce))
+    }
+
+    /// Commits to the DEEP composition evaluations and their successive foldings, the same as
+    /// `FriProver::build_layers` with the layer leaves hashed on the pool
+    async fn build_fri_layers<H, E>(
+        &self,
+        channel: &mut ProverChannel<<ExecutionProver as Prover>::Air, E, H>,
+        evaluations: Vec<E>,
+    ) -> Result<Vec<FriLayer<H, E>>, ProverError>
+    where
+        H: ElementHasher<BaseField = Felt>,
+        E: FieldElement<BaseField = Felt>,
+    {
+        match self
+            .air
+            .as_ref()
+            .unwrap()
+            .options()
+            .to_fri_options()
+            .folding_factor()
+        {
+            4 => {
+                self.build_fri_layers_with::<H, E, 4>(channel, evaluations)
+                    .await
+            }
+            8 => {
+                self.build_fri_layers_with::<H, E, 8>(channel, evaluations)
+                    .await
+            }
+            16 => {
+                self.build_fri_layers_with::<H, E, 16>(channel, evaluations)
+                    .await
+            }
+            factor => Err(unsupported_folding_factor(factor)),
+        }
+    }
+
+    async fn build_fri_layers_with<H, E, const N: usize>(
+        &self,
+        channel: &mut ProverChannel<<ExecutionProver as Prover>::Air, E, H>,
+        mut evaluations: Vec<E>,
+    ) -> Result<Vec<FriLayer<H, E>>, ProverError>
+    where
+        H: ElementHasher<BaseField = Felt>,
+        E: FieldElement<BaseField = Felt>,
+    {
+        let fri_options = self.air.as_ref().unwrap().options().to_fri_options();
+        // + 1 is for the remainder
+        let num_layers = fri_options.num_fri_layers(evaluations.len()) + 1;
+        let domain_offset = fri_options.domain_offset();
+        // a layer stored column by column is the layer transposed into rows of N values, the
+        // first layer is copied into a shared buffer and the workers fold the next ones in place
+        let layer_rows = evaluations.len() / N;
+        let layer_columns: Vec<&[E]> = evaluations.chunks(layer_rows).collect();
+        let mut shared_layer = self.share_columns(layer_rows, &layer_columns)?;
+        let mut layers = Vec::with_capacity(num_layers);
+        for layer_idx in 0..num_layers {
+            // the last layer is the remainder, its folding is never read
+            let fold = layer_idx + 1 < num_layers;
+            let layer = self
+                .build_fri_layer::<H, E, N>(
+                    channel,
+                    &mut evaluations,
+                    &mut shared_layer,
+                    domain_offset,
+                    fold,
+                )
+                .await?;
+            layers.push(layer);
+        }
+        Ok(layers)
+    }
+
+    async fn build_fri_layer<H, E, const N: usize>(
+        &self,
+        channel: &mut ProverChannel<<ExecutionProver as Prover>::Air, E, H>,
+        evaluations: &mut Vec<E>,
+        shared_layer: &mut Option<SharedMatrix>,
+        domain_offset: Felt,
+        fold: bool,
+    ) -> Result<FriLayer<H, E>, ProverError>
+    where
+        H: ElementHasher<BaseField = Felt>,
+        E: FieldElement<BaseField = Felt>,
+    {
+        // commit to the evaluations transposed into rows of N values, so that N values can be
+        // opened with a single Merkle authentication path
+        let transposed_evaluations = transpose_slice::<_, N>(evaluations);
+        let rows = match shared_layer {
+            Some(shared) => RowSource::Shared(shared.reference()),
+            None => RowSource::Copied {
+                num_rows: transposed_evaluations.len(),
+                num_cols: N * E::EXTENSION_DEGREE,
+                read_row: Box::new(|row_idx, row| {
+                    row.copy_from_slice(E::as_base_elements(&transposed_evaluations[row_idx]))
+                }),
+            },
+        };
+        let tree = self
+            .commit_to_rows::<H>(rows, ProvingStage::BuildFriLayers)
+            .await?;
+        channel.commit_fri_layer(*tree.root());
+
+        // reduce the degree of the evaluations by N with a pseudo-random coefficient, the
+        // channel draws it for the remainder as well
+        let alpha = channel.draw_fri_alpha();
+        if fold {
+            let (folded, next_layer) = self
+                .fold_fri_layer(
+                    &transposed_evaluations,
+                    shared_layer.as_ref(),
+                    domain_offset,
+                    alpha,
+                )
+                .await?;
+            *evaluations = folded;
+            *shared_layer = next_layer;
+        }
+
+        Ok(FriLayer {
+            tree,
+            evaluations: flatten_vector_elements(transposed_evaluations),
+        })
     }
 
-    async fn evaluate_constraints<'a>(
+    /// Folds the transposed rows of a FRI layer into the next layer on the pool, the same as
+    /// `apply_drp` does for the whole layer.
+    ///
+    /// The rows of a shared layer are folded in place into a shared next layer, which is
+    /// returned with the folded evaluations.
+    async fn fold_fri_layer<E: FieldElement<BaseField = Felt>, const N: usize>(
+        &self,
+        rows: &[[E; N]],
+        shared_layer: Option<&SharedMatrix>,
+        domain_offset: Felt,
+        alpha: E,
+    ) -> Result<(Vec<E>, Option<SharedMatrix>), ProverError> {
+        let next_layer = match shared_layer {
+            Some(_) if rows.len() % N == 0 => Some(SharedMatrix::new(
+                &self.worker_pool,
+                rows.len() / N,
+                N * E::EXTENSION_DEGREE,
+            )?),
+            Some(_) => {
+                return Err(ProverError::Proving(format!(
+                    "FRI layer of {} evaluations does not split into rows of {}",
+                    rows.len(),
+                    N
+                )))
+            }
+            None => None,
+        };
+        let chunk_size = self
+            .partition
+            .chunk_size(rows.len(), self.worker_pool.concurrency())?;
+        let num_jobs = (rows.len() + chunk_size - 1) / chunk_size;
+        self.fri_foldings.clear();
+        for first_row in (0..rows.len()).step_by(chunk_size) {
+            let num_rows = usize::min(chunk_size, rows.len() - first_row);
+            let batch = match shared_layer {
+                Some(shared) => RowBatch::Shared {
+                    matrix: shared.reference(),
+                    first_row,
+                    num_rows,
+                },
+                None => RowBatch::Copied(
+                    rows[first_row..first_row + num_rows]
+                        .iter()
+                        .map(|row| to_base_elements(row))
+                        .collect(),
+                ),
+            };
+            let job = WorkerJob::FoldFriLayer(FriFoldWorkItem {
+                rows: batch,
+                first_row,
+                layer_rows: rows.len(),
+                folding_factor: N,
+                field_extension: field_extension_of::<E>(),
+                domain_offset: FeltWrapper(domain_offset),
+                alpha: to_base_elements(&[alpha]),
+                next_layer: next_layer.as_ref().map(SharedMatrix::reference),
+            });
+            self.worker_pool
+                .submit(job, self.on_folded_fri_layer(num_jobs))?;
+        }
+        self.fri_foldings.collect(num_jobs).await?;
+
+        let mut results = self.fri_foldings.take();
+        let folded: Vec<E> = match &next_layer {
+            // the proving worker keeps the layer evaluations to open them once queried
+            Some(next_layer) => next_layer.view()?.read_elements(0, rows.len())?,
+            None => {
+                results.sort_by_key(|result| result.first_row);
+                let mut folded = Vec::with_capacity(rows.len());
+                for result in results {
+                    folded.extend(read_worker_elements::<E>(&result.evaluations)?);
+                }
+                folded
+            }
+        };
+        if folded.len() != rows.len() {
+            return Err(ProverError::Worker(format!(
+                "expected {} folded FRI evaluations from workers, got {}",
+                rows.len(),
+                folded.len()
+            )));
+        }
+        Ok((folded, next_layer))
+    }
+
+    async fn evaluate_constraints<'a, H, E>(
         &'a self,
-        channel: &mut ProverChannel<<ExecutionProver as Prover>::Air, Felt, Blake2s_256<Felt>>,
-        trace_table: &TraceLde<Felt>,
-        aux_trace_rand_elements: AuxTraceRandElements<Felt>,
+        channel: &mut ProverChannel<<ExecutionProver as Prover>::Air, E, H>,
+        trace_commitment: &TraceLdeCommitment<H, E>,
+        aux_trace_rand_elements: AuxTraceRandElements<E>,
         domain: &'a StarkDomain<Felt>,
-    ) -> Result<ConstraintEvaluationTable<Felt>, JsValue> {
+    ) -> Result<ConstraintEvaluationTable<E>, ProverError>
+    where
+        H: ElementHasher<BaseField = Felt>,
+        E: FieldElement<BaseField = Felt>,
+    {
         let air = self.air.as_ref().unwrap();
         // 2 ----- evaluate constraints -----------------------------------------------------------
         // evaluate constraints specified by the AIR over the constraint evaluation domain, and
         // compute random linear combinations of these evaluations using coefficients drawn from
-        // the channel; this step evaluates only constraint numerators, thus, only constraints with
-        // identical denominators are merged together. the results are saved into a constraint
-        // evaluation table where each column contains merged evaluations of constraints with
-        // identical denominators.
+        // the channel; the workers merge every constraint into a single column of numerators over
+        // the transition divisor, see `evaluate_fragment`, which is saved into a constraint
+        // evaluation table.
         let constraint_coeffs = channel.get_constraint_composition_coeffs();
-        // build a list of constraint divisors; currently, all transition constraints have the same
-        // divisor which we put at the front of the list; boundary constraint divisors are appended
-        // after that
-        let evaluator: ConstraintEvaluator<_, Felt> = ConstraintEvaluator::new(
-            air,
-            aux_trace_rand_elements.clone(),
-            constraint_coeffs.clone(),
-        );
-        // build a list of constraint divisors; currently, all transition constraints have the same
-        // divisor which we put at the front of the list; boundary constraint divisors are appended
-        // after that
-        let mut divisors = vec![evaluator.transition_constraints.divisor().clone()];
-        divisors.append(&mut evaluator.boundary_constraints.get_divisors());
+        let transition_constraints = air.get_transition_constraints(&constraint_coeffs.transition);
+        let divisors = vec![transition_constraints.divisor().clone()];
 
         // allocate space for constraint evaluations; when we are in debug mode, we also allocate
         // memory to hold all transition constraint evaluations (before they are merged into a
         // single value) so that we can check their degrees later
         #[cfg(not(debug_assertions))]
-        let mut evaluation_table_workers = ConstraintEvaluationTable::<Felt>::new(domain, divisors);
+        let mut evaluation_table_workers = ConstraintEvaluationTable::<E>::new(domain, divisors);
         #[cfg(debug_assertions)]
-        let mut evaluation_table_workers = ConstraintEvaluationTable::<Felt>::new(
-            &domain,
-            divisors,
-            &evaluator.transition_constraints,
-        );
-        let frag_num = 8;
+        let mut evaluation_table_workers =
+            ConstraintEvaluationTable::<E>::new(&domain, divisors, &transition_constraints);
+        let frag_num = self
+            .partition
+            .num_fragments(domain.ce_domain_size(), self.worker_pool.concurrency())?;
+        debug!("evaluating constraints in {} fragments", frag_num);
         let pub_inputs = PublicInputs::new(
             self.program.clone().unwrap().hash(),
             self.program_inputs.clone().unwrap().stack_init().to_vec(),
             self.program_outputs.clone().unwrap(),
         );
         let proof_options = self.proof_options.as_ref().unwrap().0.clone();
-        let trace_lde_wrapper = TraceLdeWrapper {
-            trace_lde: trace_table.clone(),
+        let aux_rand_elements: Vec<Vec<FeltWrapper>> = aux_trace_rand_elements
+            .0
+            .iter()
+            .map(|elements| to_base_elements(elements))
+            .collect();
+        let constraint_coeffs = CompositionCoefficients::new(&constraint_coeffs);
+        // each fragment only receives the LDE rows its frames read, see `TraceLdeWindow`
+        let fragment_rows = domain.ce_domain_size() / frag_num;
+        let lde_shift = domain.ce_to_lde_blowup().trailing_zeros();
+        let window_rows = (fragment_rows << lde_shift) + domain.trace_to_lde_blowup();
+        // with shared memory the workers read their windows in place from the shared trace
+        // segments and write their evaluations straight into a shared table
+        let shared_evaluations = if self.shared_memory {
+            Some(SharedMatrix::new(
+                &self.worker_pool,
+                domain.ce_domain_size(),
+                E::EXTENSION_DEGREE,
+            )?)
+        } else {
+            None
         };
-        let serialized_trace_wrapper = bincode::serialize(&trace_lde_wrapper).unwrap();
         for i in 0..frag_num {
+            let first_row = (i * fragment_rows) << lde_shift;
+            let trace_rows = trace_commitment.fragment_rows(first_row, window_rows);
             let constraint_work_item = ConstraintComputeWorkItem {
                 trace_info: air.trace_info().clone(),
                 public_inputs: pub_inputs.clone(),
                 proof_options: proof_options.clone(),
-                trace_lde_wrapper: serialized_trace_wrapper.clone(),
+                field_extension: field_extension_of::<E>(),
+                trace_rows,
+                evaluation_table: shared_evaluations.as_ref().map(SharedMatrix::reference),
                 constraint_coeffs: constraint_coeffs.clone(),
-                aux_rand_elements: aux_trace_rand_elements.clone(),
+                aux_rand_elements: aux_rand_elements.clone(),
                 computation_fragment: ComputationFragment {
                     num_fragments: frag_num,
                     fragment_offset: i,
                 },
             };
-            self.worker_pool
-                .run_constraint(constraint_work_item, self.get_on_msg_callback_constraints())?;
+            self.worker_pool.submit(
+                WorkerJob::EvaluateConstraints(constraint_work_item),
+                self.on_constraint_evaluations(frag_num),
+            )?;
         }
-        let fut = ResolvableFuture {
-            result: self.constraint_evaluations.clone(),
-            exepected_size: 8,
-        };
-        fut.await;
-        for evaluation in self.constraint_evaluations.borrow().iter() {
-            for i in 0..evaluation.constraint_evaluations[0].len() {
-                let step = i + evaluation.frag_index;
-                let mut row = vec![];
-                for col in 0..evaluation.constraint_evaluations.len() {
-                    row.push(evaluation.constraint_evaluations[col][i].0);
-                }
-                evaluation_table_workers.update_row(step, &row);
+        self.constraint_evaluations.collect(frag_num).await?;
+        if let Some(shared_evaluations) = shared_evaluations {
+            self.constraint_evaluations.clear();
+            let evaluations: Vec<E> = shared_evaluations
+                .view()?
+                .read_elements(0, domain.ce_domain_size())?;
+            for (step, evaluation) in evaluations.iter().enumerate() {
+                evaluation_table_workers.update_row(step, &[*evaluation]);
+            }
+            return Ok(evaluation_table_workers);
+        }
+        for result in self.constraint_evaluations.take().iter() {
+            let evaluations = read_worker_elements::<E>(&result.evaluations)?;
+            for (i, evaluation) in evaluations.into_iter().enumerate() {
+                evaluation_table_workers.update_row(result.frag_index + i, &[evaluation]);
             }
         }
         Ok(evaluation_table_workers)
@@ -441,22 +828,12 @@ impl MidenProverAsyncWorker {
     fn prove_sequential(
         &mut self,
         proving_work_item: ProvingWorkItem,
-    ) -> Result<ProverOutput, JsValue> {
-        console::time_with_label("preparing_inputs");
-        let miden_program = sdk::MidenProgram::decode(&proving_work_item.program[..])
-            .expect("Cannot decode miden program");
-        let miden_program_inputs =
-            sdk::MidenProgramInputs::decode(&proving_work_item.program_inputs[..])
-                .expect("Cannot decode miden program inputs");
-        let proof_options = sdk::ProofOptions::decode(&proving_work_item.proof_options[..])
-            .expect("Cannot decode proof options");
-
-        self.program = Some(miden_program.into());
-        self.program_inputs = Some(miden_program_inputs.into());
-        self.proof_options = Some(proof_options.into());
-        console::time_end_with_label("preparing_inputs");
+    ) -> Result<ProverOutput, ProverError> {
+        self.prepare_inputs(&proving_work_item)?;
+        self.start_stage(ProvingStage::GeneratingTrace)?;
         self.build_execution_trace()?;
-        console::time_with_label("prove_full");
+        console::time_end_with_label("generating_trace");
+        self.start_stage(ProvingStage::ProveFull)?;
         let prover = ExecutionProver::new(
             self.proof_options.clone().unwrap(),
             self.program_inputs.clone().unwrap().stack_init().to_vec(),
@@ -464,8 +841,13 @@ impl MidenProverAsyncWorker {
         );
         let proof = prover
             .prove(self.trace.clone().unwrap())
-            .map_err(|err| format!("Failed to prove program - {:?}", err))?;
+            .map_err(|err| ProverError::Proving(format!("Failed to prove program - {:?}", err)))?;
         console::time_end_with_label("prove_full");
+        self.finish_proof(proof)
+    }
+
+    /// Verifies the proof against the program outputs and encodes it for the SDK
+    fn finish_proof(&self, proof: StarkProof) -> Result<ProverOutput, ProverError> {
         let pub_inputs = PublicInputs::new(
             self.program.clone().unwrap().hash(),
             self.program_inputs.clone().unwrap().stack_init().to_vec(),
@@ -483,19 +865,19 @@ impl MidenProverAsyncWorker {
             .unwrap()
             .stack_init()
             .iter()
-            //for whatever reason miden reverses the stack
+            // for whatever reason miden reverses the stack
             .rev()
             .map(|e| e.as_int())
             .collect();
 
-        console::time_with_label("verify_program");
+        self.start_stage(ProvingStage::VerifyProgram)?;
         verify(
             self.program.clone().unwrap().hash(),
             &stack_inputs[..],
             &self.program_outputs.clone().unwrap(),
             proof.clone(),
         )
-        .map_err(|e| format!("Could not verify proof due to {}", e))?;
+        .map_err(|e| ProverError::Verification(format!("Could not verify proof due to {}", e)))?;
         console::time_end_with_label("verify_program");
 
         info!(
@@ -509,63 +891,292 @@ impl MidenProverAsyncWorker {
         );
         let sdk_outputs: sdk::MidenProgramOutputs = self.program_outputs.clone().unwrap().into();
         let sdk_pub_inputs: sdk::MidenPublicInputs = pub_inputs.into();
-        let js_output = ProverOutput {
+        Ok(ProverOutput {
             proof: sdk_proof.encode_to_vec(),
             program_outputs: sdk_outputs.encode_to_vec(),
             public_inputs: sdk_pub_inputs.encode_to_vec(),
-        };
-        Ok(js_output)
+        })
+    }
+
+    /// Collects the columns extended by a job once it is done
+    fn on_extended_columns(
+        &self,
+        num_jobs: usize,
+        stage: ProvingStage,
+    ) -> impl FnOnce(Result<JobOutput, ProverError>) + 'static {
+        let trace_columns = self.trace_columns.clone();
+        let progress = self.progress.clone();
+        move |output| match output.and_then(JobOutput::into_extended_columns) {
+            Ok(result) => {
+                let completed = trace_columns.push(result);
+                report_progress(&progress, stage, completed, num_jobs);
+            }
+            Err(err) => trace_columns.abort(worker_failure(err)),
+        }
     }
 
-    /// Message passing by the main thread
-    fn get_on_msg_callback(&self) -> Closure<dyn FnMut(MessageEvent)> {
+    /// Collects the row hashes of a batch once its job is done
+    fn on_row_hashes(
+        &self,
+        num_of_batches: usize,
+        stage: ProvingStage,
+    ) -> impl FnOnce(Result<JobOutput, ProverError>) + 'static {
         let trace_row_hashes = self.trace_row_hashes.clone();
-        let callback = Closure::new(move |event: MessageEvent| {
-            debug!("Proving get_on_msg_callback thread got message");
-            let data: Uint8Array = Uint8Array::new(&event.data());
-            let hashing_result: HashingResult = from_uint8array(&data).unwrap();
-            let hashes = hashing_result
-                .hashes
-                .into_iter()
-                .map(|d| ByteDigest::new(d))
-                .collect();
-            trace_row_hashes
-                .borrow_mut()
-                .push((hashing_result.batch_idx, hashes));
-        });
+        let progress = self.progress.clone();
+        move |output| match output.and_then(JobOutput::into_row_hashes) {
+            Ok(hashing_result) => {
+                let completed =
+                    trace_row_hashes.push((hashing_result.batch_idx, hashing_result.hashes));
+                report_progress(&progress, stage, completed, num_of_batches);
+            }
+            Err(err) => trace_row_hashes.abort(worker_failure(err)),
+        }
+    }
 
-        callback
+    /// Collects the next layer evaluations folded by a job once it is done
+    fn on_folded_fri_layer(
+        &self,
+        num_jobs: usize,
+    ) -> impl FnOnce(Result<JobOutput, ProverError>) + 'static {
+        let fri_foldings = self.fri_foldings.clone();
+        let progress = self.progress.clone();
+        move |output| match output.and_then(JobOutput::into_folded_fri_layer) {
+            Ok(result) => {
+                let completed = fri_foldings.push(result);
+                report_progress(&progress, ProvingStage::BuildFriLayers, completed, num_jobs);
+            }
+            Err(err) => fri_foldings.abort(worker_failure(err)),
+        }
     }
 
-    fn get_on_msg_callback_constraints(&self) -> Closure<dyn FnMut(MessageEvent)> {
+    /// Collects the evaluations of a constraint fragment once its job is done
+    fn on_constraint_evaluations(
+        &self,
+        num_fragments: usize,
+    ) -> impl FnOnce(Result<JobOutput, ProverError>) + 'static {
         let constraint_evaluations = self.constraint_evaluations.clone();
-        let callback = Closure::new(move |event: MessageEvent| {
-            let result =
-                from_uint8array::<ConstraintComputeResult>(&Uint8Array::new(&event.data()))
-                    .unwrap();
-            constraint_evaluations.borrow_mut().push(result);
+        let progress = self.progress.clone();
+        move |output| match output.and_then(JobOutput::into_constraint_evaluations) {
+            Ok(result) => {
+                let completed = constraint_evaluations.push(result);
+                report_progress(
+                    &progress,
+                    ProvingStage::ConstraintEvaluations,
+                    completed,
+                    num_fragments,
+                );
+            }
+            Err(err) => constraint_evaluations.abort(worker_failure(err)),
+        }
+    }
+}
+
+/// Rows hashed on the pool, copied into the jobs or read in place from a shared matrix
+enum RowSource<'a> {
+    Copied {
+        num_rows: usize,
+        num_cols: usize,
+        read_row: Box<dyn Fn(usize, &mut [Felt]) + 'a>,
+    },
+    Shared(SharedMatrixRef),
+}
+
+impl<'a> RowSource<'a> {
+    /// Rows of a matrix of `E` elements, read in base field coordinates
+    fn matrix<E: FieldElement<BaseField = Felt>>(matrix: &'a Matrix<E>) -> Self {
+        let degree = E::EXTENSION_DEGREE;
+        RowSource::Copied {
+            num_rows: matrix.num_rows(),
+            num_cols: matrix.num_cols() * degree,
+            read_row: Box::new(move |row_idx, row| {
+                for (col_idx, column) in matrix.columns().enumerate() {
+                    row[col_idx * degree..(col_idx + 1) * degree].copy_from_slice(
+                        E::as_base_elements(std::slice::from_ref(&column[row_idx])),
+                    );
+                }
+            }),
+        }
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        match self {
+            RowSource::Copied {
+                num_rows, num_cols, ..
+            } => (*num_rows, *num_cols),
+            RowSource::Shared(matrix) => (matrix.num_rows, matrix.num_cols),
+        }
+    }
+}
+
+/// LDE of a trace segment, extended into the proving worker's memory or, when workers can share
+/// memory, straight into a shared buffer
+enum SegmentLde<E: FieldElement<BaseField = Felt>> {
+    Copied(Matrix<E>),
+    Shared(SharedMatrix),
+}
+
+impl<E: FieldElement<BaseField = Felt>> SegmentLde<E> {
+    fn rows(&self) -> RowSource<'_> {
+        match self {
+            SegmentLde::Copied(matrix) => RowSource::matrix(matrix),
+            SegmentLde::Shared(matrix) => RowSource::Shared(matrix.reference()),
+        }
+    }
+}
+
+/// Committed trace LDE segments, read by the constraint workers and opened at the query
+/// positions once they are drawn
+enum TraceLdeCommitment<H: ElementHasher<BaseField = Felt>, E: FieldElement<BaseField = Felt>> {
+    Copied(TraceCommitment<E, H>),
+    Shared {
+        segments: Vec<(SharedMatrix, MerkleTree<H>)>,
+        blowup: usize,
+    },
+}
+
+impl<H, E> TraceLdeCommitment<H, E>
+where
+    H: ElementHasher<BaseField = Felt>,
+    E: FieldElement<BaseField = Felt>,
+{
+    fn new(
+        main_segment_lde: SegmentLde<Felt>,
+        main_segment_tree: MerkleTree<H>,
+        blowup: usize,
+    ) -> Self {
+        match main_segment_lde {
+            SegmentLde::Copied(lde) => {
+                TraceLdeCommitment::Copied(TraceCommitment::new(lde, main_segment_tree, blowup))
+            }
+            SegmentLde::Shared(lde) => TraceLdeCommitment::Shared {
+                segments: vec![(lde, main_segment_tree)],
+                blowup,
+            },
+        }
+    }
+
+    fn add_segment(
+        &mut self,
+        aux_segment_lde: SegmentLde<E>,
+        aux_segment_tree: MerkleTree<H>,
+    ) -> Result<(), ProverError> {
+        match (self, aux_segment_lde) {
+            (TraceLdeCommitment::Copied(commitment), SegmentLde::Copied(lde)) => {
+                commitment.add_segment(lde, aux_segment_tree)
+            }
+            (TraceLdeCommitment::Shared { segments, .. }, SegmentLde::Shared(lde)) => {
+                segments.push((lde, aux_segment_tree))
+            }
+            _ => {
+                return Err(ProverError::Proving(
+                    "trace segments must all be shared or all be copied".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// The `num_rows` LDE rows from `first_row` on a constraint fragment reads
+    fn fragment_rows(&self, first_row: usize, num_rows: usize) -> TraceLdeRows {
+        match self {
+            TraceLdeCommitment::Copied(commitment) => TraceLdeRows::Copied(TraceLdeWindow::new(
+                commitment.trace_table(),
+                first_row,
+                num_rows,
+            )),
+            TraceLdeCommitment::Shared { segments, blowup } => TraceLdeRows::Shared {
+                segments: segments.iter().map(|(lde, _)| lde.reference()).collect(),
+                blowup: *blowup,
+                first_row,
+                num_rows,
+            },
+        }
+    }
+
+    /// Opens every segment at `positions`, the same as `TraceCommitment::query`; the rows of
+    /// an auxiliary segment are read in base field coordinates, which serialize the same
+    fn query(&self, positions: &[usize]) -> Result<Vec<Queries>, ProverError> {
+        let segments = match self {
+            TraceLdeCommitment::Copied(commitment) => return Ok(commitment.query(positions)),
+            TraceLdeCommitment::Shared { segments, .. } => segments,
+        };
+        segments
+            .iter()
+            .map(|(lde, tree)| {
+                let view = lde.view()?;
+                let states = positions
+                    .iter()
+                    .map(|&position| {
+                        let mut state = vec![Felt::ZERO; view.num_cols()];
+                        view.read_row_into(position, &mut state);
+                        state
+                    })
+                    .collect();
+                let proof = tree.prove_batch(positions).map_err(|e| {
+                    ProverError::Proving(format!("Cannot open trace commitment: {}", e))
+                })?;
+                Ok(Queries::new::<H, Felt>(proof, states))
+            })
+            .collect()
+    }
+}
+
+fn report_progress(
+    progress: &Option<Rc<dyn Fn(&ProverProgress)>>,
+    stage: ProvingStage,
+    completed: usize,
+    total: usize,
+) {
+    if let Some(progress) = progress {
+        progress(&ProverProgress {
+            stage,
+            completed,
+            total,
         });
-        callback
+    }
+}
+
+fn build_merkle_tree<H: ElementHasher<BaseField = Felt>>(
+    hashes: &[Vec<u8>],
+) -> Result<MerkleTree<H>, ProverError> {
+    let digests = hashes
+        .iter()
+        .map(|hash| H::Digest::read_from(&mut SliceReader::new(hash)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ProverError::Worker(format!("Cannot decode row hash: {}", e)))?;
+    MerkleTree::new(digests)
+        .map_err(|e| ProverError::Proving(format!("Cannot construct Merkle tree: {}", e)))
+}
+
+/// Reads elements of `E` a worker posted back in base field coordinates
+fn read_worker_elements<E: FieldElement<BaseField = Felt>>(
+    elements: &[FeltWrapper],
+) -> Result<Vec<E>, ProverError> {
+    elements_from_base(&unwrap_elements(elements)).map_err(worker_failure)
+}
+
+// a result the prover cannot decode means the worker misbehaved, not the caller's input
+fn worker_failure(err: ProverError) -> ProverError {
+    match err {
+        ProverError::Decode(msg) => ProverError::Worker(msg),
+        err => err,
     }
 }
 
 pub async fn proving_seq_entry_point(
     prover: &mut MidenProverAsyncWorker,
     payload: Uint8Array,
-) -> Result<Uint8Array, JsValue> {
-    if let Ok(proving_work_item) = from_uint8array::<ProvingWorkItem>(&payload) {
-        let prover_output = if proving_work_item.is_sequential {
-            prover.prove_sequential(proving_work_item)?
-        } else {
-            prover.prove(proving_work_item).await?
-        };
-        let payload = to_uint8array(&prover_output);
-        debug!("sent payload back to main thread");
-        Ok(payload)
+) -> Result<ProverOutput, ProverError> {
+    let proving_work_item = from_uint8array::<ProvingWorkItem>(&payload)?;
+    let prover_output = if proving_work_item.is_sequential {
+        prover.prove_sequential(proving_work_item)
     } else {
-        debug!("failed to decode proving workload");
-        Err(JsValue::from_str("failed to decode proving workload"))
+        prover.prove(proving_work_item).await
+    };
+    if let Err(ProverError::Cancelled) = prover_output {
+        prover.terminate_workers();
     }
+    prover_output
 }
 
 #[wasm_bindgen]
@@ -576,18 +1187,63 @@ pub async fn proving_entry_point(
     set_once_logger();
     debug!("got proving workload");
     let data: Uint8Array = Uint8Array::new(&msg.data());
-    if let Ok(proving_work_item) = from_uint8array::<ProvingWorkItem>(&data) {
-        let prover_output = if proving_work_item.is_sequential {
-            prover.prove_sequential(proving_work_item)?
-        } else {
-            prover.prove(proving_work_item).await?
-        };
-        let payload = to_uint8array(&prover_output);
-        let global_scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
-        global_scope.post_message(&payload)?;
-        debug!("sent payload back to main thread");
-    } else {
-        debug!("failed to decode proving workload");
+    let global_scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+    let progress_scope = global_scope.clone();
+    prover.set_progress_sink(Rc::new(move |progress: &ProverProgress| {
+        let payload = to_uint8array(&ProverMessage::Progress(progress.clone()));
+        if let Err(err) = progress_scope.post_message(&payload) {
+            debug!("failed to post progress: {:?}", err);
+        }
+    }));
+    let prover_output = proving_seq_entry_point(prover, data).await;
+    if let Err(err) = &prover_output {
+        debug!("failed to prove workload: {}", err);
     }
+    // errors are posted as well so the main thread does not wait forever
+    let payload = to_uint8array(&ProverMessage::Done(prover_output));
+    global_scope.post_message(&payload)?;
+    debug!("sent payload back to main thread");
     Ok(())
 }
+
+#[cfg(test)]
+mod proving_test {
+    use super::*;
+    use miden::{FieldExtension, HashFunction};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_prove_with_quadratic_extension() {
+        let options = miden::ProofOptions::new(
+            27,
+            8,
+            16,
+            HashFunction::Blake3_256,
+            FieldExtension::Quadratic,
+            8,
+            256,
+        );
+        let item = ProvingWorkItem {
+            program: sdk::MidenProgram {
+                program: "begin repeat.20 swap dup.1 add end end".to_string(),
+            }
+            .encode_to_vec(),
+            program_inputs: sdk::MidenProgramInputs::default().encode_to_vec(),
+            proof_options: sdk::ProofOptions::from(&options.0).encode_to_vec(),
+            partition: WorkPartition::default(),
+            is_sequential: false,
+        };
+
+        // the proof is verified before it is encoded
+        let mut prover = MidenProverAsyncWorker::with_worker_pool(WorkerPool::inline(2));
+        let output = prover.prove(item).await.unwrap();
+        let proof = sdk::StarkProof::decode(&output.proof[..]).unwrap();
+        let options = proof.context.unwrap().options.unwrap();
+        assert_eq!(
+            options.field_extension,
+            sdk::FieldExtension::Quadratic as i32
+        );
+    }
+}
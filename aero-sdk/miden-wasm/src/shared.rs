@@ -0,0 +1,295 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use js_sys::{BigUint64Array, Reflect, SharedArrayBuffer};
+use miden_air::{Felt, FieldElement, StarkField};
+
+use crate::error::ProverError;
+use crate::pool::WorkerPool;
+use crate::utils::elements_from_base;
+
+/// Identifies a shared buffer attached to the workers of a pool
+pub type BufferId = u32;
+
+const ELEMENT_BYTES: usize = 8;
+
+thread_local! {
+    // buffers attached to this worker, the proving worker registers the ones it creates too
+    static ATTACHED_BUFFERS: RefCell<HashMap<BufferId, SharedArrayBuffer>> =
+        RefCell::new(HashMap::new());
+}
+
+pub fn register_buffer(buffer_id: BufferId, buffer: SharedArrayBuffer) {
+    ATTACHED_BUFFERS.with(|buffers| buffers.borrow_mut().insert(buffer_id, buffer));
+}
+
+pub fn unregister_buffer(buffer_id: BufferId) {
+    ATTACHED_BUFFERS.with(|buffers| buffers.borrow_mut().remove(&buffer_id));
+}
+
+/// Whether workers can share memory, which browsers only allow on cross-origin isolated pages
+pub fn shared_memory_available() -> bool {
+    let global = js_sys::global();
+    let isolated = Reflect::get(&global, &"crossOriginIsolated".into())
+        .map(|isolated| isolated.is_truthy())
+        .unwrap_or(false);
+    isolated
+        && Reflect::get(&global, &"SharedArrayBuffer".into())
+            .map(|constructor| constructor.is_function())
+            .unwrap_or(false)
+}
+
+/// A column major matrix of field elements stored in a buffer shared with the pool workers.
+///
+/// Elements are stored as canonical `u64`s, a column of extension field elements as one column
+/// per coordinate so that a row holds the elements in their base field coordinates. Jobs only
+/// carry the reference, workers read and write the elements in place through a
+/// [`SharedMatrixView`] of the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SharedMatrixRef {
+    pub buffer_id: BufferId,
+    pub num_rows: usize,
+    pub num_cols: usize,
+}
+
+impl SharedMatrixRef {
+    /// A typed array view of the buffer attached to this worker
+    pub fn view(&self) -> Result<SharedMatrixView, ProverError> {
+        let elements = ATTACHED_BUFFERS.with(|buffers| {
+            buffers
+                .borrow()
+                .get(&self.buffer_id)
+                .map(|buffer| BigUint64Array::new(buffer))
+                .ok_or_else(|| {
+                    ProverError::Worker(format!("shared buffer {} is not attached", self.buffer_id))
+                })
+        })?;
+        if self.num_rows.checked_mul(self.num_cols) != Some(elements.length() as usize) {
+            return Err(ProverError::Worker(format!(
+                "shared buffer {} of {} elements does not hold {} x {} elements",
+                self.buffer_id,
+                elements.length(),
+                self.num_rows,
+                self.num_cols
+            )));
+        }
+        Ok(SharedMatrixView {
+            elements,
+            num_rows: self.num_rows,
+            num_cols: self.num_cols,
+        })
+    }
+}
+
+/// Typed array view of a shared matrix, elements are read from and written to the shared
+/// buffer without going through a copy of the matrix.
+///
+/// The view holds exactly `num_rows * num_cols` elements, so any element index inside the
+/// matrix fits the `u32` indices of the typed array.
+pub struct SharedMatrixView {
+    elements: BigUint64Array,
+    num_rows: usize,
+    num_cols: usize,
+}
+
+impl SharedMatrixView {
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    /// Checks that rows `[first_row, first_row + num_rows)` are rows of the matrix
+    pub fn check_rows(&self, first_row: usize, num_rows: usize) -> Result<(), ProverError> {
+        if first_row + num_rows > self.num_rows {
+            return Err(ProverError::Worker(format!(
+                "rows {}..{} are outside of a shared matrix of {} rows",
+                first_row,
+                first_row + num_rows,
+                self.num_rows
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reads the element at `row` of column `col`
+    pub fn get(&self, row: usize, col: usize) -> Felt {
+        debug_assert!(row < self.num_rows && col < self.num_cols);
+        Felt::new(self.elements.get_index((col * self.num_rows + row) as u32))
+    }
+
+    /// Reads row `row` into `row_values`, which holds `num_cols` elements
+    pub fn read_row_into(&self, row: usize, row_values: &mut [Felt]) {
+        for (col, value) in row_values.iter_mut().enumerate() {
+            *value = self.get(row, col);
+        }
+    }
+
+    /// Reads row `row` of a matrix of `E` elements, each column of which is stored as
+    /// `E::EXTENSION_DEGREE` consecutive base field columns
+    pub fn read_row_elements<E: FieldElement<BaseField = Felt>>(
+        &self,
+        row: usize,
+        row_values: &mut [E],
+    ) -> Result<(), ProverError> {
+        if row_values.len() * E::EXTENSION_DEGREE != self.num_cols {
+            return Err(ProverError::Worker(format!(
+                "row of {} elements of degree {} does not fit a shared matrix of {} columns",
+                row_values.len(),
+                E::EXTENSION_DEGREE,
+                self.num_cols
+            )));
+        }
+        let mut coordinates = vec![Felt::ZERO; self.num_cols];
+        self.read_row_into(row, &mut coordinates);
+        row_values.copy_from_slice(&elements_from_base::<E>(&coordinates)?);
+        Ok(())
+    }
+
+    /// Reads `num_elements` elements of `E` from the column major element index
+    /// `first_element` on, in a matrix storing each column of `E` elements as
+    /// `E::EXTENSION_DEGREE` consecutive base field columns
+    pub fn read_elements<E: FieldElement<BaseField = Felt>>(
+        &self,
+        first_element: usize,
+        num_elements: usize,
+    ) -> Result<Vec<E>, ProverError> {
+        let degree = E::EXTENSION_DEGREE;
+        self.check_elements::<E>(first_element, num_elements)?;
+        let mut coordinates = vec![Felt::ZERO; num_elements * degree];
+        let mut coordinate = vec![0u64; num_elements];
+        for (run_start, start, run) in self.column_runs::<E>(first_element, num_elements) {
+            for k in 0..degree {
+                let start = (start + k * self.num_rows) as u32;
+                self.elements
+                    .subarray(start, start + run as u32)
+                    .copy_to(&mut coordinate[..run]);
+                for (i, value) in coordinate[..run].iter().enumerate() {
+                    coordinates[(run_start + i) * degree + k] = Felt::new(*value);
+                }
+            }
+        }
+        elements_from_base(&coordinates)
+    }
+
+    /// Writes `values` from the column major element index `first_element` on, the same
+    /// layout [`SharedMatrixView::read_elements`] reads
+    pub fn write_elements<E: FieldElement<BaseField = Felt>>(
+        &self,
+        first_element: usize,
+        values: &[E],
+    ) -> Result<(), ProverError> {
+        let degree = E::EXTENSION_DEGREE;
+        self.check_elements::<E>(first_element, values.len())?;
+        let coordinates = E::as_base_elements(values);
+        for (run_start, start, run) in self.column_runs::<E>(first_element, values.len()) {
+            for k in 0..degree {
+                let coordinate: Vec<u64> = coordinates[run_start * degree..]
+                    .iter()
+                    .skip(k)
+                    .step_by(degree)
+                    .take(run)
+                    .map(|value| value.as_int())
+                    .collect();
+                let start = (start + k * self.num_rows) as u32;
+                self.elements
+                    .subarray(start, start + run as u32)
+                    .copy_from(&coordinate);
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits elements `[first_element, first_element + num_elements)` of `E` into runs within
+    /// a column, as `(offset in the range, index of the first coordinate, run length)`
+    fn column_runs<E: FieldElement<BaseField = Felt>>(
+        &self,
+        first_element: usize,
+        num_elements: usize,
+    ) -> Vec<(usize, usize, usize)> {
+        let mut runs = Vec::new();
+        let mut offset = 0;
+        while offset < num_elements {
+            let (col, row) = (
+                (first_element + offset) / self.num_rows,
+                (first_element + offset) % self.num_rows,
+            );
+            let run = usize::min(num_elements - offset, self.num_rows - row);
+            runs.push((offset, col * E::EXTENSION_DEGREE * self.num_rows + row, run));
+            offset += run;
+        }
+        runs
+    }
+
+    fn check_elements<E: FieldElement<BaseField = Felt>>(
+        &self,
+        first_element: usize,
+        num_elements: usize,
+    ) -> Result<(), ProverError> {
+        let degree = E::EXTENSION_DEGREE;
+        let end = first_element + num_elements;
+        if self.num_cols % degree != 0 || end * degree > self.num_rows * self.num_cols {
+            return Err(ProverError::Worker(format!(
+                "elements {}..{} of degree {} are outside of a shared matrix of {} x {} elements",
+                first_element, end, degree, self.num_rows, self.num_cols
+            )));
+        }
+        Ok(())
+    }
+}
+
+// shared buffer sizes are u32 on wasm32, larger matrices cannot be shared
+fn buffer_size(bytes: usize) -> Result<u32, ProverError> {
+    u32::try_from(bytes).map_err(|_| {
+        ProverError::Proving(format!(
+            "{} bytes exceed the size of a shared buffer",
+            bytes
+        ))
+    })
+}
+
+/// Owner of a shared matrix, detaches it from the pool workers when dropped
+pub struct SharedMatrix {
+    reference: SharedMatrixRef,
+    pool: WorkerPool,
+}
+
+impl SharedMatrix {
+    /// Allocates a zeroed matrix and attaches it to the workers of `pool`
+    pub fn new(pool: &WorkerPool, num_rows: usize, num_cols: usize) -> Result<Self, ProverError> {
+        let num_bytes = num_rows
+            .checked_mul(num_cols)
+            .and_then(|num_elements| num_elements.checked_mul(ELEMENT_BYTES))
+            .ok_or_else(|| {
+                ProverError::Proving(format!(
+                    "shared matrix of {} x {} elements overflows",
+                    num_rows, num_cols
+                ))
+            })?;
+        let buffer = SharedArrayBuffer::new(buffer_size(num_bytes)?);
+        let buffer_id = pool.attach(&buffer)?;
+        Ok(SharedMatrix {
+            reference: SharedMatrixRef {
+                buffer_id,
+                num_rows,
+                num_cols,
+            },
+            pool: pool.clone(),
+        })
+    }
+
+    pub fn reference(&self) -> SharedMatrixRef {
+        self.reference
+    }
+
+    pub fn view(&self) -> Result<SharedMatrixView, ProverError> {
+        self.reference.view()
+    }
+}
+
+impl Drop for SharedMatrix {
+    fn drop(&mut self) {
+        self.pool.detach(self.reference.buffer_id);
+    }
+}
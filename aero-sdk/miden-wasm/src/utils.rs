@@ -1,13 +1,18 @@
 use std::{marker::PhantomData, sync::Once};
 
-use js_sys::Uint8Array;
-use miden_air::{Felt, PublicInputs, StarkField};
+use js_sys::{Object, Reflect, Uint8Array};
+use miden::{Program, ProgramInputs};
+use miden_air::{Felt, FieldElement, PublicInputs, StarkField};
+use miden_proof_format::format::{FieldExtensionTag, HashFunctionTag};
+use miden_proof_format::sdk;
+use prost::Message;
+
+use crate::error::ProverError;
+use crate::shared::SharedMatrixRef;
 use serde::{ser::SerializeSeq, Deserializer, Serializer};
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 use wasm_bindgen_console_logger::DEFAULT_LOGGER;
-use winter_air::{
-    AuxTraceRandElements, ConstraintCompositionCoefficients, ProofOptions, TraceInfo, TraceLayout,
-};
+use winter_air::{ConstraintCompositionCoefficients, ProofOptions, TraceInfo, TraceLayout};
 use winter_prover::{Matrix, TraceLde};
 use winter_utils::{Deserializable, Serializable, SliceReader};
 
@@ -117,188 +122,6 @@ winter_serde!(
     ProofOptions
 );
 
-fn serialize_aux_rand_elements<S: Serializer>(
-    input: &AuxTraceRandElements<Felt>,
-    serializer: S,
-) -> Result<S::Ok, S::Error> {
-    let flattened: Vec<Vec<FeltWrapper>> = input
-        .0
-        .iter()
-        .map(|v| v.iter().map(|e| e.into()).collect::<Vec<_>>())
-        .collect::<Vec<_>>();
-    let mut s = serializer.serialize_seq(Some(flattened.len()))?;
-    for e in flattened {
-        s.serialize_element(&e)?;
-    }
-    s.end()
-}
-
-fn deserialize_aux_rand_elements<'de, D: Deserializer<'de>>(
-    deserializer: D,
-) -> Result<AuxTraceRandElements<Felt>, D::Error> {
-    struct AuxRandElementsVisitor;
-
-    impl<'de> serde::de::Visitor<'de> for AuxRandElementsVisitor {
-        type Value = AuxTraceRandElements<Felt>;
-
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str("a AuxTraceRandElements<Felt>")
-        }
-
-        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-        where
-            A: serde::de::SeqAccess<'de>,
-        {
-            let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
-            while let Some(v) = seq.next_element::<Vec<FeltWrapper>>()? {
-                vec.push(
-                    v.iter()
-                        .map(|wrapper| wrapper.clone().into())
-                        .collect::<Vec<_>>(),
-                );
-            }
-            Ok(AuxTraceRandElements(vec))
-        }
-    }
-
-    deserializer.deserialize_seq(AuxRandElementsVisitor)
-}
-
-fn serialize_constraint_coeffs<S: Serializer>(
-    input: &ConstraintCompositionCoefficients<Felt>,
-    serializer: S,
-) -> Result<S::Ok, S::Error> {
-    let mut s: <S as Serializer>::SerializeSeq = serializer.serialize_seq(Some(2))?;
-    let converted_transition: Vec<(FeltWrapper, FeltWrapper)> = input
-        .transition
-        .iter()
-        .map(|(v0, v1)| (v0.into(), v1.into()))
-        .collect::<Vec<_>>();
-    let converted_boundary: Vec<(FeltWrapper, FeltWrapper)> = input
-        .boundary
-        .iter()
-        .map(|(v0, v1)| (v0.into(), v1.into()))
-        .collect::<Vec<_>>();
-    s.serialize_element(&converted_transition)?;
-    s.serialize_element(&converted_boundary)?;
-    s.end()
-}
-
-fn deserialize_constraint_coeffs<'de, D: Deserializer<'de>>(
-    deserializer: D,
-) -> Result<ConstraintCompositionCoefficients<Felt>, D::Error> {
-    struct ConstraintCoeffsVisitor;
-
-    impl<'de> serde::de::Visitor<'de> for ConstraintCoeffsVisitor {
-        type Value = ConstraintCompositionCoefficients<Felt>;
-
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str("a ConstraintCompositionCoefficients<Felt>")
-        }
-
-        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-        where
-            A: serde::de::SeqAccess<'de>,
-        {
-            let transition = seq
-                .next_element::<Vec<(FeltWrapper, FeltWrapper)>>()?
-                .ok_or_else(|| {
-                    serde::de::Error::custom("expected transition to deser to Vec<(Felt, Felt)>")
-                })?
-                .iter()
-                .map(|(v0, v1)| (v0.clone().into(), v1.clone().into()))
-                .collect::<Vec<_>>();
-            let boundary = seq
-                .next_element::<Vec<(FeltWrapper, FeltWrapper)>>()?
-                .ok_or_else(|| {
-                    serde::de::Error::custom("expected boundary to deser to Vec<(Felt, Felt)>")
-                })?
-                .iter()
-                .map(|(v0, v1)| (v0.clone().into(), v1.clone().into()))
-                .collect::<Vec<_>>();
-            Ok(ConstraintCompositionCoefficients {
-                transition,
-                boundary,
-            })
-        }
-    }
-
-    deserializer.deserialize_seq(ConstraintCoeffsVisitor)
-}
-
-fn serialize_trace_lde<S: Serializer>(
-    input: &TraceLde<Felt>,
-    serializer: S,
-) -> Result<S::Ok, S::Error> {
-    let mut s: <S as Serializer>::SerializeSeq = serializer.serialize_seq(Some(3))?;
-    let mut converted_main: Vec<Vec<FeltWrapper>> = vec![];
-    for col in input.main_segment_lde.columns() {
-        converted_main.push(col.iter().map(|e| e.into()).collect::<Vec<_>>());
-    }
-    let mut converted_aux: Vec<Vec<Vec<FeltWrapper>>> = vec![];
-    for aux in input.aux_segment_ldes.iter() {
-        let mut converted_aux_segment: Vec<Vec<FeltWrapper>> = vec![];
-        for col in aux.columns() {
-            converted_aux_segment.push(col.iter().map(|e| e.into()).collect::<Vec<_>>());
-        }
-        converted_aux.push(converted_aux_segment);
-    }
-    s.serialize_element(&converted_main)?;
-    s.serialize_element(&converted_aux)?;
-    s.serialize_element(&input.blowup)?;
-    s.end()
-}
-
-fn deserialize_trace_lde<'de, D: Deserializer<'de>>(
-    deserializer: D,
-) -> Result<TraceLde<Felt>, D::Error> {
-    struct TraceLdeVisitor;
-
-    impl<'de> serde::de::Visitor<'de> for TraceLdeVisitor {
-        type Value = TraceLde<Felt>;
-
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str("a TraceLde<Felt>")
-        }
-
-        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-        where
-            A: serde::de::SeqAccess<'de>,
-        {
-            let main_columns = seq
-                .next_element::<Vec<Vec<FeltWrapper>>>()?
-                .ok_or_else(|| {
-                    serde::de::Error::custom("expected main to deser to Vec<Vec<Felt>>")
-                })?
-                .iter()
-                .map(|col| col.iter().map(|e| e.clone().into()).collect::<Vec<_>>())
-                .collect::<Vec<_>>();
-            let aux_segments = seq
-                .next_element::<Vec<Vec<Vec<FeltWrapper>>>>()?
-                .ok_or_else(|| serde::de::Error::custom("expected aux to deser to Vec<Vec<Felt>>"))?
-                .iter()
-                .map(|aux_segment| {
-                    aux_segment
-                        .iter()
-                        .map(|col| col.iter().map(|e| e.clone().into()).collect::<Vec<_>>())
-                        .collect::<Vec<_>>()
-                })
-                .collect::<Vec<_>>();
-            let blowup = seq
-                .next_element::<usize>()?
-                .ok_or_else(|| serde::de::Error::custom("expected blowup to deser"))?;
-            let mut trace_lde = TraceLde::new(Matrix::new(main_columns), blowup);
-            for aux_segment in aux_segments {
-                trace_lde.add_aux_segment(Matrix::new(aux_segment));
-            }
-
-            Ok(trace_lde)
-        }
-    }
-
-    deserializer.deserialize_seq(TraceLdeVisitor)
-}
-
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct ConstraintComputeWorkItem {
     #[serde(
@@ -316,28 +139,126 @@ pub struct ConstraintComputeWorkItem {
         deserialize_with = "deserialize_proof_options"
     )]
     pub proof_options: ProofOptions,
-    #[serde(
-        serialize_with = "serialize_aux_rand_elements",
-        deserialize_with = "deserialize_aux_rand_elements"
-    )]
-    pub aux_rand_elements: AuxTraceRandElements<Felt>,
-    #[serde(
-        serialize_with = "serialize_constraint_coeffs",
-        deserialize_with = "deserialize_constraint_coeffs"
-    )]
-    pub constraint_coeffs: ConstraintCompositionCoefficients<Felt>,
-    #[serde(with = "serde_bytes")]
-    pub trace_lde_wrapper: Vec<u8>,
+    /// Field the auxiliary trace and the constraint evaluations are computed over
+    pub field_extension: FieldExtensionTag,
+    /// Random elements of each auxiliary segment, in base field coordinates
+    pub aux_rand_elements: Vec<Vec<FeltWrapper>>,
+    pub constraint_coeffs: CompositionCoefficients,
+    pub trace_rows: TraceLdeRows,
+    /// Shared constraint evaluation table the worker writes its fragment into, the evaluations
+    /// are posted back when it is `None`
+    pub evaluation_table: Option<SharedMatrixRef>,
     pub computation_fragment: ComputationFragment,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct TraceLdeWrapper {
-    #[serde(
-        serialize_with = "serialize_trace_lde",
-        deserialize_with = "deserialize_trace_lde"
-    )]
-    pub trace_lde: TraceLde<Felt>,
+/// Constraint composition coefficients in base field coordinates, each pair of coefficients
+/// flattened one after the other
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CompositionCoefficients {
+    pub transition: Vec<FeltWrapper>,
+    pub boundary: Vec<FeltWrapper>,
+}
+
+impl CompositionCoefficients {
+    pub fn new<E: FieldElement<BaseField = Felt>>(
+        coefficients: &ConstraintCompositionCoefficients<E>,
+    ) -> Self {
+        let flatten = |pairs: &[(E, E)]| {
+            let elements: Vec<E> = pairs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+            to_base_elements(&elements)
+        };
+        Self {
+            transition: flatten(&coefficients.transition),
+            boundary: flatten(&coefficients.boundary),
+        }
+    }
+
+    pub fn decode<E: FieldElement<BaseField = Felt>>(
+        &self,
+    ) -> Result<ConstraintCompositionCoefficients<E>, ProverError> {
+        let pairs = |coordinates: &[FeltWrapper]| -> Result<Vec<(E, E)>, ProverError> {
+            let elements = elements_from_base::<E>(&unwrap_elements(coordinates))?;
+            if elements.len() % 2 != 0 {
+                return Err(ProverError::Decode(format!(
+                    "{} composition coefficients do not split into pairs",
+                    elements.len()
+                )));
+            }
+            Ok(elements.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+        };
+        Ok(ConstraintCompositionCoefficients {
+            transition: pairs(&self.transition)?,
+            boundary: pairs(&self.boundary)?,
+        })
+    }
+}
+
+/// Trace LDE rows of a constraint fragment, copied into the work item or read from the shared
+/// trace LDE segments
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TraceLdeRows {
+    Copied(TraceLdeWindow),
+    Shared {
+        /// The main segment followed by the auxiliary segments
+        segments: Vec<SharedMatrixRef>,
+        blowup: usize,
+        first_row: usize,
+        num_rows: usize,
+    },
+}
+
+/// Rows of the trace LDE a constraint fragment reads.
+///
+/// A fragment over steps `[offset, offset + n)` of the constraint evaluation domain reads the
+/// LDE rows `step << lde_shift`, and `blowup` rows further for the next row of each frame,
+/// wrapping around the end of the LDE. The window holds only these rows, so a worker allocates
+/// about `lde_size / num_fragments` rows rather than the whole LDE.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TraceLdeWindow {
+    pub blowup: usize,
+    /// Index of the first LDE row of the window
+    pub first_row: usize,
+    pub num_rows: usize,
+    /// Rows of the main segment, row major
+    pub main_rows: Vec<FeltWrapper>,
+    /// Rows of the auxiliary segments side by side, row major, each element in base field
+    /// coordinates
+    pub aux_rows: Vec<FeltWrapper>,
+}
+
+impl TraceLdeWindow {
+    pub fn new<E: FieldElement<BaseField = Felt>>(
+        trace_lde: &TraceLde<E>,
+        first_row: usize,
+        num_rows: usize,
+    ) -> Self {
+        fn read_row<E: FieldElement<BaseField = Felt>>(
+            matrix: &Matrix<E>,
+            row_idx: usize,
+            rows: &mut Vec<FeltWrapper>,
+        ) {
+            for column in matrix.columns() {
+                rows.extend(to_base_elements(std::slice::from_ref(&column[row_idx])));
+            }
+        }
+        let lde_size = trace_lde.main_segment_lde.num_rows();
+        let mut main_rows = Vec::with_capacity(num_rows * trace_lde.main_segment_lde.num_cols());
+        let mut aux_rows = Vec::new();
+        for i in 0..num_rows {
+            let row_idx = (first_row + i) % lde_size;
+            read_row(&trace_lde.main_segment_lde, row_idx, &mut main_rows);
+            for aux_segment in trace_lde.aux_segment_ldes.iter() {
+                read_row(aux_segment, row_idx, &mut aux_rows);
+            }
+        }
+        Self {
+            blowup: trace_lde.blowup,
+            first_row,
+            num_rows,
+            main_rows,
+            aux_rows,
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -351,14 +272,152 @@ pub struct ProvingWorkItem {
     pub program: Vec<u8>,
     pub program_inputs: Vec<u8>,
     pub proof_options: Vec<u8>,
-    pub chunk_size: usize,
+    pub partition: WorkPartition,
     pub is_sequential: bool,
 }
 
+impl ProvingWorkItem {
+    /// Decodes the protobuf messages and assembles the program
+    pub fn decode(&self) -> Result<(Program, ProgramInputs, miden::ProofOptions), ProverError> {
+        let miden_program = sdk::MidenProgram::decode(&self.program[..])
+            .map_err(|e| ProverError::Decode(format!("Cannot decode miden program: {}", e)))?;
+        let miden_program_inputs = sdk::MidenProgramInputs::decode(&self.program_inputs[..])
+            .map_err(|e| {
+                ProverError::Decode(format!("Cannot decode miden program inputs: {}", e))
+            })?;
+        let proof_options = sdk::ProofOptions::decode(&self.proof_options[..])
+            .map_err(|e| ProverError::Decode(format!("Cannot decode proof options: {}", e)))?;
+        Ok((
+            miden_program.try_into()?,
+            miden_program_inputs.try_into()?,
+            proof_options.try_into()?,
+        ))
+    }
+}
+
+// each worker gets a few hashing batches so that a slow worker does not hold up the stage
+const HASHING_BATCHES_PER_WORKER: usize = 4;
+const MIN_HASHING_CHUNK_SIZE: usize = 256;
+// smaller fragments cost more in copied trace rows and job overhead than they save
+const MIN_FRAGMENT_ROWS: usize = 1024;
+
+/// How the pool jobs split the trace, derived from the trace and pool sizes unless overridden
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WorkPartition {
+    /// Rows hashed per job
+    pub chunk_size: Option<usize>,
+    /// Fragments the constraint evaluation domain is split into, a power of two
+    pub num_fragments: Option<usize>,
+}
+
+impl WorkPartition {
+    /// Checks the overrides that do not depend on the trace size
+    pub fn validate(&self) -> Result<(), ProverError> {
+        if self.chunk_size == Some(0) {
+            return Err(ProverError::InvalidInput(
+                "chunk size must be at least 1".to_string(),
+            ));
+        }
+        match self.num_fragments {
+            Some(num_fragments) if !num_fragments.is_power_of_two() => {
+                Err(ProverError::InvalidInput(format!(
+                    "fragment count must be a power of two, got {}",
+                    num_fragments
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rows per hashing job for a matrix of `num_rows` rows
+    pub fn chunk_size(&self, num_rows: usize, concurrency: usize) -> Result<usize, ProverError> {
+        self.validate()?;
+        Ok(self.chunk_size.unwrap_or_else(|| {
+            let num_batches = concurrency.max(1) * HASHING_BATCHES_PER_WORKER;
+            let chunk_size = (num_rows + num_batches - 1) / num_batches;
+            chunk_size.max(MIN_HASHING_CHUNK_SIZE)
+        }))
+    }
+
+    /// Fragments for a constraint evaluation domain of `ce_domain_size` rows
+    pub fn num_fragments(
+        &self,
+        ce_domain_size: usize,
+        concurrency: usize,
+    ) -> Result<usize, ProverError> {
+        self.validate()?;
+        match self.num_fragments {
+            Some(num_fragments) if num_fragments > ce_domain_size => {
+                Err(ProverError::InvalidInput(format!(
+                    "fragment count {} exceeds the constraint evaluation domain size {}",
+                    num_fragments, ce_domain_size
+                )))
+            }
+            Some(num_fragments) => Ok(num_fragments),
+            None => {
+                // both sizes are powers of two
+                let max_fragments = usize::max(ce_domain_size / MIN_FRAGMENT_ROWS, 1);
+                Ok(concurrency.max(1).next_power_of_two().min(max_fragments))
+            }
+        }
+    }
+}
+
+/// Rows a hashing job hashes, copied into the work item or read from a shared matrix
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RowBatch {
+    Copied(Vec<Vec<FeltWrapper>>),
+    Shared {
+        matrix: SharedMatrixRef,
+        first_row: usize,
+        num_rows: usize,
+    },
+}
+
+/// Rows of a FRI layer a worker folds into the next layer.
+///
+/// The rows are those of the layer transposed into rows of `folding_factor` evaluations, each
+/// row folds into a single evaluation of the next layer. Evaluations are in base field
+/// coordinates, a row holds `folding_factor` times the extension degree elements.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FriFoldWorkItem {
+    pub rows: RowBatch,
+    /// Index of the first row in the transposed layer
+    pub first_row: usize,
+    /// Number of rows of the transposed layer
+    pub layer_rows: usize,
+    pub folding_factor: usize,
+    /// Field the layer evaluations are in
+    pub field_extension: FieldExtensionTag,
+    pub domain_offset: FeltWrapper,
+    /// Folding coefficient, in base field coordinates
+    pub alpha: Vec<FeltWrapper>,
+    /// Shared next layer the worker writes the folded evaluations into, they are posted back
+    /// when it is `None`
+    pub next_layer: Option<SharedMatrixRef>,
+}
+
 #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct HashingWorkItem {
-    pub data: Vec<Vec<FeltWrapper>>,
+    pub rows: RowBatch,
     pub batch_idx: usize,
+    pub hash_fn: HashFunctionTag,
+}
+
+/// Columns of a trace segment a worker interpolates and extends over the LDE domain
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LdeWorkItem {
+    /// Index of the first column in the segment
+    pub first_column: usize,
+    /// Field the columns are over, the main segment is always over the base field
+    pub field_extension: FieldExtensionTag,
+    /// Columns in base field coordinates
+    pub columns: Vec<Vec<FeltWrapper>>,
+    pub domain_offset: FeltWrapper,
+    pub blowup_factor: usize,
+    /// Shared LDE segment the worker writes the extended columns into, each one as extension
+    /// degree base field columns, they are posted back when it is `None`
+    pub lde: Option<SharedMatrixRef>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -408,17 +467,82 @@ impl Into<Felt> for FeltWrapper {
     }
 }
 
+/// Flattens extension field elements into their base field coordinates, the way they are
+/// serialized and hashed
+pub fn to_base_elements<E: FieldElement<BaseField = Felt>>(elements: &[E]) -> Vec<FeltWrapper> {
+    E::as_base_elements(elements)
+        .iter()
+        .map(FeltWrapper::from)
+        .collect()
+}
+
+/// Reads extension field elements back from their base field coordinates
+pub fn elements_from_base<E: FieldElement<BaseField = Felt>>(
+    coordinates: &[Felt],
+) -> Result<Vec<E>, ProverError> {
+    if coordinates.len() % E::EXTENSION_DEGREE != 0 {
+        return Err(ProverError::Decode(format!(
+            "{} base field elements do not split into elements of degree {}",
+            coordinates.len(),
+            E::EXTENSION_DEGREE
+        )));
+    }
+    let mut bytes = Vec::with_capacity(coordinates.len() * Felt::ELEMENT_BYTES);
+    for coordinate in coordinates {
+        coordinate.write_into(&mut bytes);
+    }
+    let mut reader = SliceReader::new(&bytes);
+    (0..coordinates.len() / E::EXTENSION_DEGREE)
+        .map(|_| E::read_from(&mut reader))
+        .collect::<Result<_, _>>()
+        .map_err(|e| ProverError::Decode(format!("Cannot read field element: {}", e)))
+}
+
+pub fn unwrap_elements(elements: &[FeltWrapper]) -> Vec<Felt> {
+    elements.iter().map(|e| e.0).collect()
+}
+
+/// Field extension of the elements of `E`, the pool workers compute over the same field
+pub fn field_extension_of<E: FieldElement<BaseField = Felt>>() -> FieldExtensionTag {
+    match E::EXTENSION_DEGREE {
+        1 => FieldExtensionTag::None,
+        2 => FieldExtensionTag::Quadratic,
+        _ => FieldExtensionTag::Cubic,
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct HashingResult {
     pub batch_idx: usize,
-    pub hashes: Vec<[u8; 32]>,
+    /// Serialized row digests, their length depends on the hash function
+    pub hashes: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct LdeResult {
+    pub first_column: usize,
+    /// Coefficients of the column polynomials, in base field coordinates
+    pub polys: Vec<Vec<FeltWrapper>>,
+    /// Evaluations of the column polynomials over the LDE domain, in base field coordinates,
+    /// empty when they were written to a shared LDE segment
+    pub lde: Vec<Vec<FeltWrapper>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct FriFoldResult {
+    pub first_row: usize,
+    /// Evaluations of the next layer, one per folded row in base field coordinates, empty when
+    /// they were written to a shared next layer
+    pub evaluations: Vec<FeltWrapper>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct ConstraintComputeResult {
+    /// Step of the first evaluation in the constraint evaluation domain
     pub frag_index: usize,
     pub frag_num: usize,
-    pub constraint_evaluations: Vec<Vec<FeltWrapper>>,
+    /// Evaluations of the fragment in base field coordinates, see `evaluate_fragment`
+    pub evaluations: Vec<FeltWrapper>,
 }
 
 #[wasm_bindgen(getter_with_clone)]
@@ -429,6 +553,67 @@ pub struct ProverOutput {
     pub public_inputs: Vec<u8>,
 }
 
+/// Stages of a proof, named after the `console::time` labels they are measured with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ProvingStage {
+    PreparingInputs,
+    GeneratingTrace,
+    ProveProgramStage1,
+    ProveTraceHashes,
+    CommitAuxTrace,
+    ConstraintEvaluations,
+    CommitConstraints,
+    BuildFriLayers,
+    ProveFinalStage,
+    ProveFull,
+    VerifyProgram,
+}
+
+impl ProvingStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProvingStage::PreparingInputs => "preparing_inputs",
+            ProvingStage::GeneratingTrace => "generating_trace",
+            ProvingStage::ProveProgramStage1 => "prove_program_stage1",
+            ProvingStage::ProveTraceHashes => "prove_trace_hashes",
+            ProvingStage::CommitAuxTrace => "commit_aux_trace",
+            ProvingStage::ConstraintEvaluations => "constraint_evaluations",
+            ProvingStage::CommitConstraints => "commit_constraints",
+            ProvingStage::BuildFriLayers => "build_fri_layers",
+            ProvingStage::ProveFinalStage => "prove_final_stage",
+            ProvingStage::ProveFull => "prove_full",
+            ProvingStage::VerifyProgram => "verify_program",
+        }
+    }
+}
+
+/// Progress within a stage; every stage reports `0` out of `1` when it starts, stages running
+/// on the worker pool then report the jobs completed so far
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProverProgress {
+    pub stage: ProvingStage,
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl From<&ProverProgress> for JsValue {
+    fn from(progress: &ProverProgress) -> Self {
+        let object = Object::new();
+        // setting properties on a fresh object cannot fail
+        let _ = Reflect::set(&object, &"stage".into(), &progress.stage.as_str().into());
+        let _ = Reflect::set(&object, &"completed".into(), &progress.completed.into());
+        let _ = Reflect::set(&object, &"total".into(), &progress.total.into());
+        object.into()
+    }
+}
+
+/// Messages posted by the proving worker to the main thread
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub enum ProverMessage {
+    Progress(ProverProgress),
+    Done(Result<ProverOutput, ProverError>),
+}
+
 #[inline]
 pub fn set_once_logger() {
     static SET_SINGLETONS: Once = Once::new();
@@ -444,15 +629,18 @@ pub fn to_uint8array<T: serde::Serialize>(data: &T) -> Uint8Array {
     Uint8Array::from(serialized.as_slice())
 }
 
-pub fn from_uint8array<T: serde::de::DeserializeOwned>(data: &Uint8Array) -> Result<T, JsValue> {
+pub fn from_uint8array<T: serde::de::DeserializeOwned>(
+    data: &Uint8Array,
+) -> Result<T, ProverError> {
     let bytes = data.to_vec();
-    bincode::deserialize(bytes.as_slice()).map_err(|e| JsValue::from_str(&format!("{}", e)))
+    bincode::deserialize(bytes.as_slice()).map_err(|e| ProverError::Decode(e.to_string()))
 }
 
 #[cfg(test)]
 mod work_item_test {
     use miden::Assembler;
     use miden_core::{Program, ProgramOutputs};
+    use miden_proof_format::field::QuadExtension;
     use miden_stdlib::StdLibrary;
 
     use super::*;
@@ -470,12 +658,13 @@ mod work_item_test {
             .collect();
 
         let work_item = HashingWorkItem {
-            data: converted,
+            rows: RowBatch::Copied(converted),
             batch_idx: 0,
+            hash_fn: HashFunctionTag::Blake3_192,
         };
         let serialized = bincode::serialize(&work_item).unwrap();
         let deserialized: HashingWorkItem = bincode::deserialize(&serialized).unwrap();
-        assert_eq!(work_item.data, deserialized.data);
+        assert_eq!(work_item, deserialized);
     }
 
     #[test]
@@ -493,11 +682,11 @@ mod work_item_test {
             16,
             128,
         );
-        let aux_rand_elements = AuxTraceRandElements(vec![
+        let aux_rand_elements = vec![
             vec![Felt::from(1u64)],
             vec![Felt::from(2u64), Felt::from(3u64)],
             vec![Felt::from(4u64), Felt::from(5u64), Felt::from(6u64)],
-        ]);
+        ];
 
         let constraint_coeffs = ConstraintCompositionCoefficients {
             transition: vec![
@@ -524,8 +713,8 @@ mod work_item_test {
             vec![Felt::from(5u64), Felt::from(6u64)],
         ]));
 
-        let trace_lde_wrapper = TraceLdeWrapper { trace_lde };
-        let trace_lde_serialized = bincode::serialize(&trace_lde_wrapper).unwrap();
+        // the window of a fragment wraps around the end of the LDE
+        let trace_window = TraceLdeWindow::new(&trace_lde, 1, 2);
 
         let computation_fragment = ComputationFragment {
             fragment_offset: 0,
@@ -537,9 +726,14 @@ mod work_item_test {
             trace_info,
             public_inputs,
             proof_options,
-            aux_rand_elements,
-            constraint_coeffs,
-            trace_lde_wrapper: trace_lde_serialized,
+            field_extension: FieldExtensionTag::None,
+            aux_rand_elements: aux_rand_elements
+                .iter()
+                .map(|elements| to_base_elements(elements))
+                .collect(),
+            constraint_coeffs: CompositionCoefficients::new(&constraint_coeffs),
+            trace_rows: TraceLdeRows::Copied(trace_window),
+            evaluation_table: None,
             computation_fragment,
         };
         println!("{}", serde_json::to_string(&work_item).unwrap());
@@ -561,68 +755,28 @@ mod work_item_test {
             deserialized.public_inputs.outputs.stack
         );
         assert_eq!(work_item.proof_options, deserialized.proof_options);
-        assert_eq!(
-            work_item.aux_rand_elements.0,
-            deserialized.aux_rand_elements.0
-        );
-        assert_eq!(
-            work_item.constraint_coeffs.transition,
-            deserialized.constraint_coeffs.transition
-        );
-        assert_eq!(
-            work_item.constraint_coeffs.boundary,
-            deserialized.constraint_coeffs.boundary
-        );
-
-        // trace lde
-        let mut work_item_main_trace = vec![];
-        let trace_lde_wrapper_payload = work_item.trace_lde_wrapper;
-        let trace_lde_wrapper: TraceLdeWrapper =
-            bincode::deserialize(&trace_lde_wrapper_payload).unwrap();
-        for i in 0..work_item
-            .trace_lde_wrapper
-            .trace_lde
-            .main_segment_lde
-            .num_rows()
-        {
-            let mut r = vec![];
-            work_item
-                .trace_lde
-                .main_segment_lde
-                .read_row_into(i, &mut r);
-            work_item_main_trace.push(r);
-        }
-        let mut deserialized_main_trace = vec![];
-        for i in 0..trace_lde_wrapper.trace_lde.main_segment_lde.num_rows() {
-            let mut r = vec![];
-            trace_lde_wrapper
-                .trace_lde
-                .main_segment_lde
-                .read_row_into(i, &mut r);
-            deserialized_main_trace.push(r);
-        }
-        assert_eq!(work_item_main_trace, deserialized_main_trace);
-        let mut work_item_aux_trace = vec![];
-        for aux_segment in work_item.trace_lde.aux_segment_ldes.iter() {
-            let mut aux_segment_rows = vec![];
-            for i in 0..aux_segment.num_rows() {
-                let mut r = vec![];
-                aux_segment.read_row_into(i, &mut r);
-                aux_segment_rows.push(r);
-            }
-            work_item_aux_trace.push(aux_segment_rows);
-        }
-        let mut deserialized_aux_trace = vec![];
-        for aux_segment in deserialized.trace_lde.aux_segment_ldes.iter() {
-            let mut aux_segment_rows = vec![];
-            for i in 0..aux_segment.num_rows() {
-                let mut r = vec![];
-                aux_segment.read_row_into(i, &mut r);
-                aux_segment_rows.push(r);
-            }
-            deserialized_aux_trace.push(aux_segment_rows);
-        }
-        assert_eq!(work_item_aux_trace, deserialized_aux_trace);
+        assert_eq!(work_item.field_extension, deserialized.field_extension);
+        assert_eq!(work_item.aux_rand_elements, deserialized.aux_rand_elements);
+        let decoded = deserialized.constraint_coeffs.decode::<Felt>().unwrap();
+        assert_eq!(constraint_coeffs.transition, decoded.transition);
+        assert_eq!(constraint_coeffs.boundary, decoded.boundary);
+
+        // trace lde window
+        assert_eq!(work_item.trace_rows, deserialized.trace_rows);
+        assert_eq!(work_item.evaluation_table, deserialized.evaluation_table);
+        let window = match &deserialized.trace_rows {
+            TraceLdeRows::Copied(window) => window,
+            trace_rows => panic!("expected copied trace rows, got {:?}", trace_rows),
+        };
+        let felts = |elements: &[u64]| -> Vec<FeltWrapper> {
+            elements
+                .iter()
+                .map(|e| FeltWrapper(Felt::new(*e)))
+                .collect()
+        };
+        assert_eq!(window.num_rows, 2);
+        assert_eq!(window.main_rows, felts(&[2, 11, 1, 2]));
+        assert_eq!(window.aux_rows, felts(&[3, 5, 6, 2, 4, 5]));
 
         // computation fragment
         assert_eq!(
@@ -631,6 +785,35 @@ mod work_item_test {
         );
     }
 
+    #[test]
+    fn test_extension_elements_round_trip() {
+        type E = QuadExtension<Felt>;
+        let element = |a: u64, b: u64| E::new(Felt::new(a), Felt::new(b));
+        let coefficients = ConstraintCompositionCoefficients {
+            transition: vec![(element(1, 2), element(3, 4))],
+            boundary: vec![
+                (element(5, 6), element(7, 8)),
+                (element(9, 10), element(11, 12)),
+            ],
+        };
+        let flattened = CompositionCoefficients::new(&coefficients);
+        assert_eq!(flattened.transition.len(), 4);
+        let decoded = flattened.decode::<E>().unwrap();
+        assert_eq!(coefficients.transition, decoded.transition);
+        assert_eq!(coefficients.boundary, decoded.boundary);
+
+        // an element is split into its coordinates, which read back into the element
+        let elements = vec![element(1, 2), element(u64::MAX - 1, 0)];
+        let coordinates = unwrap_elements(&to_base_elements(&elements));
+        assert_eq!(coordinates, E::as_base_elements(&elements));
+        assert_eq!(elements_from_base::<E>(&coordinates).unwrap(), elements);
+        assert!(matches!(
+            elements_from_base::<E>(&coordinates[1..]),
+            Err(ProverError::Decode(_))
+        ));
+        assert_eq!(field_extension_of::<E>(), FieldExtensionTag::Quadratic);
+    }
+
     /// Generates a program to compute the `n`-th term of Fibonacci sequence
     fn generate_fibonacci_program(n: usize) -> Program {
         // the program is a simple repetition of 4 stack operations:
@@ -653,4 +836,44 @@ mod work_item_test {
             .compile(&program)
             .unwrap()
     }
+
+    #[test]
+    fn test_work_partition() {
+        let derived = WorkPartition::default();
+        // every row is covered by a batch, even when the batches do not divide the rows
+        let chunk_size = derived.chunk_size(100_000, 6).unwrap();
+        assert!(chunk_size * 6 * HASHING_BATCHES_PER_WORKER >= 100_000);
+        assert_eq!(derived.chunk_size(10, 4).unwrap(), MIN_HASHING_CHUNK_SIZE);
+        assert_eq!(derived.num_fragments(1 << 16, 6).unwrap(), 8);
+        assert_eq!(derived.num_fragments(1 << 11, 16).unwrap(), 2);
+        assert_eq!(derived.num_fragments(1 << 8, 16).unwrap(), 1);
+
+        let requested = WorkPartition {
+            chunk_size: Some(1000),
+            num_fragments: Some(4),
+        };
+        assert_eq!(requested.chunk_size(100_000, 6).unwrap(), 1000);
+        assert_eq!(requested.num_fragments(1 << 8, 16).unwrap(), 4);
+        assert!(matches!(
+            requested.num_fragments(2, 16),
+            Err(ProverError::InvalidInput(_))
+        ));
+
+        let invalid = [
+            WorkPartition {
+                chunk_size: Some(0),
+                num_fragments: None,
+            },
+            WorkPartition {
+                chunk_size: None,
+                num_fragments: Some(6),
+            },
+        ];
+        for partition in invalid {
+            assert!(matches!(
+                partition.validate(),
+                Err(ProverError::InvalidInput(_))
+            ));
+        }
+    }
 }
@@ -1,7 +1,8 @@
 import init from "miden-wasm";
-import { constraint_entry_point } from "miden-wasm";
+import { pool_worker_entry_point } from "miden-wasm";
 
 self.onmessage = event => {
+    console.debug("Pool worker received init:", event.data);
     let initialised = init().catch(err => {
         // Propagate to main `onerror`:
         setTimeout(() => {
@@ -14,6 +15,6 @@ self.onmessage = event => {
     self.onmessage = async event => {
         // This will queue further commands up until the module is fully initialised:
         await initialised;
-        constraint_entry_point(event);
+        pool_worker_entry_point(event);
     };
 };
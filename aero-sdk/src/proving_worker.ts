@@ -1,17 +1,14 @@
 import init, { MidenProverAsyncWorker } from "miden-wasm";
 import { proving_entry_point } from "miden-wasm";
 
-async function initialize(): Promise<MidenProverAsyncWorker> {
-    await init();
-    return new MidenProverAsyncWorker();
-}
-
-let full_init = initialize();
+let initialised = init();
 let prover: MidenProverAsyncWorker = null;
 
+// the first message carries the JSON encoded pool configuration
 self.onmessage = async event => {
     console.trace("Proving worker received init:", event.data);
-    prover = await full_init;
+    await initialised;
+    prover = new MidenProverAsyncWorker(event.data);
     self.onmessage = async event => {
         // maintain the reference to the worker pool
         let new_prover = prover.reset();
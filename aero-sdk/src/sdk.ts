@@ -1,19 +1,110 @@
-import init, { MidenProver, start } from "miden-wasm";
+import init, { MidenProver, start, verify as verify_proof } from "miden-wasm";
 import { MidenProgram, MidenProgramInputs } from "./proto-ts/miden_prover";
 import { StarkProof } from "./proto-ts/stark_proof";
 import { MidenProgramOutputs, MidenPublicInputs } from "./proto-ts/miden_vm";
 import { FieldExtension, HashFunction, PrimeField, ProofOptions } from "./proto-ts/context";
-import "./hashing_worker";
+import "./pool_worker";
 import "./proving_worker";
-import "./constraints_worker";
 
+export type ProverErrorCode =
+    | "DECODE_ERROR"
+    | "INVALID_INPUT"
+    | "ASSEMBLY_ERROR"
+    | "EXECUTION_ERROR"
+    | "PROVING_ERROR"
+    | "VERIFICATION_ERROR"
+    | "WORKER_ERROR"
+    | "CANCELLED";
+
+// `prove`, `prove_sequential` and `verify` reject with this shape, e.g. an ASSEMBLY_ERROR
+// carries the assembler message pointing at the offending MASM
+export interface ProverError {
+    code: ProverErrorCode;
+    message: string;
+}
+
+export type ProvingStage =
+    | "preparing_inputs"
+    | "generating_trace"
+    | "prove_program_stage1"
+    | "prove_trace_hashes"
+    | "commit_aux_trace"
+    | "constraint_evaluations"
+    | "commit_constraints"
+    | "build_fri_layers"
+    | "prove_final_stage"
+    | "prove_full"
+    | "verify_program";
+
+// `completed` out of `total` jobs dispatched to the worker pool, e.g. trace column ranges,
+// hashing batches or constraint fragments; every stage reports 0 out of 1 when it starts
+export interface ProverProgress {
+    stage: ProvingStage;
+    completed: number;
+    total: number;
+}
+
+// a script URL, or a function returning one, e.g. `() => URL.createObjectURL(blob)` for
+// scripts that are not served from a fixed path
+export type WorkerSource = string | (() => string);
+
+// the worker count defaults to `navigator.hardwareConcurrency`, URLs to the scripts emitted
+// next to the page; pool workers are only spawned once jobs are dispatched to them. A worker
+// that throws or holds a job longer than `jobTimeoutMs` (0, disabled, by default) is restarted and
+// its jobs resubmitted up to `jobRetries` (2) times before the proof rejects with a WORKER_ERROR
+export interface PoolConfig {
+    workers?: number;
+    provingWorkerUrl?: WorkerSource;
+    poolWorkerUrl?: WorkerSource;
+    jobTimeoutMs?: number;
+    jobRetries?: number;
+}
+
+// rows hashed per job and constraint evaluation fragments, a power of two; both are derived
+// from the trace size and the worker count when unset
+export interface WorkPartition {
+    chunkSize?: number;
+    numFragments?: number;
+}
+
+var pool_config: PoolConfig = {};
+var progress_callback: ((progress: ProverProgress) => void) | null = null;
 var miden_prover: MidenProver = null;
-init().then(() => {
+const sdk_init = init().then(() => {
     start();
-    miden_prover = new MidenProver();
     console.log("finished sdk init");
 });
 
+function resolve_source(source?: WorkerSource): string | undefined {
+    return typeof source === "function" ? source() : source;
+}
+
+// the prover and its proving worker are created on the first proof
+async function get_prover(): Promise<MidenProver> {
+    await sdk_init;
+    if (miden_prover === null) {
+        miden_prover = new MidenProver({
+            workers: pool_config.workers,
+            provingWorkerUrl: resolve_source(pool_config.provingWorkerUrl),
+            poolWorkerUrl: resolve_source(pool_config.poolWorkerUrl),
+            jobTimeoutMs: pool_config.jobTimeoutMs,
+            jobRetries: pool_config.jobRetries,
+        });
+        miden_prover.set_progress_callback(progress_callback);
+    }
+    return miden_prover;
+}
+
+// replaces the pool configuration, a running prover is stopped and recreated on the next proof
+export function configure(config: PoolConfig) {
+    pool_config = config;
+    if (miden_prover !== null) {
+        miden_prover.free();
+        miden_prover = null;
+    }
+}
+
+// base field, quadratic and cubic field extension proofs are all spread over the worker pool
 export async function prove(program: MidenProgram, inputs: MidenProgramInputs, options: ProofOptions = ProofOptions.fromJSON({
     numQueries: 27,
     blowupFactor: 8,
@@ -23,11 +114,12 @@ export async function prove(program: MidenProgram, inputs: MidenProgramInputs, o
     friFoldingFactor: 8,
     friMaxRemainderSize: 256,
     primeField: PrimeField.GOLDILOCKS,
-})): Promise<[StarkProof, MidenProgramOutputs, MidenPublicInputs]> {
+}), partition: WorkPartition = {}): Promise<[StarkProof, MidenProgramOutputs, MidenPublicInputs]> {
     let program_bytes = MidenProgram.encode(program).finish();
     let input_bytes = MidenProgramInputs.encode(inputs).finish();
     let option_bytes = ProofOptions.encode(options).finish();
-    let proof_outputs = await miden_prover.prove(program_bytes, input_bytes, option_bytes, 1024, true);
+    let prover = await get_prover();
+    let proof_outputs = await prover.prove(program_bytes, input_bytes, option_bytes, partition.chunkSize, partition.numFragments, true);
 
     let proof = StarkProof.decode(proof_outputs.proof);
     let outputs = MidenProgramOutputs.decode(proof_outputs.program_outputs);
@@ -49,7 +141,8 @@ export async function prove_sequential(program: MidenProgram, inputs: MidenProgr
     let program_bytes = MidenProgram.encode(program).finish();
     let input_bytes = MidenProgramInputs.encode(inputs).finish();
     let option_bytes = ProofOptions.encode(options).finish();
-    let proof_outputs = await miden_prover.prove_sequential(program_bytes, input_bytes, option_bytes, true);
+    let prover = await get_prover();
+    let proof_outputs = await prover.prove_sequential(program_bytes, input_bytes, option_bytes, true);
 
     let proof = StarkProof.decode(proof_outputs.proof);
     let outputs = MidenProgramOutputs.decode(proof_outputs.program_outputs);
@@ -58,6 +151,26 @@ export async function prove_sequential(program: MidenProgram, inputs: MidenProgr
     return [proof, outputs, pub_inputs];
 }
 
+export function on_progress(callback: ((progress: ProverProgress) => void) | null) {
+    progress_callback = callback;
+    if (miden_prover !== null) {
+        miden_prover.set_progress_callback(callback);
+    }
+}
+
+// rejects the running `prove` call with a CANCELLED error and restarts the proving worker
+export function cancel() {
+    if (miden_prover !== null) {
+        miden_prover.cancel();
+    }
+}
+
+export function verify(proof: StarkProof, pub_inputs: MidenPublicInputs): boolean {
+    let proof_bytes = StarkProof.encode(proof).finish();
+    let pub_input_bytes = MidenPublicInputs.encode(pub_inputs).finish();
+    return verify_proof(proof_bytes, pub_input_bytes);
+}
+
 export function uint8ArrayToU64LE(arr: Uint8Array): BigInt {
     if (arr.length !== 8) {
         throw new Error('Uint8Array must have exactly 8 elements to be converted to u64.');
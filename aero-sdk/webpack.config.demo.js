@@ -28,11 +28,11 @@ module.exports = {
                 },
             },
             {
-                test: /src\/hashing_worker\.ts$/,
+                test: /src\/pool_worker\.ts$/,
                 use: [{
                     loader: 'worker-loader',
                     options: {
-                        filename: 'hashing_worker.js',
+                        filename: 'pool_worker.js',
                     }
                 }, { loader: 'ts-loader', }],
             },
@@ -45,15 +45,6 @@ module.exports = {
                     }
                 }, { loader: 'ts-loader', }],
             },
-            {
-                test: /src\/constraints_worker\.ts$/,
-                use: [{
-                    loader: 'worker-loader',
-                    options: {
-                        filename: 'constraints_worker.js',
-                    }
-                }, { loader: 'ts-loader', }],
-            },
         ]
     },
     plugins: [
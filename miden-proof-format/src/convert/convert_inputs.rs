@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use super::SdkConversionError;
+use crate::sdk;
+use crate::sdk::{advice_set, MidenProgram, MidenProgramInputs};
+use miden::{Assembler, FieldExtension, HashFunction, Program, ProgramInputs, ProofOptions};
+use miden_core::{AdviceSet, Felt, Word};
+use miden_stdlib::StdLibrary;
+
+impl TryFrom<MidenProgramInputs> for ProgramInputs {
+    type Error = SdkConversionError;
+
+    fn try_from(value: MidenProgramInputs) -> Result<Self, Self::Error> {
+        let advice_map = value
+            .advice_map
+            .iter()
+            .map(|entry| {
+                let key = <[u8; 32]>::try_from(entry.key.as_slice()).map_err(|_| {
+                    SdkConversionError::InvalidInputs(format!(
+                        "advice map key of {} bytes",
+                        entry.key.len()
+                    ))
+                })?;
+                Ok((key, entry.values.iter().map(|v| Felt::new(*v)).collect()))
+            })
+            .collect::<Result<BTreeMap<_, _>, SdkConversionError>>()?;
+        let advice_sets = value
+            .advice_sets
+            .iter()
+            .map(|set| set.try_into())
+            .collect::<Result<Vec<AdviceSet>, _>>()?;
+
+        ProgramInputs::with_advice_map(
+            &value.stack_init,
+            &value.advice_tape,
+            advice_map,
+            advice_sets,
+        )
+        .map_err(|err| SdkConversionError::InvalidInputs(format!("{:?}", err)))
+    }
+}
+
+impl TryFrom<&sdk::AdviceSet> for AdviceSet {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::AdviceSet) -> Result<Self, Self::Error> {
+        let invalid_set = |err| SdkConversionError::InvalidInputs(format!("{:?}", err));
+        match value
+            .set
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField("advice_set.set"))?
+        {
+            advice_set::Set::MerkleTree(tree) => {
+                AdviceSet::new_merkle_tree(words_from_sdk(&tree.leaves)?).map_err(invalid_set)
+            }
+            advice_set::Set::SparseMerkleTree(tree) => AdviceSet::new_sparse_merkle_tree(
+                tree.keys.clone(),
+                words_from_sdk(&tree.values)?,
+                tree.depth,
+            )
+            .map_err(invalid_set),
+            advice_set::Set::MerklePathSet(path_set) => {
+                let mut set =
+                    AdviceSet::new_merkle_path_set(path_set.depth).map_err(invalid_set)?;
+                for path in path_set.paths.iter() {
+                    let value = path
+                        .value
+                        .as_ref()
+                        .ok_or(SdkConversionError::MissingField("merkle_path.value"))?;
+                    set.add_path(path.index, value.try_into()?, words_from_sdk(&path.path)?)
+                        .map_err(invalid_set)?;
+                }
+                Ok(set)
+            }
+        }
+    }
+}
+
+impl TryFrom<&sdk::Word> for Word {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::Word) -> Result<Self, Self::Error> {
+        let elements: [u64; 4] = value.elements.as_slice().try_into().map_err(|_| {
+            SdkConversionError::InvalidInputs(format!("word of {} elements", value.elements.len()))
+        })?;
+        Ok(elements.map(Felt::new))
+    }
+}
+
+fn words_from_sdk(words: &[sdk::Word]) -> Result<Vec<Word>, SdkConversionError> {
+    words.iter().map(|w| w.try_into()).collect()
+}
+
+impl From<u64> for sdk::FieldElement {
+    fn from(value: u64) -> Self {
+        Self {
+            element: value.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&sdk::FieldElement> for u64 {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::FieldElement) -> Result<Self, Self::Error> {
+        let bytes: [u8; 8] = value
+            .element
+            .as_slice()
+            .try_into()
+            .map_err(|_| SdkConversionError::InvalidFieldElement)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+impl TryFrom<MidenProgram> for Program {
+    type Error = SdkConversionError;
+
+    fn try_from(value: MidenProgram) -> Result<Self, Self::Error> {
+        Assembler::new()
+            .with_module_provider(StdLibrary::default())
+            .compile(&value.program)
+            .map_err(|err| SdkConversionError::Assembly(err.to_string()))
+    }
+}
+
+impl Into<HashFunction> for sdk::HashFunction {
+    fn into(self) -> HashFunction {
+        match self {
+            sdk::HashFunction::Blake2s => HashFunction::Blake2s_256,
+            sdk::HashFunction::Blake3192 => HashFunction::Blake3_192,
+            sdk::HashFunction::Blake3256 => HashFunction::Blake3_256,
+            sdk::HashFunction::Sha3256 => HashFunction::Sha3_256,
+        }
+    }
+}
+
+impl Into<FieldExtension> for sdk::FieldExtension {
+    fn into(self) -> FieldExtension {
+        match self {
+            sdk::FieldExtension::None => FieldExtension::None,
+            sdk::FieldExtension::Quadratic => FieldExtension::Quadratic,
+            sdk::FieldExtension::Cubic => FieldExtension::Cubic,
+        }
+    }
+}
+
+const MAX_NUM_QUERIES: u32 = 128;
+const MAX_BLOWUP_FACTOR: u32 = 128;
+const MAX_GRINDING_FACTOR: u32 = 32;
+const MAX_FRI_FOLDING_FACTOR: u32 = 16;
+const MAX_FRI_REMAINDER_SIZE: u32 = 1024;
+
+fn check_option(
+    name: &str,
+    value: u32,
+    min: u32,
+    max: u32,
+    power_of_two: bool,
+) -> Result<(), SdkConversionError> {
+    if value < min || value > max || (power_of_two && !value.is_power_of_two()) {
+        return Err(SdkConversionError::Deserialization(format!(
+            "{} {} is not {}between {} and {}",
+            name,
+            value,
+            if power_of_two { "a power of two " } else { "" },
+            min,
+            max
+        )));
+    }
+    Ok(())
+}
+
+impl TryFrom<sdk::ProofOptions> for ProofOptions {
+    type Error = SdkConversionError;
+
+    fn try_from(value: sdk::ProofOptions) -> Result<Self, Self::Error> {
+        // the generated getters silently fall back to the default for unknown values
+        let hash_fn = sdk::HashFunction::from_i32(value.hash_fn).ok_or_else(|| {
+            SdkConversionError::Unsupported(format!("hash function {}", value.hash_fn))
+        })?;
+        let field_extension =
+            sdk::FieldExtension::from_i32(value.field_extension).ok_or_else(|| {
+                SdkConversionError::Unsupported(format!(
+                    "field extension {}",
+                    value.field_extension
+                ))
+            })?;
+
+        // `ProofOptions::new` asserts these bounds
+        check_option(
+            "number of queries",
+            value.num_queries,
+            1,
+            MAX_NUM_QUERIES,
+            false,
+        )?;
+        check_option(
+            "blowup factor",
+            value.blowup_factor,
+            2,
+            MAX_BLOWUP_FACTOR,
+            true,
+        )?;
+        check_option(
+            "grinding factor",
+            value.grinding_factor,
+            0,
+            MAX_GRINDING_FACTOR,
+            false,
+        )?;
+        check_option(
+            "FRI folding factor",
+            value.fri_folding_factor,
+            2,
+            MAX_FRI_FOLDING_FACTOR,
+            true,
+        )?;
+        check_option(
+            "FRI max remainder size",
+            value.fri_max_remainder_size,
+            value.fri_folding_factor * 2,
+            MAX_FRI_REMAINDER_SIZE,
+            true,
+        )?;
+
+        Ok(ProofOptions::new(
+            value.num_queries as usize,
+            value.blowup_factor as usize,
+            value.grinding_factor,
+            hash_fn.into(),
+            field_extension.into(),
+            value.fri_folding_factor as usize,
+            value.fri_max_remainder_size as usize,
+        ))
+    }
+}
@@ -0,0 +1,1038 @@
+use super::SdkConversionError;
+use crate::sdk;
+use miden::{FieldExtension, HashFunction, StarkProof};
+use miden_air::{Felt, FieldElement, ProcessorAir, PublicInputs, StarkField};
+use miden_core::{
+    utils::{Deserializable, Serializable, SliceReader},
+    ProgramOutputs,
+};
+use winter_air::{
+    proof::{Commitments, Context, OodFrame, Queries, Table},
+    Air, EvaluationFrame, ProofOptions, TraceInfo, TraceLayout,
+};
+use winter_crypto::{BatchMerkleProof, Digest, ElementHasher, Hasher};
+use winter_fri::{FriProof, FriProofLayer};
+use winter_verifier::{math::log2, ConstraintQueries, TraceQueries};
+
+use crate::{with_field_extension, with_hasher};
+
+impl IntoSdk<StarkProof, &ProcessorAir> for sdk::StarkProof {
+    fn into_sdk(input: StarkProof, params: &ProcessorAir) -> Self {
+        Self {
+            ood_frame: Some(sdk::OodFrame::into_sdk(input.ood_frame, params)),
+            context: Some(input.context.into()),
+            commitments: Some(sdk::Commitments::into_sdk(input.commitments, params)),
+            trace_queries: Some(sdk::TraceQueries::into_sdk(input.trace_queries, params)),
+            constraint_queries: Some(sdk::ConstraintQueries::into_sdk(
+                input.constraint_queries,
+                params,
+            )),
+            fri_proof: Some(sdk::FriProof::into_sdk(input.fri_proof, params)),
+            pow_nonce: input.pow_nonce,
+        }
+    }
+}
+
+pub trait IntoSdk<Input, Parameters> {
+    fn into_sdk(input: Input, params: Parameters) -> Self;
+}
+
+impl IntoSdk<OodFrame, &ProcessorAir> for sdk::OodFrame {
+    fn into_sdk(input: OodFrame, params: &ProcessorAir) -> Self {
+        with_field_extension!(params.options().field_extension(), |E| {
+            ood_frame_into_sdk::<E>(input, params)
+        })
+    }
+}
+
+fn ood_frame_into_sdk<E: FieldElement<BaseField = Felt>>(
+    input: OodFrame,
+    params: &ProcessorAir,
+) -> sdk::OodFrame {
+    let main_trace_width = params.trace_layout().main_trace_width();
+    let aux_trace_width = params.trace_layout().aux_trace_width();
+    let num_evaluations = params.ce_blowup_factor();
+    let (ood_main_trace_frame, ood_aux_trace_frame, ood_constraint_evaluations) = input
+        .parse::<E>(main_trace_width, aux_trace_width, num_evaluations)
+        .unwrap();
+
+    sdk::OodFrame {
+        main_frame: Some(ood_main_trace_frame.into()),
+        aux_frame: ood_aux_trace_frame.map(|f| f.into()),
+        evaluations: ood_constraint_evaluations
+            .iter()
+            .map(|e| e.into())
+            .collect(),
+    }
+}
+
+// extension field elements are encoded as the concatenation of their base field coefficients
+impl<E: FieldElement> From<&E> for sdk::FieldElement {
+    fn from(element: &E) -> Self {
+        Self {
+            element: element.to_bytes(),
+        }
+    }
+}
+
+impl<E: FieldElement> From<EvaluationFrame<E>> for sdk::EvaluationFrame {
+    fn from(frame: EvaluationFrame<E>) -> Self {
+        let current = frame.current().iter().map(|e| e.into()).collect::<Vec<_>>();
+        let next = frame.next().iter().map(|e| e.into()).collect::<Vec<_>>();
+
+        Self { current, next }
+    }
+}
+
+impl From<Context> for sdk::Context {
+    fn from(value: Context) -> Self {
+        let binding = value.get_trace_info();
+        let trace_meta = binding.meta();
+        let field_modulus = sdk::FieldElement {
+            element: value.field_modulus_bytes().to_vec(),
+        };
+
+        Self {
+            trace_layout: Some(value.trace_layout().into()),
+            trace_length: value.trace_length() as u64,
+            trace_meta: trace_meta.to_vec(),
+            field_modulus: Some(field_modulus),
+            options: Some(value.options().into()),
+        }
+    }
+}
+
+impl From<&TraceLayout> for sdk::TraceLayout {
+    fn from(layout: &TraceLayout) -> Self {
+        let mut aux_segment_widths = Vec::new();
+        let mut aux_segment_rands = Vec::new();
+
+        for i in 0..layout.num_aux_segments() {
+            aux_segment_widths.push(layout.get_aux_segment_width(i) as u64);
+            aux_segment_rands.push(layout.get_aux_segment_rand_elements(i) as u64);
+        }
+
+        Self {
+            main_segment_width: layout.main_trace_width() as u64,
+            aux_segment_widths,
+            aux_segment_rands,
+            num_aux_segments: layout.num_aux_segments() as u64,
+        }
+    }
+}
+
+impl From<&ProofOptions> for sdk::ProofOptions {
+    fn from(value: &ProofOptions) -> Self {
+        let hash_fn: sdk::HashFunction = value.hash_fn().into();
+        let field_extension: sdk::FieldExtension = value.field_extension().into();
+        let fri_options = value.to_fri_options();
+
+        Self {
+            num_queries: value.num_queries() as u32,
+            blowup_factor: value.blowup_factor() as u32,
+            grinding_factor: value.grinding_factor(),
+            hash_fn: hash_fn.into(),
+            field_extension: field_extension.into(),
+            fri_folding_factor: fri_options.folding_factor() as u32,
+            fri_max_remainder_size: fri_options.max_remainder_size() as u32,
+            // this should be configurable
+            prime_field: sdk::PrimeField::Goldilocks.into(),
+        }
+    }
+}
+
+impl From<HashFunction> for sdk::HashFunction {
+    fn from(value: HashFunction) -> Self {
+        match value {
+            HashFunction::Blake2s_256 => Self::Blake2s,
+            HashFunction::Blake3_192 => Self::Blake3192,
+            HashFunction::Blake3_256 => Self::Blake3256,
+            HashFunction::Sha3_256 => Self::Sha3256,
+        }
+    }
+}
+
+impl From<FieldExtension> for sdk::FieldExtension {
+    fn from(value: FieldExtension) -> Self {
+        match value {
+            FieldExtension::None => Self::None,
+            FieldExtension::Quadratic => Self::Quadratic,
+            FieldExtension::Cubic => Self::Cubic,
+        }
+    }
+}
+
+fn digest_into_sdk<D: Digest>(digest: &D) -> sdk::Digest {
+    sdk::Digest {
+        data: digest.to_bytes(),
+    }
+}
+
+impl IntoSdk<Commitments, &ProcessorAir> for sdk::Commitments {
+    fn into_sdk(input: Commitments, params: &ProcessorAir) -> Self {
+        with_hasher!(params.options().hash_fn(), |H| {
+            commitments_into_sdk::<H>(input, params)
+        })
+    }
+}
+
+fn commitments_into_sdk<H: Hasher>(input: Commitments, params: &ProcessorAir) -> sdk::Commitments {
+    let num_trace_segments = params.trace_layout().num_segments();
+    let lde_domain_size = params.lde_domain_size();
+    let fri_options = params.options().to_fri_options();
+    let num_fri_layers = fri_options.num_fri_layers(lde_domain_size);
+
+    let (trace_commitments, constraint_commitment, fri_commitments) = input
+        .parse::<H>(num_trace_segments, num_fri_layers)
+        .unwrap();
+
+    sdk::Commitments {
+        trace_roots: trace_commitments.iter().map(digest_into_sdk).collect(),
+        constraint_root: Some(digest_into_sdk(&constraint_commitment)),
+        fri_roots: fri_commitments.iter().map(digest_into_sdk).collect(),
+    }
+}
+
+impl<E: FieldElement> From<Table<E>> for sdk::Table {
+    fn from(table: Table<E>) -> Self {
+        // table saved as a single dim array
+        let data = table.data().iter().map(|e| e.into()).collect::<Vec<_>>();
+
+        Self {
+            n_rows: table.num_rows() as u32,
+            n_cols: table.num_columns() as u32,
+            elements: data,
+        }
+    }
+}
+
+impl IntoSdk<Vec<Queries>, &ProcessorAir> for sdk::TraceQueries {
+    fn into_sdk(input: Vec<Queries>, params: &ProcessorAir) -> Self {
+        with_hasher!(params.options().hash_fn(), |H| {
+            with_field_extension!(params.options().field_extension(), |E| {
+                trace_queries_into_sdk::<E, H>(input, params)
+            })
+        })
+    }
+}
+
+fn trace_queries_into_sdk<E, H>(input: Vec<Queries>, params: &ProcessorAir) -> sdk::TraceQueries
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+{
+    let trace_queries = TraceQueries::<E, H>::new(input, params).unwrap();
+
+    sdk::TraceQueries {
+        main_states: Some(trace_queries.main_states.into()),
+        aux_states: trace_queries.aux_states.map(|t| t.into()),
+        query_proofs: trace_queries
+            .query_proofs
+            .iter()
+            .map(|p| p.into())
+            .collect(),
+    }
+}
+
+impl IntoSdk<Queries, &ProcessorAir> for sdk::ConstraintQueries {
+    fn into_sdk(input: Queries, params: &ProcessorAir) -> Self {
+        with_hasher!(params.options().hash_fn(), |H| {
+            with_field_extension!(params.options().field_extension(), |E| {
+                constraint_queries_into_sdk::<E, H>(input, params)
+            })
+        })
+    }
+}
+
+fn constraint_queries_into_sdk<E, H>(
+    input: Queries,
+    params: &ProcessorAir,
+) -> sdk::ConstraintQueries
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+{
+    let constraint_queries = ConstraintQueries::<E, H>::new(input, params).unwrap();
+
+    sdk::ConstraintQueries {
+        evaluations: Some(constraint_queries.evaluations.into()),
+        query_proof: Some((&constraint_queries.query_proofs).into()),
+    }
+}
+
+impl IntoSdk<FriProof, &ProcessorAir> for sdk::FriProof {
+    fn into_sdk(proof: FriProof, params: &ProcessorAir) -> Self {
+        with_hasher!(params.options().hash_fn(), |H| {
+            with_field_extension!(params.options().field_extension(), |E| {
+                fri_proof_into_sdk::<E, H>(proof, params)
+            })
+        })
+    }
+}
+
+fn fri_proof_into_sdk<E, H>(proof: FriProof, params: &ProcessorAir) -> sdk::FriProof
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+{
+    let num_partitions = log2(proof.num_partitions());
+    let (queries_values, proofs) = proof
+        .clone()
+        .parse_layers::<H, E>(
+            params.lde_domain_size(),
+            params.options().to_fri_options().folding_factor(),
+        )
+        .unwrap();
+
+    let layers = proofs
+        .iter()
+        .zip(queries_values)
+        .map(|(p, q)| sdk::FriProofLayer {
+            values: q.iter().map(|e| e.into()).collect::<Vec<_>>(),
+            proofs: Some(p.into()),
+        })
+        .collect();
+
+    let remainder = proof
+        .parse_remainder::<E>()
+        .unwrap()
+        .iter()
+        .map(|e| e.into())
+        .collect();
+
+    sdk::FriProof {
+        layers,
+        remainder,
+        num_partitions,
+    }
+}
+
+impl From<ProgramOutputs> for sdk::MidenProgramOutputs {
+    fn from(outputs: ProgramOutputs) -> Self {
+        Self {
+            stack: outputs.stack().iter().map(|e| e.clone().into()).collect(),
+            overflow_addrs: outputs
+                .overflow_addrs()
+                .iter()
+                .map(|e| e.clone().into())
+                .collect(),
+        }
+    }
+}
+
+impl From<PublicInputs> for sdk::MidenPublicInputs {
+    fn from(inputs: PublicInputs) -> Self {
+        Self {
+            program_hash: Some(sdk::Digest {
+                data: inputs.program_hash.to_bytes(),
+            }),
+            stack_inputs: inputs.stack_inputs.iter().map(|e| e.into()).collect(),
+            outputs: Some(inputs.outputs.into()),
+        }
+    }
+}
+
+impl<H: Hasher> From<&BatchMerkleProof<H>> for sdk::BatchMerkleProof {
+    fn from(proof: &BatchMerkleProof<H>) -> Self {
+        let leaves = proof.leaves.iter().map(digest_into_sdk).collect();
+
+        let nodes = proof
+            .nodes
+            .iter()
+            .map(|e| sdk::BatchMerkleProofLayer {
+                nodes: e.iter().map(digest_into_sdk).collect(),
+            })
+            .collect();
+
+        Self {
+            leaves,
+            nodes,
+            depth: proof.depth as u32,
+        }
+    }
+}
+
+// SDK -> NATIVE CONVERSIONS
+// ================================================================================================
+
+pub trait TryFromSdk<Input, Parameters>: Sized {
+    fn try_from_sdk(input: Input, params: Parameters) -> Result<Self, SdkConversionError>;
+}
+
+impl TryFrom<sdk::StarkProof> for StarkProof {
+    type Error = SdkConversionError;
+
+    fn try_from(proof: sdk::StarkProof) -> Result<Self, Self::Error> {
+        let context: Context = proof
+            .context
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField("context"))?
+            .try_into()?;
+
+        let commitments = Commitments::try_from_sdk(
+            proof
+                .commitments
+                .ok_or(SdkConversionError::MissingField("commitments"))?,
+            &context,
+        )?;
+        let trace_queries = Vec::<Queries>::try_from_sdk(
+            proof
+                .trace_queries
+                .ok_or(SdkConversionError::MissingField("trace_queries"))?,
+            &context,
+        )?;
+        let constraint_queries = Queries::try_from_sdk(
+            proof
+                .constraint_queries
+                .ok_or(SdkConversionError::MissingField("constraint_queries"))?,
+            &context,
+        )?;
+        let ood_frame = OodFrame::try_from_sdk(
+            proof
+                .ood_frame
+                .ok_or(SdkConversionError::MissingField("ood_frame"))?,
+            &context,
+        )?;
+        let fri_proof = FriProof::try_from_sdk(
+            proof
+                .fri_proof
+                .ok_or(SdkConversionError::MissingField("fri_proof"))?,
+            &context,
+        )?;
+
+        Ok(StarkProof {
+            context,
+            commitments,
+            trace_queries,
+            constraint_queries,
+            ood_frame,
+            fri_proof,
+            pow_nonce: proof.pow_nonce,
+        })
+    }
+}
+
+impl TryFrom<&sdk::FieldElement> for Felt {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::FieldElement) -> Result<Self, Self::Error> {
+        element_from_sdk(value)
+    }
+}
+
+fn element_from_sdk<E: FieldElement>(value: &sdk::FieldElement) -> Result<E, SdkConversionError> {
+    if value.element.len() != E::ELEMENT_BYTES {
+        return Err(SdkConversionError::InvalidFieldElement);
+    }
+    E::read_from(&mut SliceReader::new(&value.element))
+        .map_err(|_| SdkConversionError::InvalidFieldElement)
+}
+
+fn elements_from_sdk<E: FieldElement>(
+    elements: &[sdk::FieldElement],
+) -> Result<Vec<E>, SdkConversionError> {
+    elements.iter().map(element_from_sdk).collect()
+}
+
+fn u64_from_sdk(element: &sdk::FieldElement) -> Result<u64, SdkConversionError> {
+    u64::try_from(element)
+}
+
+fn digest_from_sdk<D: Digest>(digest: &sdk::Digest) -> Result<D, SdkConversionError> {
+    let invalid = || SdkConversionError::InvalidDigest(digest.data.len());
+    let decoded = D::read_from(&mut SliceReader::new(&digest.data)).map_err(|_| invalid())?;
+    // reject digests of a different length than the one produced by the hasher
+    if decoded.to_bytes() != digest.data {
+        return Err(invalid());
+    }
+    Ok(decoded)
+}
+
+fn digests_from_sdk<D: Digest>(digests: &[sdk::Digest]) -> Result<Vec<D>, SdkConversionError> {
+    digests.iter().map(digest_from_sdk).collect()
+}
+
+impl TryFrom<&sdk::Context> for Context {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::Context) -> Result<Self, Self::Error> {
+        let layout: TraceLayout = value
+            .trace_layout
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField("context.trace_layout"))?
+            .try_into()?;
+        let options: miden::ProofOptions = value
+            .options
+            .clone()
+            .ok_or(SdkConversionError::MissingField("context.options"))?
+            .try_into()?;
+        let field_modulus = value
+            .field_modulus
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField("context.field_modulus"))?;
+        if field_modulus.element != Felt::get_modulus_le_bytes() {
+            return Err(SdkConversionError::Unsupported(
+                "only the goldilocks field is supported".to_owned(),
+            ));
+        }
+
+        // `TraceInfo::new_multi_segment` asserts these bounds, and the LDE domain must have a
+        // root of unity in the field
+        let trace_length = usize::try_from(value.trace_length)
+            .ok()
+            .filter(|length| *length >= TraceInfo::MIN_TRACE_LENGTH && length.is_power_of_two())
+            .ok_or_else(|| {
+                SdkConversionError::Deserialization(format!(
+                    "trace length {} is not a power of two of at least {}",
+                    value.trace_length,
+                    TraceInfo::MIN_TRACE_LENGTH
+                ))
+            })?;
+        let lde_domain_size = trace_length.checked_mul(options.0.blowup_factor());
+        if lde_domain_size.map_or(true, |size| log2(size) > Felt::TWO_ADICITY) {
+            return Err(SdkConversionError::Deserialization(format!(
+                "trace length {} is too large for a blowup factor of {}",
+                trace_length,
+                options.0.blowup_factor()
+            )));
+        }
+        if value.trace_meta.len() > TraceInfo::MAX_META_LENGTH {
+            return Err(SdkConversionError::Deserialization(format!(
+                "trace meta of {} bytes",
+                value.trace_meta.len()
+            )));
+        }
+        let trace_info =
+            TraceInfo::new_multi_segment(layout, trace_length, value.trace_meta.clone());
+        Ok(Context::new::<Felt>(&trace_info, options.0))
+    }
+}
+
+impl TryFrom<&sdk::TraceLayout> for TraceLayout {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::TraceLayout) -> Result<Self, Self::Error> {
+        let num_aux_segments = value.num_aux_segments as usize;
+        if value.aux_segment_widths.len() != num_aux_segments
+            || value.aux_segment_rands.len() != num_aux_segments
+        {
+            return Err(SdkConversionError::Deserialization(format!(
+                "trace layout declares {} auxiliary segments",
+                num_aux_segments
+            )));
+        }
+
+        // `TraceLayout::new` asserts the widths and the number of random elements
+        let invalid = || {
+            SdkConversionError::Deserialization(format!(
+                "trace layout of {} main columns, {:?} auxiliary columns and {:?} random elements",
+                value.main_segment_width, value.aux_segment_widths, value.aux_segment_rands
+            ))
+        };
+        let full_width = value
+            .aux_segment_widths
+            .iter()
+            .try_fold(value.main_segment_width, |width, aux_width| {
+                width.checked_add(*aux_width)
+            });
+        if value.main_segment_width == 0
+            || value.aux_segment_widths.contains(&0)
+            || full_width.map_or(true, |width| width > TraceInfo::MAX_TRACE_WIDTH as u64)
+            || value
+                .aux_segment_rands
+                .iter()
+                .any(|rands| *rands > TraceInfo::MAX_RAND_SEGMENT_ELEMENTS as u64)
+        {
+            return Err(invalid());
+        }
+        let main_width = value.main_segment_width as usize;
+
+        // the number of auxiliary segments is a const generic of the layout
+        match num_aux_segments {
+            0 => Ok(TraceLayout::new(main_width, [], [])),
+            1 => Ok(TraceLayout::new(
+                main_width,
+                [value.aux_segment_widths[0] as usize],
+                [value.aux_segment_rands[0] as usize],
+            )),
+            n => Err(SdkConversionError::Unsupported(format!(
+                "{} auxiliary trace segments",
+                n
+            ))),
+        }
+    }
+}
+
+impl TryFromSdk<sdk::Commitments, &Context> for Commitments {
+    fn try_from_sdk(
+        input: sdk::Commitments,
+        context: &Context,
+    ) -> Result<Self, SdkConversionError> {
+        with_hasher!(context.options().hash_fn(), |H| {
+            commitments_from_sdk::<H>(&input)
+        })
+    }
+}
+
+fn commitments_from_sdk<H: Hasher>(
+    input: &sdk::Commitments,
+) -> Result<Commitments, SdkConversionError> {
+    let trace_roots = digests_from_sdk::<H::Digest>(&input.trace_roots)?;
+    let constraint_root = digest_from_sdk::<H::Digest>(input.constraint_root.as_ref().ok_or(
+        SdkConversionError::MissingField("commitments.constraint_root"),
+    )?)?;
+    let fri_roots = digests_from_sdk::<H::Digest>(&input.fri_roots)?;
+
+    Ok(Commitments::new::<H>(
+        trace_roots,
+        constraint_root,
+        fri_roots,
+    ))
+}
+
+impl TryFromSdk<sdk::OodFrame, &Context> for OodFrame {
+    fn try_from_sdk(input: sdk::OodFrame, context: &Context) -> Result<Self, SdkConversionError> {
+        with_field_extension!(context.options().field_extension(), |E| {
+            ood_frame_from_sdk::<E>(&input)
+        })
+    }
+}
+
+fn ood_frame_from_sdk<E: FieldElement>(
+    input: &sdk::OodFrame,
+) -> Result<OodFrame, SdkConversionError> {
+    let main_frame = input
+        .main_frame
+        .as_ref()
+        .ok_or(SdkConversionError::MissingField("ood_frame.main_frame"))?;
+    // the native frame stores main and auxiliary states as a single row
+    let mut current = elements_from_sdk::<E>(&main_frame.current)?;
+    let mut next = elements_from_sdk::<E>(&main_frame.next)?;
+    if let Some(aux_frame) = &input.aux_frame {
+        current.extend(elements_from_sdk::<E>(&aux_frame.current)?);
+        next.extend(elements_from_sdk::<E>(&aux_frame.next)?);
+    }
+    let evaluations = elements_from_sdk::<E>(&input.evaluations)?;
+
+    let mut ood_frame = OodFrame::default();
+    ood_frame.set_trace_states(&[current, next]);
+    ood_frame.set_constraint_evaluations(&evaluations);
+    Ok(ood_frame)
+}
+
+fn table_rows<E: FieldElement>(table: &sdk::Table) -> Result<Vec<Vec<E>>, SdkConversionError> {
+    let elements = elements_from_sdk::<E>(&table.elements)?;
+    let n_rows = table.n_rows as usize;
+    let n_cols = table.n_cols as usize;
+    if elements.len() != n_rows * n_cols || n_cols == 0 {
+        return Err(SdkConversionError::InvalidTable {
+            n_rows: table.n_rows,
+            n_cols: table.n_cols,
+            len: elements.len(),
+        });
+    }
+    Ok(elements.chunks(n_cols).map(|row| row.to_vec()).collect())
+}
+
+impl<H: Hasher> TryFrom<&sdk::BatchMerkleProof> for BatchMerkleProof<H> {
+    type Error = SdkConversionError;
+
+    fn try_from(value: &sdk::BatchMerkleProof) -> Result<Self, Self::Error> {
+        let nodes = value
+            .nodes
+            .iter()
+            .map(|layer| digests_from_sdk(&layer.nodes))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(BatchMerkleProof {
+            leaves: digests_from_sdk(&value.leaves)?,
+            nodes,
+            depth: u8::try_from(value.depth).map_err(|_| {
+                SdkConversionError::Deserialization(format!(
+                    "Merkle proof depth {} does not fit in a byte",
+                    value.depth
+                ))
+            })?,
+        })
+    }
+}
+
+impl TryFromSdk<sdk::TraceQueries, &Context> for Vec<Queries> {
+    fn try_from_sdk(
+        input: sdk::TraceQueries,
+        context: &Context,
+    ) -> Result<Self, SdkConversionError> {
+        with_hasher!(context.options().hash_fn(), |H| {
+            with_field_extension!(context.options().field_extension(), |E| {
+                trace_queries_from_sdk::<E, H>(&input, context.trace_layout())
+            })
+        })
+    }
+}
+
+fn trace_queries_from_sdk<E: FieldElement<BaseField = Felt>, H: Hasher>(
+    input: &sdk::TraceQueries,
+    layout: &TraceLayout,
+) -> Result<Vec<Queries>, SdkConversionError> {
+    if input.query_proofs.len() != layout.num_segments() {
+        return Err(SdkConversionError::Deserialization(format!(
+            "expected {} trace query proofs, found {}",
+            layout.num_segments(),
+            input.query_proofs.len()
+        )));
+    }
+
+    let main_states = table_rows::<Felt>(input.main_states.as_ref().ok_or(
+        SdkConversionError::MissingField("trace_queries.main_states"),
+    )?)?;
+    let mut queries = vec![Queries::new::<H, Felt>(
+        BatchMerkleProof::<H>::try_from(&input.query_proofs[0])?,
+        main_states,
+    )];
+
+    if layout.num_aux_segments() > 0 {
+        let aux_table = input
+            .aux_states
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField("trace_queries.aux_states"))?;
+        if aux_table.n_cols as usize != layout.aux_trace_width() {
+            return Err(SdkConversionError::Deserialization(format!(
+                "expected {} auxiliary columns, found {}",
+                layout.aux_trace_width(),
+                aux_table.n_cols
+            )));
+        }
+        let aux_states = table_rows::<E>(aux_table)?;
+
+        // auxiliary segments are merged column-wise into a single table, split them back
+        let mut offset = 0;
+        for i in 0..layout.num_aux_segments() {
+            let width = layout.get_aux_segment_width(i);
+            let segment_states = aux_states
+                .iter()
+                .map(|row| row[offset..offset + width].to_vec())
+                .collect();
+            queries.push(Queries::new::<H, E>(
+                BatchMerkleProof::<H>::try_from(&input.query_proofs[i + 1])?,
+                segment_states,
+            ));
+            offset += width;
+        }
+    }
+
+    Ok(queries)
+}
+
+impl TryFromSdk<sdk::ConstraintQueries, &Context> for Queries {
+    fn try_from_sdk(
+        input: sdk::ConstraintQueries,
+        context: &Context,
+    ) -> Result<Self, SdkConversionError> {
+        with_hasher!(context.options().hash_fn(), |H| {
+            with_field_extension!(context.options().field_extension(), |E| {
+                constraint_queries_from_sdk::<E, H>(&input)
+            })
+        })
+    }
+}
+
+fn constraint_queries_from_sdk<E: FieldElement, H: Hasher>(
+    input: &sdk::ConstraintQueries,
+) -> Result<Queries, SdkConversionError> {
+    let evaluations = table_rows::<E>(input.evaluations.as_ref().ok_or(
+        SdkConversionError::MissingField("constraint_queries.evaluations"),
+    )?)?;
+    let query_proof = input
+        .query_proof
+        .as_ref()
+        .ok_or(SdkConversionError::MissingField(
+            "constraint_queries.query_proof",
+        ))?;
+
+    Ok(Queries::new::<H, E>(
+        BatchMerkleProof::<H>::try_from(query_proof)?,
+        evaluations,
+    ))
+}
+
+impl TryFromSdk<sdk::FriProof, &Context> for FriProof {
+    fn try_from_sdk(input: sdk::FriProof, context: &Context) -> Result<Self, SdkConversionError> {
+        with_hasher!(context.options().hash_fn(), |H| {
+            with_field_extension!(context.options().field_extension(), |E| {
+                fri_proof_from_sdk::<E, H>(&input, context)
+            })
+        })
+    }
+}
+
+fn fri_proof_from_sdk<E: FieldElement, H: Hasher>(
+    input: &sdk::FriProof,
+    context: &Context,
+) -> Result<FriProof, SdkConversionError> {
+    let folding_factor = context.options().to_fri_options().folding_factor();
+    let layers = input
+        .layers
+        .iter()
+        .map(|layer| fri_layer_from_sdk::<E, H>(layer, folding_factor))
+        .collect::<Result<Vec<_>, _>>()?;
+    let remainder = elements_from_sdk::<E>(&input.remainder)?;
+
+    // the number of partitions is stored as a power of two
+    let num_partitions = 1usize.checked_shl(input.num_partitions).ok_or_else(|| {
+        SdkConversionError::Deserialization(format!("2^{} FRI partitions", input.num_partitions))
+    })?;
+    Ok(FriProof::new(layers, remainder, num_partitions))
+}
+
+fn fri_layer_from_sdk<E: FieldElement, H: Hasher>(
+    layer: &sdk::FriProofLayer,
+    folding_factor: usize,
+) -> Result<FriProofLayer, SdkConversionError> {
+    let values = elements_from_sdk::<E>(&layer.values)?;
+    let merkle_proof = BatchMerkleProof::<H>::try_from(
+        layer
+            .proofs
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField("fri_proof.layers.proofs"))?,
+    )?;
+
+    // the folding factor is a const generic of the layer
+    match folding_factor {
+        2 => build_fri_layer::<E, H, 2>(values, merkle_proof),
+        4 => build_fri_layer::<E, H, 4>(values, merkle_proof),
+        8 => build_fri_layer::<E, H, 8>(values, merkle_proof),
+        16 => build_fri_layer::<E, H, 16>(values, merkle_proof),
+        _ => Err(SdkConversionError::Unsupported(format!(
+            "FRI folding factor {}",
+            folding_factor
+        ))),
+    }
+}
+
+fn build_fri_layer<E: FieldElement, H: Hasher, const N: usize>(
+    values: Vec<E>,
+    merkle_proof: BatchMerkleProof<H>,
+) -> Result<FriProofLayer, SdkConversionError> {
+    if values.len() % N != 0 {
+        return Err(SdkConversionError::Deserialization(format!(
+            "{} FRI layer values cannot be folded by {}",
+            values.len(),
+            N
+        )));
+    }
+    let query_values = values
+        .chunks(N)
+        .map(|chunk| <[E; N]>::try_from(chunk).expect("chunk has N elements"))
+        .collect();
+
+    Ok(FriProofLayer::new::<E, H, N>(query_values, merkle_proof))
+}
+
+impl TryFrom<sdk::MidenProgramOutputs> for ProgramOutputs {
+    type Error = SdkConversionError;
+
+    fn try_from(value: sdk::MidenProgramOutputs) -> Result<Self, Self::Error> {
+        let stack = value
+            .stack
+            .iter()
+            .map(u64_from_sdk)
+            .collect::<Result<Vec<_>, _>>()?;
+        let overflow_addrs = value
+            .overflow_addrs
+            .iter()
+            .map(u64_from_sdk)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ProgramOutputs::new(stack, overflow_addrs))
+    }
+}
+
+impl TryFrom<sdk::MidenPublicInputs> for PublicInputs {
+    type Error = SdkConversionError;
+
+    fn try_from(value: sdk::MidenPublicInputs) -> Result<Self, Self::Error> {
+        let program_hash = value
+            .program_hash
+            .as_ref()
+            .ok_or(SdkConversionError::MissingField(
+                "public_inputs.program_hash",
+            ))?;
+        let program_hash = Deserializable::read_from(&mut SliceReader::new(&program_hash.data))
+            .map_err(|_| SdkConversionError::InvalidDigest(program_hash.data.len()))?;
+        let stack_inputs = elements_from_sdk(&value.stack_inputs)?;
+        let outputs = value
+            .outputs
+            .ok_or(SdkConversionError::MissingField("public_inputs.outputs"))?
+            .try_into()?;
+
+        Ok(PublicInputs::new(program_hash, stack_inputs, outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProofData;
+    use miden::{Assembler, ProgramInputs};
+    use prost::Message;
+
+    fn fib_proof_data() -> ProofData {
+        ProofData::from_file(&format!("{}/../proofs/fib.bin", env!("CARGO_MANIFEST_DIR"))).unwrap()
+    }
+
+    fn assert_round_trip(proof: StarkProof, pub_inputs: PublicInputs) {
+        let air = ProcessorAir::new(
+            proof.get_trace_info(),
+            pub_inputs.clone(),
+            proof.options().clone(),
+        );
+        let proof_bytes = proof.to_bytes();
+        let input_bytes = pub_inputs.to_bytes();
+
+        // go through the wire encoding to make sure nothing is lost in protobuf
+        let sdk_proof = sdk::StarkProof::into_sdk(proof, &air).encode_to_vec();
+        let sdk_pub_inputs = sdk::MidenPublicInputs::from(pub_inputs).encode_to_vec();
+        let sdk_proof = sdk::StarkProof::decode(&sdk_proof[..]).unwrap();
+        let sdk_pub_inputs = sdk::MidenPublicInputs::decode(&sdk_pub_inputs[..]).unwrap();
+
+        let decoded_proof = StarkProof::try_from(sdk_proof).unwrap();
+        let decoded_pub_inputs = PublicInputs::try_from(sdk_pub_inputs).unwrap();
+        assert_eq!(decoded_proof.to_bytes(), proof_bytes);
+        assert_eq!(decoded_pub_inputs.to_bytes(), input_bytes);
+    }
+
+    #[test]
+    fn test_stored_proof_round_trip() {
+        let data = fib_proof_data();
+        assert_round_trip(data.proof().unwrap(), data.public_inputs().unwrap());
+    }
+
+    fn prove_fib(options: &miden::ProofOptions) -> (StarkProof, PublicInputs) {
+        let program = Assembler::new()
+            .compile("begin repeat.20 swap dup.1 add end end")
+            .unwrap();
+        let inputs = ProgramInputs::new(&[0, 1], &[], vec![]).unwrap();
+        let (outputs, proof) = miden::prove(&program, &inputs, options).unwrap();
+        let pub_inputs = PublicInputs::new(program.hash(), inputs.stack_init().to_vec(), outputs);
+        (proof, pub_inputs)
+    }
+
+    #[test]
+    fn test_generated_proof_round_trip() {
+        let (proof, pub_inputs) = prove_fib(&miden::ProofOptions::with_96_bit_security());
+        assert_round_trip(proof, pub_inputs);
+    }
+
+    #[test]
+    fn test_hash_function_round_trip() {
+        for hash_fn in [
+            HashFunction::Blake3_192,
+            HashFunction::Blake3_256,
+            HashFunction::Sha3_256,
+        ] {
+            let options =
+                miden::ProofOptions::new(27, 8, 16, hash_fn, FieldExtension::None, 8, 256);
+            let (proof, pub_inputs) = prove_fib(&options);
+            assert_round_trip(proof, pub_inputs);
+        }
+    }
+
+    #[test]
+    fn test_field_extension_round_trip() {
+        for field_extension in [FieldExtension::Quadratic, FieldExtension::Cubic] {
+            let options = miden::ProofOptions::new(
+                27,
+                8,
+                16,
+                HashFunction::Blake3_256,
+                field_extension,
+                8,
+                256,
+            );
+            let (proof, pub_inputs) = prove_fib(&options);
+            assert_round_trip(proof, pub_inputs);
+        }
+    }
+
+    #[test]
+    fn test_proof_data_round_trip() {
+        let data = fib_proof_data();
+        let (sdk_proof, sdk_pub_inputs) = data.to_sdk().unwrap();
+        let decoded = ProofData::from_sdk(sdk_proof, sdk_pub_inputs).unwrap();
+        assert_eq!(decoded.header, data.header);
+        assert_eq!(decoded.proof_bytes, data.proof_bytes);
+        assert_eq!(decoded.input_bytes, data.input_bytes);
+    }
+
+    #[test]
+    fn test_missing_field_is_rejected() {
+        let data = fib_proof_data();
+        let (mut sdk_proof, _) = data.to_sdk().unwrap();
+        sdk_proof.ood_frame = None;
+        assert_eq!(
+            StarkProof::try_from(sdk_proof).err(),
+            Some(SdkConversionError::MissingField("ood_frame"))
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_fri_partitions_are_rejected() {
+        let data = fib_proof_data();
+        let (mut sdk_proof, _) = data.to_sdk().unwrap();
+        sdk_proof.fri_proof.as_mut().unwrap().num_partitions = 64;
+        assert!(matches!(
+            StarkProof::try_from(sdk_proof),
+            Err(SdkConversionError::Deserialization(_))
+        ));
+    }
+
+    fn assert_context_rejected(corrupt: impl Fn(&mut sdk::Context)) {
+        let (mut sdk_proof, _) = fib_proof_data().to_sdk().unwrap();
+        corrupt(sdk_proof.context.as_mut().unwrap());
+        assert!(matches!(
+            StarkProof::try_from(sdk_proof),
+            Err(SdkConversionError::Deserialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_context_is_rejected() {
+        assert_context_rejected(|context| context.trace_length = 7);
+        assert_context_rejected(|context| context.trace_length = 4);
+        assert_context_rejected(|context| context.trace_length = 1 << 40);
+        assert_context_rejected(|context| {
+            context.trace_layout.as_mut().unwrap().main_segment_width = 0
+        });
+        assert_context_rejected(|context| {
+            context.trace_layout.as_mut().unwrap().main_segment_width = 256
+        });
+    }
+
+    #[test]
+    fn test_invalid_options_are_rejected() {
+        let corruptions: [fn(&mut sdk::ProofOptions); 8] = [
+            |options| options.num_queries = 0,
+            |options| options.num_queries = 129,
+            |options| options.blowup_factor = 1,
+            |options| options.blowup_factor = 3,
+            |options| options.grinding_factor = 33,
+            |options| options.fri_folding_factor = 3,
+            |options| options.fri_folding_factor = 32,
+            |options| options.fri_max_remainder_size = 100,
+        ];
+        for corrupt in corruptions {
+            assert_context_rejected(|context| corrupt(context.options.as_mut().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_invalid_field_element_is_rejected() {
+        let element = sdk::FieldElement {
+            element: vec![1, 2, 3],
+        };
+        assert_eq!(
+            u64::try_from(&element),
+            Err(SdkConversionError::InvalidFieldElement)
+        );
+        assert_eq!(u64::try_from(&sdk::FieldElement::from(42u64)), Ok(42));
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+
+pub mod convert_inputs;
+pub mod convert_proof;
+
+/// Errors raised when a protobuf message cannot be turned back into its native counterpart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SdkConversionError {
+    /// A required message field was not set
+    MissingField(&'static str),
+    /// A field element is not a canonical encoding of an element of the field
+    InvalidFieldElement,
+    /// A digest does not have the length expected by the hash function
+    InvalidDigest(usize),
+    /// A table does not have as many elements as its dimensions imply
+    InvalidTable {
+        n_rows: u32,
+        n_cols: u32,
+        len: usize,
+    },
+    /// The message describes a proof this build cannot represent
+    Unsupported(String),
+    /// A nested winterfell structure could not be decoded
+    Deserialization(String),
+    /// The program inputs were rejected by the VM
+    InvalidInputs(String),
+    /// The program source could not be assembled
+    Assembly(String),
+}
+
+impl fmt::Display for SdkConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdkConversionError::MissingField(field) => write!(f, "missing field {}", field),
+            SdkConversionError::InvalidFieldElement => write!(f, "invalid field element"),
+            SdkConversionError::InvalidDigest(len) => {
+                write!(f, "invalid digest of {} bytes", len)
+            }
+            SdkConversionError::InvalidTable {
+                n_rows,
+                n_cols,
+                len,
+            } => write!(
+                f,
+                "table of {}x{} elements holds {} elements",
+                n_rows, n_cols, len
+            ),
+            SdkConversionError::Unsupported(err) => write!(f, "unsupported proof: {}", err),
+            SdkConversionError::Deserialization(err) => write!(f, "invalid proof: {}", err),
+            SdkConversionError::InvalidInputs(err) => write!(f, "invalid program inputs: {}", err),
+            SdkConversionError::Assembly(err) => write!(f, "failed to assemble program: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SdkConversionError {}
@@ -0,0 +1,39 @@
+pub use miden::FieldExtension;
+pub use miden_air::Felt;
+pub use winter_verifier::math::fields::{CubeExtension, QuadExtension};
+
+use crate::format::FieldExtensionTag;
+
+impl From<FieldExtensionTag> for FieldExtension {
+    fn from(value: FieldExtensionTag) -> Self {
+        match value {
+            FieldExtensionTag::None => Self::None,
+            FieldExtensionTag::Quadratic => Self::Quadratic,
+            FieldExtensionTag::Cubic => Self::Cubic,
+        }
+    }
+}
+
+/// Evaluates `$body` with `$field` bound to the field the proof is computed over.
+///
+/// The out-of-domain frame, the auxiliary trace and the FRI layers of a proof live in the
+/// extension of the base field selected by `$field_extension`; see [`with_hasher`](crate::with_hasher).
+#[macro_export]
+macro_rules! with_field_extension {
+    ($field_extension:expr, |$field:ident| $body:expr) => {
+        match $field_extension {
+            $crate::field::FieldExtension::None => {
+                type $field = $crate::field::Felt;
+                $body
+            }
+            $crate::field::FieldExtension::Quadratic => {
+                type $field = $crate::field::QuadExtension<$crate::field::Felt>;
+                $body
+            }
+            $crate::field::FieldExtension::Cubic => {
+                type $field = $crate::field::CubeExtension<$crate::field::Felt>;
+                $body
+            }
+        }
+    };
+}
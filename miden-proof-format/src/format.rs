@@ -0,0 +1,264 @@
+use miden::{FieldExtension, HashFunction, StarkProof};
+use miden_air::PublicInputs;
+use miden_core::utils::Serializable;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Magic bytes every serialized proof starts with
+pub const PROOF_MAGIC: [u8; 4] = *b"AERO";
+/// Version of the on-disk proof container layout
+pub const PROOF_FORMAT_VERSION: u16 = 1;
+/// Miden revision the proofs are generated and parsed with
+pub const MIDEN_VERSION: &str = "0.3.0";
+/// Winterfell revision the proofs are generated and parsed with
+pub const WINTERFELL_VERSION: &str = "0.4.0";
+
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashFunctionTag {
+    Blake2s_256,
+    Blake3_192,
+    Blake3_256,
+    Sha3_256,
+}
+
+impl From<HashFunction> for HashFunctionTag {
+    fn from(value: HashFunction) -> Self {
+        match value {
+            HashFunction::Blake2s_256 => Self::Blake2s_256,
+            HashFunction::Blake3_192 => Self::Blake3_192,
+            HashFunction::Blake3_256 => Self::Blake3_256,
+            HashFunction::Sha3_256 => Self::Sha3_256,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldExtensionTag {
+    None,
+    Quadratic,
+    Cubic,
+}
+
+impl From<FieldExtension> for FieldExtensionTag {
+    fn from(value: FieldExtension) -> Self {
+        match value {
+            FieldExtension::None => Self::None,
+            FieldExtension::Quadratic => Self::Quadratic,
+            FieldExtension::Cubic => Self::Cubic,
+        }
+    }
+}
+
+/// Self-describing header stored in front of the proof and its public inputs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProofHeader {
+    pub magic: [u8; 4],
+    pub format_version: u16,
+    pub miden_version: String,
+    pub winterfell_version: String,
+    pub hash_fn: HashFunctionTag,
+    pub field_extension: FieldExtensionTag,
+    pub program_hash: [u8; 32],
+}
+
+impl ProofHeader {
+    pub fn new(proof: &StarkProof, pub_inputs: &PublicInputs) -> ProofHeader {
+        ProofHeader {
+            magic: PROOF_MAGIC,
+            format_version: PROOF_FORMAT_VERSION,
+            miden_version: MIDEN_VERSION.to_owned(),
+            winterfell_version: WINTERFELL_VERSION.to_owned(),
+            hash_fn: proof.options().hash_fn().into(),
+            field_extension: proof.options().field_extension().into(),
+            program_hash: program_hash_bytes(pub_inputs),
+        }
+    }
+
+    /// Checks that the header was written by a compatible version of the toolchain
+    pub fn validate(&self) -> Result<(), FormatError> {
+        if self.magic != PROOF_MAGIC {
+            return Err(FormatError::BadMagic(self.magic.to_vec()));
+        }
+        if self.format_version != PROOF_FORMAT_VERSION {
+            return Err(FormatError::FormatVersionMismatch {
+                expected: PROOF_FORMAT_VERSION,
+                found: self.format_version,
+            });
+        }
+        if self.miden_version != MIDEN_VERSION {
+            return Err(FormatError::MidenVersionMismatch {
+                expected: MIDEN_VERSION.to_owned(),
+                found: self.miden_version.clone(),
+            });
+        }
+        if self.winterfell_version != WINTERFELL_VERSION {
+            return Err(FormatError::WinterfellVersionMismatch {
+                expected: WINTERFELL_VERSION.to_owned(),
+                found: self.winterfell_version.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that the header describes the given proof and public inputs
+    pub fn validate_contents(
+        &self,
+        proof: &StarkProof,
+        pub_inputs: &PublicInputs,
+    ) -> Result<(), FormatError> {
+        let hash_fn: HashFunctionTag = proof.options().hash_fn().into();
+        if self.hash_fn != hash_fn {
+            return Err(FormatError::HashFunctionMismatch {
+                header: self.hash_fn,
+                proof: hash_fn,
+            });
+        }
+        let field_extension: FieldExtensionTag = proof.options().field_extension().into();
+        if self.field_extension != field_extension {
+            return Err(FormatError::FieldExtensionMismatch {
+                header: self.field_extension,
+                proof: field_extension,
+            });
+        }
+        if self.program_hash != program_hash_bytes(pub_inputs) {
+            return Err(FormatError::ProgramHashMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Checks the leading magic bytes and format version before the rest of the
+/// container is decoded, so that foreign or legacy files fail early.
+pub fn check_prefix(bytes: &[u8]) -> Result<(), FormatError> {
+    if bytes.len() < 6 {
+        return Err(FormatError::BadMagic(bytes.to_vec()));
+    }
+    if bytes[..4] != PROOF_MAGIC {
+        return Err(FormatError::BadMagic(bytes[..4].to_vec()));
+    }
+    let format_version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if format_version != PROOF_FORMAT_VERSION {
+        return Err(FormatError::FormatVersionMismatch {
+            expected: PROOF_FORMAT_VERSION,
+            found: format_version,
+        });
+    }
+    Ok(())
+}
+
+fn program_hash_bytes(pub_inputs: &PublicInputs) -> [u8; 32] {
+    let mut program_hash = [0u8; 32];
+    program_hash.copy_from_slice(&pub_inputs.program_hash.to_bytes());
+    program_hash
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    BadMagic(Vec<u8>),
+    FormatVersionMismatch {
+        expected: u16,
+        found: u16,
+    },
+    MidenVersionMismatch {
+        expected: String,
+        found: String,
+    },
+    WinterfellVersionMismatch {
+        expected: String,
+        found: String,
+    },
+    HashFunctionMismatch {
+        header: HashFunctionTag,
+        proof: HashFunctionTag,
+    },
+    FieldExtensionMismatch {
+        header: FieldExtensionTag,
+        proof: FieldExtensionTag,
+    },
+    ProgramHashMismatch,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::BadMagic(magic) => write!(
+                f,
+                "not an Aero proof file (expected magic {:?}, found {:?}); proofs written before the versioned format need to be regenerated",
+                PROOF_MAGIC, magic
+            ),
+            FormatError::FormatVersionMismatch { expected, found } => write!(
+                f,
+                "unsupported proof format version {} (expected {})",
+                found, expected
+            ),
+            FormatError::MidenVersionMismatch { expected, found } => write!(
+                f,
+                "proof was generated with Miden {} but this build uses Miden {}",
+                found, expected
+            ),
+            FormatError::WinterfellVersionMismatch { expected, found } => write!(
+                f,
+                "proof was generated with Winterfell {} but this build uses Winterfell {}",
+                found, expected
+            ),
+            FormatError::HashFunctionMismatch { header, proof } => write!(
+                f,
+                "header declares hash function {:?} but the proof uses {:?}",
+                header, proof
+            ),
+            FormatError::FieldExtensionMismatch { header, proof } => write!(
+                f,
+                "header declares field extension {:?} but the proof uses {:?}",
+                header, proof
+            ),
+            FormatError::ProgramHashMismatch => {
+                write!(f, "header program hash does not match the public inputs")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProofData;
+
+    fn fib_proof_path() -> String {
+        format!("{}/../proofs/fib.bin", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn test_load_versioned_proof() {
+        let data = ProofData::from_file(&fib_proof_path()).unwrap();
+        assert_eq!(data.header.format_version, PROOF_FORMAT_VERSION);
+        assert_eq!(data.header.hash_fn, HashFunctionTag::Blake2s_256);
+        assert_eq!(data.header.field_extension, FieldExtensionTag::None);
+    }
+
+    #[test]
+    fn test_reject_legacy_proof() {
+        // legacy files start with the length of the public inputs
+        let legacy = bincode::serialize(&(vec![1u8, 2, 3], vec![4u8, 5, 6])).unwrap();
+        assert!(matches!(
+            check_prefix(&legacy),
+            Err(FormatError::BadMagic(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_version_mismatch() {
+        let data = ProofData::from_file(&fib_proof_path()).unwrap();
+        let mut header = data.header.clone();
+        header.winterfell_version = "0.5.0".to_owned();
+        assert_eq!(
+            header.validate(),
+            Err(FormatError::WinterfellVersionMismatch {
+                expected: WINTERFELL_VERSION.to_owned(),
+                found: "0.5.0".to_owned(),
+            })
+        );
+    }
+}
@@ -0,0 +1,48 @@
+pub use miden::HashFunction;
+pub use miden_air::Felt;
+pub use winter_crypto::hashers;
+
+use crate::format::HashFunctionTag;
+
+impl From<HashFunctionTag> for HashFunction {
+    fn from(value: HashFunctionTag) -> Self {
+        match value {
+            HashFunctionTag::Blake2s_256 => Self::Blake2s_256,
+            HashFunctionTag::Blake3_192 => Self::Blake3_192,
+            HashFunctionTag::Blake3_256 => Self::Blake3_256,
+            HashFunctionTag::Sha3_256 => Self::Sha3_256,
+        }
+    }
+}
+
+/// Evaluates `$body` with `$hasher` bound to the winterfell hasher selected by `$hash_fn`.
+///
+/// Winterfell takes the hash function as a type parameter while proofs carry it as a runtime
+/// option, so every generic code path goes through this macro to pick the monomorphization:
+///
+/// ```ignore
+/// let root = with_hasher!(options.hash_fn(), |H| MerkleTree::<H>::new(leaves)?.root().to_bytes());
+/// ```
+#[macro_export]
+macro_rules! with_hasher {
+    ($hash_fn:expr, |$hasher:ident| $body:expr) => {
+        match $hash_fn {
+            $crate::hash::HashFunction::Blake2s_256 => {
+                type $hasher = $crate::hash::hashers::Blake2s_256<$crate::hash::Felt>;
+                $body
+            }
+            $crate::hash::HashFunction::Blake3_192 => {
+                type $hasher = $crate::hash::hashers::Blake3_192<$crate::hash::Felt>;
+                $body
+            }
+            $crate::hash::HashFunction::Blake3_256 => {
+                type $hasher = $crate::hash::hashers::Blake3_256<$crate::hash::Felt>;
+                $body
+            }
+            $crate::hash::HashFunction::Sha3_256 => {
+                type $hasher = $crate::hash::hashers::Sha3_256<$crate::hash::Felt>;
+                $body
+            }
+        }
+    };
+}
@@ -0,0 +1,147 @@
+use miden::StarkProof;
+use miden_air::{ProcessorAir, PublicInputs};
+use miden_core::utils::{Deserializable, Serializable, SliceReader};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use winter_air::Air;
+
+pub mod convert;
+pub mod field;
+pub mod format;
+pub mod hash;
+pub mod sdk {
+    include!(concat!(env!("OUT_DIR"), "/sdk.rs"));
+}
+
+use convert::{convert_proof::IntoSdk, SdkConversionError};
+use format::{check_prefix, FormatError, ProofHeader};
+
+/// A proof together with the public inputs it was generated for, as stored on disk
+#[derive(Serialize, Deserialize)]
+pub struct ProofData {
+    pub header: ProofHeader,
+    pub input_bytes: Vec<u8>,
+    pub proof_bytes: Vec<u8>,
+}
+
+impl ProofData {
+    pub fn new(pub_inputs: &PublicInputs, proof: &StarkProof) -> ProofData {
+        ProofData {
+            header: ProofHeader::new(proof, pub_inputs),
+            input_bytes: pub_inputs.to_bytes(),
+            proof_bytes: proof.to_bytes(),
+        }
+    }
+
+    /// Decodes a proof container and validates its header against its contents
+    pub fn from_bytes(bytes: &[u8]) -> Result<ProofData, ProofDataError> {
+        check_prefix(bytes)?;
+        let data: ProofData = bincode::deserialize(bytes)
+            .map_err(|err| ProofDataError::Deserialization(err.to_string()))?;
+        data.header.validate()?;
+        data.header
+            .validate_contents(&data.proof()?, &data.public_inputs()?)?;
+        Ok(data)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProofDataError> {
+        bincode::serialize(self).map_err(|err| ProofDataError::Serialization(err.to_string()))
+    }
+
+    pub fn from_file(file_path: &str) -> Result<ProofData, ProofDataError> {
+        let file = File::open(file_path).map_err(|err| ProofDataError::Io(err.to_string()))?;
+        let mut data = Vec::new();
+        BufReader::new(file)
+            .read_to_end(&mut data)
+            .map_err(|err| ProofDataError::Io(err.to_string()))?;
+        ProofData::from_bytes(&data)
+    }
+
+    pub fn write_to_file(&self, file_path: &str) -> Result<(), ProofDataError> {
+        let bytes = self.to_bytes()?;
+        let mut file =
+            File::create(file_path).map_err(|err| ProofDataError::Io(err.to_string()))?;
+        file.write_all(&bytes)
+            .map_err(|err| ProofDataError::Io(err.to_string()))
+    }
+
+    pub fn public_inputs(&self) -> Result<PublicInputs, ProofDataError> {
+        PublicInputs::read_from(&mut SliceReader::new(&self.input_bytes))
+            .map_err(|err| ProofDataError::Deserialization(err.to_string()))
+    }
+
+    pub fn proof(&self) -> Result<StarkProof, ProofDataError> {
+        StarkProof::from_bytes(&self.proof_bytes)
+            .map_err(|err| ProofDataError::Deserialization(err.to_string()))
+    }
+
+    /// Re-encodes the proof and its public inputs as the protobuf messages used by the SDK
+    pub fn to_sdk(&self) -> Result<(sdk::StarkProof, sdk::MidenPublicInputs), ProofDataError> {
+        let pub_inputs = self.public_inputs()?;
+        let proof = self.proof()?;
+        let air = ProcessorAir::new(
+            proof.get_trace_info(),
+            pub_inputs.clone(),
+            proof.options().clone(),
+        );
+        Ok((sdk::StarkProof::into_sdk(proof, &air), pub_inputs.into()))
+    }
+
+    /// Rebuilds a proof container from the protobuf messages produced by the SDK
+    pub fn from_sdk(
+        proof: sdk::StarkProof,
+        pub_inputs: sdk::MidenPublicInputs,
+    ) -> Result<ProofData, ProofDataError> {
+        let proof = StarkProof::try_from(proof)?;
+        let pub_inputs = PublicInputs::try_from(pub_inputs)?;
+        Ok(ProofData::new(&pub_inputs, &proof))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofDataError {
+    /// The proof data could not be read from or written to disk
+    Io(String),
+    /// The proof data could not be encoded
+    Serialization(String),
+    /// The proof data could not be decoded
+    Deserialization(String),
+    /// The proof container header is invalid or does not match this build
+    Format(FormatError),
+    /// The protobuf messages do not describe a valid proof
+    Conversion(SdkConversionError),
+}
+
+impl fmt::Display for ProofDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofDataError::Io(err) => write!(f, "failed to access proof data: {}", err),
+            ProofDataError::Serialization(err) => {
+                write!(f, "failed to encode proof data: {}", err)
+            }
+            ProofDataError::Deserialization(err) => {
+                write!(f, "failed to decode proof data: {}", err)
+            }
+            ProofDataError::Format(err) => write!(f, "invalid proof container: {}", err),
+            ProofDataError::Conversion(err) => {
+                write!(f, "failed to convert proof messages: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProofDataError {}
+
+impl From<FormatError> for ProofDataError {
+    fn from(err: FormatError) -> Self {
+        ProofDataError::Format(err)
+    }
+}
+
+impl From<SdkConversionError> for ProofDataError {
+    fn from(err: SdkConversionError) -> Self {
+        ProofDataError::Conversion(err)
+    }
+}
@@ -0,0 +1,244 @@
+use miden_proof_format::sdk;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::ProveError;
+
+/// An advice set as written in the `--advice-sets` JSON file, e.g.
+///
+/// ```json
+/// [
+///     { "merkle_tree": { "leaves": [[1, 0, 0, 0], [2, 0, 0, 0]] } },
+///     { "sparse_merkle_tree": { "keys": [3], "values": [[3, 0, 0, 0]], "depth": 4 } },
+///     { "merkle_path_set": {
+///         "depth": 1,
+///         "paths": [{ "index": 0, "value": [1, 0, 0, 0], "path": [[2, 0, 0, 0]] }]
+///     } }
+/// ]
+/// ```
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AdviceSetJson {
+    MerkleTree {
+        leaves: Vec<[u64; 4]>,
+    },
+    SparseMerkleTree {
+        keys: Vec<u64>,
+        values: Vec<[u64; 4]>,
+        depth: u32,
+    },
+    MerklePathSet {
+        depth: u32,
+        paths: Vec<MerklePathJson>,
+    },
+}
+
+#[derive(Deserialize)]
+struct MerklePathJson {
+    index: u64,
+    value: [u64; 4],
+    path: Vec<[u64; 4]>,
+}
+
+impl From<AdviceSetJson> for sdk::AdviceSet {
+    fn from(value: AdviceSetJson) -> Self {
+        let set = match value {
+            AdviceSetJson::MerkleTree { leaves } => {
+                sdk::advice_set::Set::MerkleTree(sdk::MerkleTree {
+                    leaves: words(leaves),
+                })
+            }
+            AdviceSetJson::SparseMerkleTree {
+                keys,
+                values,
+                depth,
+            } => sdk::advice_set::Set::SparseMerkleTree(sdk::SparseMerkleTree {
+                keys,
+                values: words(values),
+                depth,
+            }),
+            AdviceSetJson::MerklePathSet { depth, paths } => {
+                sdk::advice_set::Set::MerklePathSet(sdk::MerklePathSet {
+                    depth,
+                    paths: paths
+                        .into_iter()
+                        .map(|path| sdk::MerklePath {
+                            index: path.index,
+                            value: Some(word(path.value)),
+                            path: words(path.path),
+                        })
+                        .collect(),
+                })
+            }
+        };
+        Self { set: Some(set) }
+    }
+}
+
+fn word(elements: [u64; 4]) -> sdk::Word {
+    sdk::Word {
+        elements: elements.to_vec(),
+    }
+}
+
+fn words(words: Vec<[u64; 4]>) -> Vec<sdk::Word> {
+    words.into_iter().map(word).collect()
+}
+
+/// Parses an advice map given as a JSON object from 32-byte hex keys to lists of values
+pub fn parse_advice_map(json: &str) -> Result<Vec<sdk::AdviceMapEntry>, ProveError> {
+    let map: BTreeMap<String, Vec<u64>> = serde_json::from_str(json)
+        .map_err(|err| ProveError::Inputs(format!("bad advice map: {}", err)))?;
+    map.into_iter()
+        .map(|(key, values)| {
+            Ok(sdk::AdviceMapEntry {
+                key: decode_key(&key)?,
+                values,
+            })
+        })
+        .collect()
+}
+
+/// Parses a JSON list of advice sets, see [`AdviceSetJson`] for the format
+pub fn parse_advice_sets(json: &str) -> Result<Vec<sdk::AdviceSet>, ProveError> {
+    let sets: Vec<AdviceSetJson> = serde_json::from_str(json)
+        .map_err(|err| ProveError::Inputs(format!("bad advice sets: {}", err)))?;
+    Ok(sets.into_iter().map(|set| set.into()).collect())
+}
+
+fn decode_key(key: &str) -> Result<Vec<u8>, ProveError> {
+    let bytes = hex::decode(key.trim_start_matches("0x"))
+        .map_err(|err| ProveError::Inputs(format!("advice map key {} is not hex: {}", key, err)))?;
+    if bytes.len() != 32 {
+        return Err(ProveError::Inputs(format!(
+            "advice map key {} is not 32 bytes",
+            key
+        )));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program_inputs;
+    use miden_core::{AdviceSet, Felt, FieldElement};
+
+    const KEY: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+
+    fn felt_word(value: u64) -> [Felt; 4] {
+        [Felt::new(value), Felt::ZERO, Felt::ZERO, Felt::ZERO]
+    }
+
+    #[test]
+    fn test_parse_advice_map() {
+        let map = parse_advice_map(&format!(r#"{{ "{}": [1, 2] }}"#, KEY)).unwrap();
+        assert_eq!(
+            map,
+            vec![sdk::AdviceMapEntry {
+                key: vec![1; 32],
+                values: vec![1, 2],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_advice_sets() {
+        let sets = parse_advice_sets(
+            r#"[
+                { "merkle_tree": { "leaves": [[1, 0, 0, 0], [2, 0, 0, 0]] } },
+                { "sparse_merkle_tree": { "keys": [3], "values": [[3, 0, 0, 0]], "depth": 4 } },
+                { "merkle_path_set": {
+                    "depth": 1,
+                    "paths": [{ "index": 0, "value": [1, 0, 0, 0], "path": [[2, 0, 0, 0]] }]
+                } }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            sets,
+            vec![
+                sdk::AdviceSet {
+                    set: Some(sdk::advice_set::Set::MerkleTree(sdk::MerkleTree {
+                        leaves: words(vec![[1, 0, 0, 0], [2, 0, 0, 0]]),
+                    })),
+                },
+                sdk::AdviceSet {
+                    set: Some(sdk::advice_set::Set::SparseMerkleTree(
+                        sdk::SparseMerkleTree {
+                            keys: vec![3],
+                            values: words(vec![[3, 0, 0, 0]]),
+                            depth: 4,
+                        }
+                    )),
+                },
+                sdk::AdviceSet {
+                    set: Some(sdk::advice_set::Set::MerklePathSet(sdk::MerklePathSet {
+                        depth: 1,
+                        paths: vec![sdk::MerklePath {
+                            index: 0,
+                            value: Some(word([1, 0, 0, 0])),
+                            path: words(vec![[2, 0, 0, 0]]),
+                        }],
+                    })),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_program_inputs_advice() {
+        let map = parse_advice_map(&format!(r#"{{ "{}": [1, 2] }}"#, KEY)).unwrap();
+        let sets =
+            parse_advice_sets(r#"[{ "merkle_tree": { "leaves": [[1, 0, 0, 0], [2, 0, 0, 0]] } }]"#)
+                .unwrap();
+        let inputs = program_inputs(&[], &[], map, sets).unwrap();
+
+        assert_eq!(
+            inputs.advice_map().get(&[1; 32]),
+            Some(&vec![Felt::new(1), Felt::new(2)])
+        );
+        let tree = AdviceSet::new_merkle_tree(vec![felt_word(1), felt_word(2)]).unwrap();
+        let roots: Vec<_> = inputs
+            .advice_sets()
+            .values()
+            .map(|set| set.root())
+            .collect();
+        assert_eq!(roots, vec![tree.root()]);
+    }
+
+    #[test]
+    fn test_bad_advice_map_key() {
+        // too short, and 32 bytes that are not hex
+        for key in ["0x0102".to_string(), "zz".repeat(32)] {
+            assert!(matches!(
+                parse_advice_map(&format!(r#"{{ "{}": [1] }}"#, key)),
+                Err(ProveError::Inputs(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_wrong_word_length() {
+        assert!(matches!(
+            parse_advice_sets(r#"[{ "merkle_tree": { "leaves": [[1, 0, 0]] } }]"#),
+            Err(ProveError::Inputs(_))
+        ));
+
+        // words built outside of the JSON parser are checked by the conversion
+        let set = sdk::AdviceSet {
+            set: Some(sdk::advice_set::Set::MerkleTree(sdk::MerkleTree {
+                leaves: vec![
+                    sdk::Word {
+                        elements: vec![1, 0, 0],
+                    },
+                    word([2, 0, 0, 0]),
+                ],
+            })),
+        };
+        assert!(matches!(
+            program_inputs(&[], &[], vec![], vec![set]),
+            Err(ProveError::Inputs(_))
+        ));
+    }
+}
@@ -1,6 +1,165 @@
-use serde::{Deserialize, Serialize};
-#[derive(Serialize, Deserialize)]
-pub struct ProofData {
-    pub input_bytes: Vec<u8>,
-    pub proof_bytes: Vec<u8>,
+use miden::{Assembler, ProgramInputs, ProofOptions};
+use miden_air::PublicInputs;
+use miden_core::StarkField;
+use miden_proof_format::convert::SdkConversionError;
+use miden_stdlib::StdLibrary;
+use std::fmt;
+
+pub use miden_proof_format::{sdk, ProofData, ProofDataError};
+
+pub mod advice;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProveError {
+    /// The MASM source could not be assembled into a program
+    Assembly(String),
+    /// The program inputs are malformed
+    Inputs(String),
+    /// The program failed to execute or could not be proven
+    Execution(String),
+    /// The generated or loaded proof did not verify
+    Verification(String),
+    /// The program source or inputs could not be read from disk
+    Io(String),
+    /// The proof container could not be encoded, decoded or validated
+    ProofData(ProofDataError),
+}
+
+impl fmt::Display for ProveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProveError::Assembly(err) => write!(f, "failed to assemble program: {}", err),
+            ProveError::Inputs(err) => write!(f, "invalid program inputs: {}", err),
+            ProveError::Execution(err) => write!(f, "failed to prove program: {}", err),
+            ProveError::Verification(err) => write!(f, "failed to verify proof: {}", err),
+            ProveError::Io(err) => write!(f, "failed to read input file: {}", err),
+            ProveError::ProofData(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProveError {}
+
+impl From<ProofDataError> for ProveError {
+    fn from(err: ProofDataError) -> Self {
+        ProveError::ProofData(err)
+    }
+}
+
+/// Builds the program inputs from the initial stack, the advice tape and the advice provided
+/// through the advice map and advice sets
+pub fn program_inputs(
+    stack_inputs: &[u64],
+    advice_tape: &[u64],
+    advice_map: Vec<sdk::AdviceMapEntry>,
+    advice_sets: Vec<sdk::AdviceSet>,
+) -> Result<ProgramInputs, ProveError> {
+    sdk::MidenProgramInputs {
+        stack_init: stack_inputs.to_vec(),
+        advice_tape: advice_tape.to_vec(),
+        advice_map,
+        advice_sets,
+    }
+    .try_into()
+    .map_err(|err: SdkConversionError| ProveError::Inputs(err.to_string()))
+}
+
+/// Assembles the MASM `source`, executes it against `inputs` and proves the execution.
+///
+/// The proof is verified before it is returned.
+pub fn prove_program(
+    source: &str,
+    inputs: &ProgramInputs,
+    options: &ProofOptions,
+) -> Result<ProofData, ProveError> {
+    let program = Assembler::new()
+        .with_module_provider(StdLibrary::default())
+        .compile(source)
+        .map_err(|err| ProveError::Assembly(err.to_string()))?;
+
+    // execute program and generate proof
+    let (outputs, proof) = miden::prove(&program, inputs, options)
+        .map_err(|err| ProveError::Execution(format!("{:?}", err)))?;
+
+    let pub_inputs = PublicInputs::new(program.hash(), inputs.stack_init().to_vec(), outputs);
+    let data = ProofData::new(&pub_inputs, &proof);
+
+    verify_proof_data(&data)?;
+    Ok(data)
+}
+
+/// Verifies the proof in `data` against the public inputs stored next to it
+pub fn verify_proof_data(data: &ProofData) -> Result<(), ProveError> {
+    let pub_inputs = data.public_inputs()?;
+    let proof = data.proof()?;
+    let stack_inputs: Vec<u64> = pub_inputs
+        .stack_inputs
+        .iter()
+        // stack inputs are stored in reverse order
+        .rev()
+        .map(|e| e.as_int())
+        .collect();
+
+    miden_verifier::verify(
+        pub_inputs.program_hash,
+        &stack_inputs,
+        &pub_inputs.outputs,
+        proof,
+    )
+    .map_err(|err| ProveError::Verification(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miden_core::Felt;
+
+    const PROGRAM: &str = "begin repeat.20 swap dup.1 add end end";
+
+    fn options() -> ProofOptions {
+        ProofOptions::with_96_bit_security()
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let inputs = program_inputs(&[0, 1], &[], vec![], vec![]).unwrap();
+        let data = prove_program(PROGRAM, &inputs, &options()).unwrap();
+        assert_eq!(verify_proof_data(&data), Ok(()));
+    }
+
+    #[test]
+    fn test_assembly_error() {
+        let inputs = program_inputs(&[], &[], vec![], vec![]).unwrap();
+        assert!(matches!(
+            prove_program("begin not_an_instruction end", &inputs, &options()),
+            Err(ProveError::Assembly(_))
+        ));
+    }
+
+    #[test]
+    fn test_inputs_error() {
+        let entry = sdk::AdviceMapEntry {
+            key: vec![0; 31],
+            values: vec![1],
+        };
+        assert!(matches!(
+            program_inputs(&[], &[], vec![entry], vec![]),
+            Err(ProveError::Inputs(_))
+        ));
+    }
+
+    #[test]
+    fn test_verification_error() {
+        let inputs = program_inputs(&[0, 1], &[], vec![], vec![]).unwrap();
+        let data = prove_program(PROGRAM, &inputs, &options()).unwrap();
+
+        // the proof does not hold for other stack inputs
+        let mut pub_inputs = data.public_inputs().unwrap();
+        pub_inputs.stack_inputs = vec![Felt::new(2), Felt::new(3)];
+        let tampered = ProofData::new(&pub_inputs, &data.proof().unwrap());
+        assert!(matches!(
+            verify_proof_data(&tampered),
+            Err(ProveError::Verification(_))
+        ));
+    }
 }
@@ -1,87 +1,264 @@
-use miden::{prove, Assembler, Program, ProgramInputs, ProofOptions};
-use miden_air::PublicInputs;
-use miden_core::{Felt, FieldElement, StarkField};
-use miden_proof_generator::ProofData;
-use miden_stdlib::StdLibrary;
-use std::fs::File;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use miden::{FieldExtension, HashFunction, ProofOptions};
+use miden_core::utils::Serializable;
+use miden_core::StarkField;
+use miden_proof_generator::{
+    advice::{parse_advice_map, parse_advice_sets},
+    program_inputs, prove_program, verify_proof_data, ProofData, ProveError,
+};
+use std::fs;
 use std::io::Write;
 
-fn main() {
-    println!("============================================================");
-    println!("Prove program");
-    println!("============================================================");
+#[derive(Parser)]
+#[command(name = "miden_proof_generator")]
+#[command(about = "Generates, verifies and inspects Miden STARK proofs", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Executes a MASM program and writes its proof to disk
+    Prove(ProveArgs),
+    /// Verifies a proof previously written by `prove`
+    Verify {
+        /// Path to the serialized proof
+        proof: String,
+    },
+    /// Prints the metadata of a proof previously written by `prove`
+    Inspect {
+        /// Path to the serialized proof
+        proof: String,
+    },
+}
 
+#[derive(Args)]
+struct ProveArgs {
+    /// Path to the `.masm` source of the program
+    program: String,
+    /// Comma separated list of initial stack values, e.g. `0,1`
+    #[arg(long, value_delimiter = ',')]
+    stack_inputs: Vec<u64>,
+    /// Path to a file with whitespace or comma separated advice tape values
+    #[arg(long)]
+    advice_tape: Option<String>,
+    /// Path to a JSON object mapping 32-byte hex keys to lists of advice values
+    #[arg(long)]
+    advice_map: Option<String>,
+    /// Path to a JSON list of Merkle trees, sparse Merkle trees and Merkle path sets
+    #[arg(long)]
+    advice_sets: Option<String>,
+    /// Security level of the generated proof
+    #[arg(long, value_enum, default_value_t = Security::Bits96)]
+    security: Security,
+    /// Hash function used for the trace, constraint and FRI commitments
+    #[arg(long, value_enum, default_value_t = HashFn::Blake2s)]
+    hash_fn: HashFn,
+    /// Extension of the base field used for the out-of-domain frame and FRI
+    #[arg(long, value_enum, default_value_t = FieldExt::None)]
+    field_extension: FieldExt,
+    /// Where to write the serialized proof
+    #[arg(long)]
+    output: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Security {
+    #[value(name = "96")]
+    Bits96,
+    #[value(name = "128")]
+    Bits128,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HashFn {
+    #[value(name = "blake2s")]
+    Blake2s,
+    #[value(name = "blake3-192")]
+    Blake3_192,
+    #[value(name = "blake3-256")]
+    Blake3_256,
+    #[value(name = "sha3-256")]
+    Sha3_256,
+}
+
+impl From<HashFn> for HashFunction {
+    fn from(value: HashFn) -> Self {
+        match value {
+            HashFn::Blake2s => HashFunction::Blake2s_256,
+            HashFn::Blake3_192 => HashFunction::Blake3_192,
+            HashFn::Blake3_256 => HashFunction::Blake3_256,
+            HashFn::Sha3_256 => HashFunction::Sha3_256,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FieldExt {
+    None,
+    Quadratic,
+    Cubic,
+}
+
+impl From<FieldExt> for FieldExtension {
+    fn from(value: FieldExt) -> Self {
+        match value {
+            FieldExt::None => FieldExtension::None,
+            FieldExt::Quadratic => FieldExtension::Quadratic,
+            FieldExt::Cubic => FieldExtension::Cubic,
+        }
+    }
+}
+
+impl Security {
+    fn proof_options(&self, hash_fn: HashFn, field_extension: FieldExt) -> ProofOptions {
+        let options = match self {
+            Security::Bits96 => ProofOptions::with_96_bit_security(),
+            Security::Bits128 => ProofOptions::with_128_bit_security(),
+        }
+        .0;
+        // keep the security preset, only swap the hash function and field extension
+        let fri_options = options.to_fri_options();
+        ProofOptions::new(
+            options.num_queries(),
+            options.blowup_factor(),
+            options.grinding_factor(),
+            hash_fn.into(),
+            field_extension.into(),
+            fri_options.folding_factor(),
+            fri_options.max_remainder_size(),
+        )
+    }
+}
+
+fn main() {
     // configure logging
     env_logger::Builder::new()
         .format(|buf, record| writeln!(buf, "{}", record.args()))
         .filter_level(log::LevelFilter::Debug)
         .init();
 
-    let n = 10;
-    let program = generate_fibonacci_program(n);
-    let expected_result = vec![compute_fibonacci(n).as_int()];
-    let proof_security = ProofOptions::with_96_bit_security();
-    let input_data = ProgramInputs::new(&[0, 1], &[], vec![]).unwrap();
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Commands::Prove(args) => prove(args),
+        Commands::Verify { proof } => verify(proof),
+        Commands::Inspect { proof } => inspect(proof),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn prove(args: &ProveArgs) -> Result<(), ProveError> {
+    println!("============================================================");
+    println!("Prove program");
+    println!("============================================================");
+
+    let source =
+        fs::read_to_string(&args.program).map_err(|err| ProveError::Io(err.to_string()))?;
+    let advice_tape = match args.advice_tape.as_deref() {
+        Some(path) => read_advice_tape(path)?,
+        None => vec![],
+    };
+    let advice_map = match args.advice_map.as_deref() {
+        Some(path) => parse_advice_map(&read_file(path)?)?,
+        None => vec![],
+    };
+    let advice_sets = match args.advice_sets.as_deref() {
+        Some(path) => parse_advice_sets(&read_file(path)?)?,
+        None => vec![],
+    };
+    let inputs = program_inputs(&args.stack_inputs, &advice_tape, advice_map, advice_sets)?;
+    println!("Proving program {}", args.program);
+
+    let data = prove_program(
+        &source,
+        &inputs,
+        &args
+            .security
+            .proof_options(args.hash_fn, args.field_extension),
+    )?;
     println!(
-        "Generated a program to compute {}-th Fibonacci term; expected result: {}",
-        n, expected_result[0]
+        "Program output: {:?}",
+        data.public_inputs()?.outputs.stack()
+    );
+    println!(
+        "Proof size: {:.1} KB",
+        data.proof_bytes.len() as f64 / 1024f64
     );
 
-    // execute program and generate proof
-    let (outputs, proof) = prove(&program, &input_data, &proof_security)
-        .map_err(|err| format!("Failed to prove program - {:?}", err))
-        .unwrap();
-
-    let pub_inputs = PublicInputs::new(program.hash(), input_data.stack_init().to_vec(), outputs);
-    let input_bytes = pub_inputs.to_bytes();
+    data.write_to_file(&args.output)?;
+    println!("Proof written to {}", args.output);
+    Ok(())
+}
 
-    let proof_bytes = proof.to_bytes();
-    println!("Proof size: {:.1} KB", proof_bytes.len() as f64 / 1024f64);
+fn verify(proof_path: &str) -> Result<(), ProveError> {
+    let data = ProofData::from_file(proof_path)?;
+    verify_proof_data(&data)?;
+    println!("Proof {} is valid", proof_path);
+    Ok(())
+}
 
-    // Write proof to disk
-    let data = ProofData {
-        input_bytes,
-        proof_bytes,
-    };
+fn inspect(proof_path: &str) -> Result<(), ProveError> {
+    let data = ProofData::from_file(proof_path)?;
+    let pub_inputs = data.public_inputs()?;
+    let proof = data.proof()?;
+    let options = proof.options();
+    let trace_info = proof.get_trace_info();
 
-    miden_verifier::verify(pub_inputs.program_hash, &[0, 1], &pub_inputs.outputs, proof).unwrap();
-
-    let b = bincode::serialize(&data).unwrap();
-    let mut f = File::create("proofs/fib.bin").unwrap();
-    f.write_all(&b).unwrap();
-}
-
-/// Generates a program to compute the `n`-th term of Fibonacci sequence
-fn generate_fibonacci_program(n: usize) -> Program {
-    // the program is a simple repetition of 4 stack operations:
-    // the first operation moves the 2nd stack item to the top,
-    // the second operation duplicates the top 2 stack items,
-    // the third operation removes the top item from the stack
-    // the last operation pops top 2 stack items, adds them, and pushes
-    // the result back onto the stack
-    let program = format!(
-        "begin 
-            repeat.{}
-                swap dup.1 add
-            end
-        end",
-        n - 1
+    println!(
+        "Proof format: v{} (Miden {}, Winterfell {})",
+        data.header.format_version, data.header.miden_version, data.header.winterfell_version
     );
+    println!(
+        "Program hash: {}",
+        hex_digest(&pub_inputs.program_hash.to_bytes())
+    );
+    println!(
+        "Stack inputs: {:?}",
+        pub_inputs
+            .stack_inputs
+            .iter()
+            .rev()
+            .map(|e| e.as_int())
+            .collect::<Vec<_>>()
+    );
+    println!("Stack outputs: {:?}", pub_inputs.outputs.stack());
+    println!("Trace length: {}", trace_info.length());
+    println!("Trace width: {}", trace_info.width());
+    println!("Number of queries: {}", options.num_queries());
+    println!("Blowup factor: {}", options.blowup_factor());
+    println!("Grinding factor: {}", options.grinding_factor());
+    println!("Hash function: {:?}", options.hash_fn());
+    println!("Field extension: {:?}", options.field_extension());
+    println!("Security level: {} bits", proof.security_level(true));
+    println!(
+        "Proof size: {:.1} KB",
+        data.proof_bytes.len() as f64 / 1024f64
+    );
+    Ok(())
+}
 
-    Assembler::new()
-        .with_module_provider(StdLibrary::default())
-        .compile(&program)
-        .unwrap()
+fn read_file(path: &str) -> Result<String, ProveError> {
+    fs::read_to_string(path).map_err(|err| ProveError::Io(err.to_string()))
 }
 
-/// Computes the `n`-th term of Fibonacci sequence
-fn compute_fibonacci(n: usize) -> Felt {
-    let mut t0 = Felt::ZERO;
-    let mut t1 = Felt::ONE;
+/// Reads advice tape values separated by whitespace or commas
+fn read_advice_tape(path: &str) -> Result<Vec<u64>, ProveError> {
+    read_file(path)?
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .map_err(|err| ProveError::Inputs(format!("bad advice value {}: {}", value, err)))
+        })
+        .collect()
+}
 
-    for _ in 0..n {
-        t1 = t0 + t1;
-        core::mem::swap(&mut t0, &mut t1);
-    }
-    t0
+fn hex_digest(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
@@ -0,0 +1,240 @@
+use serde_json::json;
+use starknet_crypto::{poseidon_hash_many, FieldElement};
+
+use crate::error::ParserError;
+use crate::memory::{
+    build_cairo_memory, CairoMemory, DynamicMemory, MemoryFormat, Writeable, WriteableWith,
+};
+use crate::parser::ParsedProof;
+use crate::transcript::query_positions;
+use crate::{write_fri_remainder, FriProofParams};
+
+/// A slice of the verifier calldata sent in a single transaction
+pub struct CalldataChunk {
+    pub name: String,
+    pub calldata: CairoMemory,
+    /// `poseidon_hash_many([previous running hash, ...calldata])`, starting from zero
+    pub running_hash: String,
+}
+
+/// The verifier calldata in chunks, in the order a contract accumulates them. The running hash
+/// of the last chunk commits to the whole calldata
+pub struct ChunkedCalldata {
+    pub chunks: Vec<CalldataChunk>,
+}
+
+impl ChunkedCalldata {
+    pub fn commitment(&self) -> &str {
+        self.chunks
+            .last()
+            .map_or("0x0", |chunk| chunk.running_hash.as_str())
+    }
+
+    /// Boundaries of the chunks in the concatenated calldata
+    pub fn manifest(&self) -> serde_json::Value {
+        let mut offset = 0;
+        let chunks: Vec<_> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let length = chunk.calldata.entries().len();
+                let entry = json!({
+                    "index": index,
+                    "name": chunk.name,
+                    "offset": offset,
+                    "length": length,
+                    "running_hash": chunk.running_hash,
+                });
+                offset += length;
+                entry
+            })
+            .collect();
+        json!({
+            "chunks": chunks,
+            "length": offset,
+            "commitment": self.commitment(),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let chunks: Vec<_> = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                json!({
+                    "name": chunk.name,
+                    "running_hash": chunk.running_hash,
+                    "calldata": chunk.calldata.entries(),
+                })
+            })
+            .collect();
+        json!({
+            "manifest": self.manifest(),
+            "chunks": chunks,
+        })
+        .to_string()
+    }
+}
+
+/// Splits the verifier input of `proof` into the proof context, the commitments, the OOD frame,
+/// the trace and constraint queries and every FRI layer. Sections longer than `max_chunk_len`
+/// are split further
+pub fn calldata_chunks(
+    proof: &ParsedProof,
+    max_chunk_len: Option<usize>,
+) -> Result<ChunkedCalldata, ParserError> {
+    if max_chunk_len == Some(0) {
+        return Err(ParserError::Deserialization(
+            "the maximum chunk length must be positive".to_string(),
+        ));
+    }
+    proof.check_cairo_support()?;
+    let positions = query_positions(proof)?;
+    let air = &proof.air;
+    let stark_proof = &proof.proof;
+
+    let mut sections = vec![
+        section("context", |target| {
+            proof.pub_inputs.write_into(target)?;
+            stark_proof.context.write_into(target)?;
+            stark_proof.pow_nonce.write_into(target)
+        })?,
+        section("commitments", |target| {
+            stark_proof.commitments.write_into(target, air)
+        })?,
+        section("ood_frame", |target| {
+            stark_proof.ood_frame.write_into(target, air)
+        })?,
+        section("trace_queries", |target| {
+            stark_proof.trace_queries.write_into(target, air)?;
+            proof.write_trace_paths(target, &positions)
+        })?,
+        section("constraint_queries", |target| {
+            stark_proof.constraint_queries.write_into(target, air)?;
+            proof.write_constraint_paths(target, &positions)
+        })?,
+    ];
+    for layer in 0..stark_proof.fri_proof.num_layers() {
+        sections.push(section(&format!("fri_layer_{}", layer), |target| {
+            stark_proof.fri_proof.write_into(
+                target,
+                FriProofParams {
+                    air,
+                    indexes: &positions,
+                    layer: Some(layer),
+                },
+            )
+        })?);
+    }
+    sections.push(section("fri_remainder", |target| {
+        write_fri_remainder(&stark_proof.fri_proof, target, air)
+    })?);
+
+    let mut chunks = Vec::new();
+    let mut running_hash = FieldElement::ZERO;
+    for (name, calldata) in sections {
+        let entries = calldata.entries();
+        let parts: Vec<&[String]> = match max_chunk_len {
+            Some(max_len) if entries.len() > max_len => entries.chunks(max_len).collect(),
+            _ => vec![entries],
+        };
+        let num_parts = parts.len();
+        for (i, part) in parts.into_iter().enumerate() {
+            let mut elements = vec![running_hash];
+            for value in part {
+                elements.push(FieldElement::from_hex_be(value).map_err(|err| {
+                    ParserError::Deserialization(format!("invalid felt {}: {}", value, err))
+                })?);
+            }
+            running_hash = poseidon_hash_many(&elements);
+            chunks.push(CalldataChunk {
+                name: match num_parts {
+                    1 => name.clone(),
+                    _ => format!("{}[{}]", name, i),
+                },
+                calldata: CairoMemory::from(part.to_vec()),
+                running_hash: format!("0x{:x}", running_hash),
+            });
+        }
+    }
+    Ok(ChunkedCalldata { chunks })
+}
+
+fn section<F>(name: &str, write: F) -> Result<(String, CairoMemory), ParserError>
+where
+    F: FnOnce(&mut DynamicMemory) -> Result<(), ParserError>,
+{
+    Ok((
+        name.to_string(),
+        build_cairo_memory(MemoryFormat::Calldata, write)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let proof =
+            ParsedProof::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../proofs/fib.bin"))
+                .unwrap();
+        let whole = calldata_chunks(&proof, None).unwrap();
+        let split = calldata_chunks(&proof, Some(100)).unwrap();
+        assert!(split.chunks.len() > whole.chunks.len());
+        assert!(split
+            .chunks
+            .iter()
+            .all(|chunk| chunk.calldata.entries().len() <= 100));
+
+        let concat = |chunked: &ChunkedCalldata| -> Vec<String> {
+            chunked
+                .chunks
+                .iter()
+                .flat_map(|chunk| chunk.calldata.entries().to_vec())
+                .collect()
+        };
+        assert_eq!(concat(&whole), concat(&split));
+
+        for chunked in [&whole, &split] {
+            // every running hash chains the previous one with the chunk calldata
+            let mut running_hash = FieldElement::ZERO;
+            for chunk in chunked.chunks.iter() {
+                let elements: Vec<FieldElement> = std::iter::once(running_hash)
+                    .chain(
+                        chunk
+                            .calldata
+                            .entries()
+                            .iter()
+                            .map(|value| FieldElement::from_hex_be(value).unwrap()),
+                    )
+                    .collect();
+                running_hash = poseidon_hash_many(&elements);
+                assert_eq!(chunk.running_hash, format!("0x{:x}", running_hash));
+            }
+            assert_eq!(chunked.commitment(), format!("0x{:x}", running_hash));
+
+            // the manifest offsets are the prefix sums of the chunk lengths
+            let manifest = chunked.manifest();
+            let mut offset = 0;
+            for (chunk, entry) in chunked
+                .chunks
+                .iter()
+                .zip(manifest["chunks"].as_array().unwrap())
+            {
+                let length = chunk.calldata.entries().len();
+                assert_eq!(entry["offset"], json!(offset));
+                assert_eq!(entry["length"], json!(length));
+                assert_eq!(entry["running_hash"], json!(chunk.running_hash));
+                offset += length;
+            }
+            assert_eq!(
+                manifest["chunks"].as_array().unwrap().len(),
+                chunked.chunks.len()
+            );
+            assert_eq!(manifest["length"], json!(offset));
+            assert_eq!(offset, concat(chunked).len());
+        }
+    }
+}
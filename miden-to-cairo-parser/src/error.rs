@@ -0,0 +1,69 @@
+use std::fmt;
+
+use miden_proof_format::ProofDataError;
+
+/// Errors raised while a proof is parsed into Cairo memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParserError {
+    /// The proof file could not be read
+    Io(String),
+    /// The proof, its public inputs or a command argument could not be decoded
+    Deserialization(String),
+    /// The query indexes are malformed or outside of the evaluation domain
+    QueryIndexes(String),
+    /// A Merkle authentication path could not be rebuilt from a batch proof
+    MerklePath(String),
+    /// The proof does not have the shape the processor AIR expects
+    AirMismatch(String),
+    /// The Fiat-Shamir transcript could not be replayed, e.g. the proof of work is invalid
+    Transcript(String),
+    /// The proof uses options the Cairo verifier does not implement, e.g. a hash function other
+    /// than Blake2s
+    Unsupported(String),
+}
+
+impl ParserError {
+    /// Exit code of the CLI, so that scripts can tell the failures apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ParserError::Io(_) => 2,
+            ParserError::Deserialization(_) => 3,
+            ParserError::QueryIndexes(_) => 4,
+            ParserError::MerklePath(_) => 5,
+            ParserError::AirMismatch(_) => 6,
+            ParserError::Transcript(_) => 7,
+            ParserError::Unsupported(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::Io(err) => write!(f, "failed to read proof: {}", err),
+            ParserError::Deserialization(err) => write!(f, "failed to decode input: {}", err),
+            ParserError::QueryIndexes(err) => write!(f, "invalid query indexes: {}", err),
+            ParserError::MerklePath(err) => {
+                write!(f, "failed to build Merkle authentication paths: {}", err)
+            }
+            ParserError::AirMismatch(err) => {
+                write!(f, "proof does not match the processor AIR: {}", err)
+            }
+            ParserError::Transcript(err) => write!(f, "failed to replay transcript: {}", err),
+            ParserError::Unsupported(err) => {
+                write!(f, "proof is not supported by the Cairo verifier: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+impl From<ProofDataError> for ParserError {
+    fn from(err: ProofDataError) -> Self {
+        match err {
+            ProofDataError::Io(err) => ParserError::Io(err),
+            err => ParserError::Deserialization(err.to_string()),
+        }
+    }
+}
@@ -1,181 +1,240 @@
 #![feature(array_chunks)]
 use miden_air::StarkField;
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufReader, Read};
 use winter_crypto::RandomCoin;
 use winter_fri::FriProof;
-use winter_math::log2;
+use winter_math::{log2, FieldElement};
 
 use winter_air::proof::{Commitments, Context, OodFrame, Queries, Table};
 pub use winter_air::{
     ConstraintCompositionCoefficients, DeepCompositionCoefficients, EvaluationFrame, ProofOptions,
     TraceLayout,
 };
-use winter_crypto::{hash::ByteDigest, hashers::Blake2s_256, Digest};
+use winter_crypto::{hash::ByteDigest, Digest, ElementHasher, Hasher};
 pub use winterfell::{Air, AirContext, FieldExtension, HashFunction, StarkProof};
 use winterfell::{AuxTraceRandElements, ConstraintQueries, DeepComposer, TraceQueries};
 
 pub use miden_air::{Felt, ProcessorAir, PublicInputs};
 use miden_core::ProgramOutputs;
 
+pub use miden_proof_format::{with_field_extension, with_hasher, ProofData};
+
+pub mod chunks;
+pub mod error;
 pub mod memory;
+pub mod parser;
+pub mod transcript;
+pub use error::ParserError;
 use memory::{DynamicMemory, Writeable, WriteableWith};
 
-#[derive(Serialize, Deserialize)]
-pub struct BinaryProofData {
-    pub input_bytes: Vec<u8>,
-    pub proof_bytes: Vec<u8>,
-}
-
-impl BinaryProofData {
-    pub fn from_file(file_path: &String) -> BinaryProofData {
-        let file = File::open(file_path).unwrap();
-        let mut data = Vec::new();
-        BufReader::new(file)
-            .read_to_end(&mut data)
-            .expect("Unable to read data");
-        bincode::deserialize(&data).unwrap()
-    }
-}
-
 impl Writeable for PublicInputs {
-    fn write_into(&self, target: &mut DynamicMemory) {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         let program_hash_elements = self.program_hash.as_elements();
-        target.write_sized_array(program_hash_elements.to_vec());
-        target.write_sized_array(self.stack_inputs.clone());
-        self.outputs.write_into(target);
+        target.write_sized_array(program_hash_elements.to_vec())?;
+        target.write_sized_array(self.stack_inputs.clone())?;
+        self.outputs.write_into(target)
     }
 }
 
 impl Writeable for ProgramOutputs {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_sized_array(self.stack.clone());
-        target.write_sized_array(self.overflow_addrs.clone());
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_sized_array(self.stack.clone())?;
+        target.write_sized_array(self.overflow_addrs.clone())
     }
 }
 
 impl Writeable for (&u64, Felt) {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        self.0.write_into(target);
-        Writeable::write_into(&self.1, target);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        self.0.write_into(target)?;
+        Writeable::write_into(&self.1, target)
     }
 }
 
 impl WriteableWith<&ProcessorAir> for StarkProof {
-    fn write_into(&self, target: &mut DynamicMemory, air: &ProcessorAir) {
-        self.context.write_into(target);
-        self.commitments.write_into(target, air);
-        self.ood_frame.write_into(target, air);
-        self.pow_nonce.write_into(target);
-        self.trace_queries.write_into(target, air);
-        self.constraint_queries.write_into(target, air);
-        target.write_sized_array(self.fri_proof.parse_remainder::<Felt>().unwrap());
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        air: &ProcessorAir,
+    ) -> Result<(), ParserError> {
+        self.context.write_into(target)?;
+        self.commitments.write_into(target, air)?;
+        self.ood_frame.write_into(target, air)?;
+        self.pow_nonce.write_into(target)?;
+        self.trace_queries.write_into(target, air)?;
+        self.constraint_queries.write_into(target, air)?;
+        write_fri_remainder(&self.fri_proof, target, air)
     }
 }
 
+pub(crate) fn write_fri_remainder(
+    fri_proof: &FriProof,
+    target: &mut DynamicMemory,
+    air: &ProcessorAir,
+) -> Result<(), ParserError> {
+    with_field_extension!(air.options().field_extension(), |E| {
+        let remainder = fri_proof
+            .parse_remainder::<E>()
+            .map_err(|err| ParserError::AirMismatch(err.to_string()))?;
+        target.write_sized_array(base_elements(&remainder))
+    })
+}
+
 impl Writeable for Context {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        self.trace_layout().write_into(target);
-        self.trace_length().write_into(target);
-        log2(self.get_trace_info().length()).write_into(target);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        self.trace_layout().write_into(target)?;
+        self.trace_length().write_into(target)?;
+        log2(self.get_trace_info().length()).write_into(target)?;
 
-        self.get_trace_info().meta().len().write_into(target);
-        target.write_array(self.get_trace_info().meta().to_vec());
+        self.get_trace_info().meta().len().write_into(target)?;
+        target.write_array(self.get_trace_info().meta().to_vec())?;
 
-        self.field_modulus_bytes().len().write_into(target);
-        target.write_array(self.field_modulus_bytes().to_vec());
+        self.field_modulus_bytes().len().write_into(target)?;
+        target.write_array(self.field_modulus_bytes().to_vec())?;
 
-        self.options().write_into(target);
+        self.options().write_into(target)?;
 
-        self.lde_domain_size().write_into(target);
+        self.lde_domain_size().write_into(target)
     }
 }
 
 impl WriteableWith<&ProcessorAir> for Commitments {
-    fn write_into(&self, target: &mut DynamicMemory, air: &ProcessorAir) {
-        let num_trace_segments = air.trace_layout().num_segments();
-        let lde_domain_size = air.lde_domain_size();
-        let fri_options = air.options().to_fri_options();
-        let num_fri_layers = fri_options.num_fri_layers(lde_domain_size);
-
-        let (trace_commitments, constraint_commitment, fri_commitments) = self
-            .clone()
-            .parse::<Blake2s_256<Felt>>(num_trace_segments, num_fri_layers)
-            .unwrap();
-
-        target.write_array(
-            trace_commitments
-                .iter()
-                .map(|x| ByteDigest::new(x.as_bytes()))
-                .collect::<Vec<_>>(),
-        );
-
-        let mut temp_memory = target.alloc();
-        ByteDigest::new(constraint_commitment.as_bytes()).write_into(&mut temp_memory);
-
-        fri_commitments.len().write_into(target);
-        target.write_array(
-            fri_commitments
-                .iter()
-                .map(|x| ByteDigest::new(x.as_bytes()))
-                .collect::<Vec<_>>(),
-        );
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        air: &ProcessorAir,
+    ) -> Result<(), ParserError> {
+        with_hasher!(air.options().hash_fn(), |H| {
+            write_commitments::<H>(self, target, air)
+        })
     }
 }
 
+fn write_commitments<H: Hasher>(
+    commitments: &Commitments,
+    target: &mut DynamicMemory,
+    air: &ProcessorAir,
+) -> Result<(), ParserError> {
+    let num_trace_segments = air.trace_layout().num_segments();
+    let lde_domain_size = air.lde_domain_size();
+    let fri_options = air.options().to_fri_options();
+    let num_fri_layers = fri_options.num_fri_layers(lde_domain_size);
+
+    let (trace_commitments, constraint_commitment, fri_commitments) = commitments
+        .clone()
+        .parse::<H>(num_trace_segments, num_fri_layers)
+        .map_err(|err| ParserError::AirMismatch(err.to_string()))?;
+
+    // digests are padded to 32 bytes, so every hash function has the same memory layout
+    target.write_array(
+        trace_commitments
+            .iter()
+            .map(|x| ByteDigest::new(x.as_bytes()))
+            .collect::<Vec<_>>(),
+    )?;
+
+    let mut temp_memory = target.alloc();
+    ByteDigest::new(constraint_commitment.as_bytes()).write_into(&mut temp_memory)?;
+
+    fri_commitments.len().write_into(target)?;
+    target.write_array(
+        fri_commitments
+            .iter()
+            .map(|x| ByteDigest::new(x.as_bytes()))
+            .collect::<Vec<_>>(),
+    )
+}
+
 impl WriteableWith<&ProcessorAir> for OodFrame {
-    fn write_into(&self, target: &mut DynamicMemory, air: &ProcessorAir) {
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        air: &ProcessorAir,
+    ) -> Result<(), ParserError> {
         let main_trace_width = air.trace_layout().main_trace_width();
         let aux_trace_width = air.trace_layout().aux_trace_width();
         let num_evaluations = air.ce_blowup_factor();
-        let (ood_main_trace_frame, ood_aux_trace_frame, ood_constraint_evaluations) = self
-            .clone()
-            .parse::<Felt>(main_trace_width, aux_trace_width, num_evaluations)
-            .unwrap();
-
-        ood_main_trace_frame.write_into(target);
-        ood_aux_trace_frame.unwrap().write_into(target);
-        target.write_sized_array(ood_constraint_evaluations);
+        with_field_extension!(air.options().field_extension(), |E| {
+            let (ood_main_trace_frame, ood_aux_trace_frame, ood_constraint_evaluations) = self
+                .clone()
+                .parse::<E>(main_trace_width, aux_trace_width, num_evaluations)
+                .map_err(|err| ParserError::AirMismatch(err.to_string()))?;
+
+            ood_main_trace_frame.write_into(target)?;
+            ood_aux_trace_frame
+                .ok_or_else(|| {
+                    ParserError::AirMismatch("missing auxiliary trace OOD frame".to_string())
+                })?
+                .write_into(target)?;
+            target.write_sized_array(base_elements(&ood_constraint_evaluations))
+        })
     }
 }
 
 impl WriteableWith<&ProcessorAir> for Vec<Queries> {
-    fn write_into(&self, target: &mut DynamicMemory, air: &ProcessorAir) {
-        let trace_queries =
-            TraceQueries::<Felt, Blake2s_256<Felt>>::new(self.clone(), air).unwrap();
-        trace_queries.main_states.write_into(target);
-        trace_queries.aux_states.unwrap().write_into(target);
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        air: &ProcessorAir,
+    ) -> Result<(), ParserError> {
+        with_hasher!(air.options().hash_fn(), |H| {
+            with_field_extension!(air.options().field_extension(), |E| {
+                let trace_queries = TraceQueries::<E, H>::new(self.clone(), air)
+                    .map_err(|err| ParserError::AirMismatch(err.to_string()))?;
+                trace_queries.main_states.write_into(target)?;
+                trace_queries
+                    .aux_states
+                    .ok_or_else(|| {
+                        ParserError::AirMismatch("missing auxiliary trace queries".to_string())
+                    })?
+                    .write_into(target)
+            })
+        })
     }
 }
 
 impl WriteableWith<&ProcessorAir> for Queries {
-    fn write_into(&self, target: &mut DynamicMemory, air: &ProcessorAir) {
-        let constraint_queries =
-            ConstraintQueries::<Felt, Blake2s_256<Felt>>::new(self.clone(), air).unwrap();
-        constraint_queries.evaluations.write_into(target);
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        air: &ProcessorAir,
+    ) -> Result<(), ParserError> {
+        with_hasher!(air.options().hash_fn(), |H| {
+            with_field_extension!(air.options().field_extension(), |E| {
+                ConstraintQueries::<E, H>::new(self.clone(), air)
+                    .map_err(|err| ParserError::AirMismatch(err.to_string()))?
+                    .evaluations
+                    .write_into(target)
+            })
+        })
     }
 }
 
-impl Writeable for Table<Felt> {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        self.num_rows().write_into(target);
-        self.num_columns().write_into(target);
-        target.write_array(self.data().to_vec());
+// extension field elements are written as their base field coefficients, so every table,
+// frame and coefficient array below is laid out over the base field
+pub(crate) fn base_elements<E: FieldElement<BaseField = Felt>>(elements: &[E]) -> Vec<Felt> {
+    E::as_base_elements(elements).to_vec()
+}
+
+impl<E: FieldElement<BaseField = Felt>> Writeable for Table<E> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        self.num_rows().write_into(target)?;
+        (self.num_columns() * E::EXTENSION_DEGREE).write_into(target)?;
+        target.write_array(base_elements(self.data()))
     }
 }
 
-impl Writeable for ByteDigest<32> {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        for chunk in self.0.to_vec().array_chunks::<4>() {
+impl<const N: usize> Writeable for ByteDigest<N> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        // shorter digests are zero padded to 32 bytes
+        for chunk in self.as_bytes().array_chunks::<4>() {
             let int = u32::from_le_bytes(*chunk);
-            int.write_into(target);
+            int.write_into(target)?;
         }
+        Ok(())
     }
 }
 
 impl Writeable for TraceLayout {
-    fn write_into(&self, target: &mut DynamicMemory) {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         let mut aux_segment_widths = Vec::new();
         let mut aux_segment_rands = Vec::new();
 
@@ -184,62 +243,63 @@ impl Writeable for TraceLayout {
             aux_segment_rands.push(self.get_aux_segment_rand_elements(i));
         }
 
-        self.main_trace_width().write_into(target);
-        self.num_aux_segments().write_into(target);
-        target.write_array(aux_segment_widths);
-        target.write_array(aux_segment_rands);
+        self.main_trace_width().write_into(target)?;
+        self.num_aux_segments().write_into(target)?;
+        target.write_array(aux_segment_widths)?;
+        target.write_array(aux_segment_rands)
     }
 }
 
 impl Writeable for ProofOptions {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        self.num_queries().write_into(target);
-        self.blowup_factor().write_into(target);
-        log2(self.blowup_factor()).write_into(target);
-        self.grinding_factor().write_into(target);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        self.num_queries().write_into(target)?;
+        self.blowup_factor().write_into(target)?;
+        log2(self.blowup_factor()).write_into(target)?;
+        self.grinding_factor().write_into(target)?;
 
-        self.hash_fn().write_into(target);
-        self.field_extension().write_into(target);
+        self.hash_fn().write_into(target)?;
+        self.field_extension().write_into(target)?;
 
         let fri_options = self.to_fri_options();
-        fri_options.folding_factor().write_into(target);
-        fri_options.max_remainder_size().write_into(target);
+        fri_options.folding_factor().write_into(target)?;
+        fri_options.max_remainder_size().write_into(target)
     }
 }
 
 impl Writeable for HashFunction {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        (*self as u8).write_into(target);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        (*self as u8).write_into(target)
     }
 }
 
 impl Writeable for FieldExtension {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        (*self as u8).write_into(target);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        (*self as u8).write_into(target)
     }
 }
 
-impl Writeable for EvaluationFrame<Felt> {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_sized_array(self.current().to_vec());
-        target.write_sized_array(self.next().to_vec());
+impl<E: FieldElement<BaseField = Felt>> Writeable for EvaluationFrame<E> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_sized_array(base_elements(self.current()))?;
+        target.write_sized_array(base_elements(self.next()))
     }
 }
 
 impl Writeable for Felt {
-    fn write_into(&self, target: &mut DynamicMemory) {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         let raw = self.as_int();
         let mut hex_string = "0x".to_owned();
         for byte in raw.to_be_bytes().iter() {
             hex_string.push_str(&format!("{:02x}", byte));
         }
         target.write_hex_value(hex_string);
+        Ok(())
     }
 }
 
 impl Writeable for [u8; 32] {
     // Convert 32 x u8 to 8 x u32
-    fn write_into(&self, target: &mut DynamicMemory) {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         let mut uint32_array = Vec::new();
         for i in 0..8 {
             let mut uint32 = 0;
@@ -252,7 +312,7 @@ impl Writeable for [u8; 32] {
             }
             uint32_array.push(uint32);
         }
-        target.write_array(uint32_array);
+        target.write_array(uint32_array)
     }
 }
 
@@ -262,10 +322,14 @@ pub struct ProcessorAirParams<'a> {
 }
 
 impl WriteableWith<ProcessorAirParams<'_>> for ProcessorAir {
-    fn write_into(&self, target: &mut DynamicMemory, params: ProcessorAirParams) {
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        params: ProcessorAirParams,
+    ) -> Result<(), ParserError> {
         // Layout
-        self.trace_layout().main_trace_width().write_into(target);
-        self.trace_layout().aux_trace_width().write_into(target);
+        self.trace_layout().main_trace_width().write_into(target)?;
+        self.trace_layout().aux_trace_width().write_into(target)?;
 
         let mut aux_segment_widths = vec![];
         let mut aux_segment_rands = vec![];
@@ -276,41 +340,41 @@ impl WriteableWith<ProcessorAirParams<'_>> for ProcessorAir {
                     .get_aux_segment_rand_elements(segment_idx),
             );
         }
-        target.write_array(aux_segment_widths);
-        target.write_array(aux_segment_rands);
-        self.trace_layout().num_aux_segments().write_into(target);
+        target.write_array(aux_segment_widths)?;
+        target.write_array(aux_segment_rands)?;
+        self.trace_layout().num_aux_segments().write_into(target)?;
 
         // Context
-        self.options().write_into(target);
-        params.proof.context.write_into(target);
+        self.options().write_into(target)?;
+        params.proof.context.write_into(target)?;
 
         self.context()
             .num_transition_constraints()
-            .write_into(target);
-        self.context().num_assertions().write_into(target);
+            .write_into(target)?;
+        self.context().num_assertions().write_into(target)?;
 
-        self.ce_blowup_factor().write_into(target);
+        self.ce_blowup_factor().write_into(target)?;
         // self.eval_frame_size::<Felt>().write_into(target);
 
-        self.trace_domain_generator().write_into(target);
-        self.lde_domain_generator().write_into(target);
+        self.trace_domain_generator().write_into(target)?;
+        self.lde_domain_generator().write_into(target)?;
 
         // pub_inputs is a pointer to a PublicInput
         let mut child_target = target.alloc();
-        params.public_inputs.write_into(&mut child_target);
+        params.public_inputs.write_into(&mut child_target)
     }
 }
 
-impl Writeable for ConstraintCompositionCoefficients<Felt> {
-    fn write_into(&self, target: &mut DynamicMemory) {
+impl<E: FieldElement<BaseField = Felt>> Writeable for ConstraintCompositionCoefficients<E> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         let mut transition_a = Vec::new();
         let mut transition_b = Vec::new();
         for elem in self.transition.iter().cloned() {
             transition_a.push(elem.0);
             transition_b.push(elem.1);
         }
-        target.write_array(transition_a);
-        target.write_array(transition_b);
+        target.write_array(base_elements(&transition_a))?;
+        target.write_array(base_elements(&transition_b))?;
 
         let mut boundary_a = Vec::new();
         let mut boundary_b = Vec::new();
@@ -318,104 +382,147 @@ impl Writeable for ConstraintCompositionCoefficients<Felt> {
             boundary_a.push(elem.0);
             boundary_b.push(elem.1);
         }
-        target.write_array(boundary_a);
-        target.write_array(boundary_b);
+        target.write_array(base_elements(&boundary_a))?;
+        target.write_array(base_elements(&boundary_b))
     }
 }
 
-impl Writeable for AuxTraceRandElements<Felt> {
-    fn write_into(&self, target: &mut DynamicMemory) {
+impl<E: FieldElement<BaseField = Felt>> Writeable for AuxTraceRandElements<E> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         // let mut child_target = target.alloc();
-        for elems in self.0.iter().cloned() {
-            target.write_array(elems);
+        for elems in self.0.iter() {
+            target.write_array(base_elements(elems))?;
         }
+        Ok(())
     }
 }
 
-impl Writeable for RandomCoin<Felt, Blake2s_256<Felt>> {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        self.seed.as_bytes().write_into(target);
-        self.counter.write_into(target);
+impl<H: ElementHasher<BaseField = Felt>> Writeable for RandomCoin<Felt, H> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        self.seed.as_bytes().write_into(target)?;
+        self.counter.write_into(target)
     }
 }
 
-impl Writeable for DeepCompositionCoefficients<Felt> {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        let mut child_target = target.alloc();
+impl<E: FieldElement<BaseField = Felt>> Writeable for DeepCompositionCoefficients<E> {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        let mut child_target = target.alloc_array(self.trace.len());
         for elem in &self.trace {
-            child_target.write_sized_array(vec![elem.0, elem.1, elem.2]);
+            child_target.write_sized_array(base_elements(&[elem.0, elem.1, elem.2]))?;
         }
-        target.write_array(self.constraints.clone());
-        self.degree.0.write_into(target);
-        self.degree.1.write_into(target);
+        target.write_array(base_elements(&self.constraints))?;
+        for elem in base_elements(&[self.degree.0, self.degree.1]) {
+            elem.write_into(target)?;
+        }
+        Ok(())
     }
 }
 
 impl Writeable for DeepComposer<Felt> {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        self.cc.write_into(target);
-        target.write_array(self.x_coordinates.to_vec());
-        self.z[0].write_into(target);
-        self.z[1].write_into(target);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        self.cc.write_into(target)?;
+        target.write_array(self.x_coordinates.to_vec())?;
+        self.z[0].write_into(target)?;
+        self.z[1].write_into(target)
     }
 }
 
-impl WriteableWith<&[usize]> for TraceQueries<Felt, Blake2s_256<Felt>> {
-    fn write_into(&self, target: &mut DynamicMemory, indexes: &[usize]) {
+impl<E, H> WriteableWith<&[usize]> for TraceQueries<E, H>
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+    H::Digest: Writeable,
+{
+    fn write_into(&self, target: &mut DynamicMemory, indexes: &[usize]) -> Result<(), ParserError> {
         for query_proof in &self.query_proofs {
-            let paths = query_proof.into_paths(indexes).unwrap();
-            // for p in &paths[0] {
-            //     for word in p.to_words() {
-            //         println!("{:2x}", word);
-            //     }
-            // }
-            let mut child_target = target.alloc();
+            let paths = query_proof
+                .into_paths(indexes)
+                .map_err(|err| ParserError::MerklePath(err.to_string()))?;
+            let mut child_target = target.alloc_array(paths.len());
             for path in paths {
-                child_target.write_sized_array(path);
+                child_target.write_sized_array(path)?;
             }
         }
+        Ok(())
     }
 }
 
-impl WriteableWith<&[usize]> for ConstraintQueries<Felt, Blake2s_256<Felt>> {
-    fn write_into(&self, target: &mut DynamicMemory, indexes: &[usize]) {
-        let paths = self.query_proofs.into_paths(indexes).unwrap();
-        let mut child_target = target.alloc();
+impl<E, H> WriteableWith<&[usize]> for ConstraintQueries<E, H>
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+    H::Digest: Writeable,
+{
+    fn write_into(&self, target: &mut DynamicMemory, indexes: &[usize]) -> Result<(), ParserError> {
+        let paths = self
+            .query_proofs
+            .into_paths(indexes)
+            .map_err(|err| ParserError::MerklePath(err.to_string()))?;
+        let mut child_target = target.alloc_array(paths.len());
         for path in paths {
-            child_target.write_sized_array(path);
+            child_target.write_sized_array(path)?;
         }
+        Ok(())
     }
 }
 
 pub struct FriProofParams<'a> {
     pub air: &'a ProcessorAir,
     pub indexes: &'a Vec<usize>,
+    /// Writes only this layer, the positions are still folded through the layers before it
+    pub layer: Option<usize>,
 }
 
 impl WriteableWith<FriProofParams<'_>> for FriProof {
-    fn write_into(&self, target: &mut DynamicMemory, params: FriProofParams) {
-        let air = &params.air;
-        let folding_factor = air.options().to_fri_options().folding_factor();
-        let (queries_values, proofs) = self
-            .clone()
-            .parse_layers::<Blake2s_256<Felt>, Felt>(air.lde_domain_size(), folding_factor)
-            .unwrap();
-        let mut indices = params.indexes.clone();
-        let mut source_domain_size = air.lde_domain_size();
-
-        for (proof, query_values) in proofs.into_iter().zip(queries_values) {
-            indices = fold_positions(&indices, source_domain_size, folding_factor);
-            let mut child_target = target.alloc();
-            source_domain_size /= folding_factor;
-            let paths = proof.into_paths(&indices).unwrap();
-            for (index, path) in paths.iter().enumerate() {
-                child_target.write_sized_array(path.to_vec());
-                let query_values =
-                    &query_values[index * folding_factor..(index + 1) * folding_factor];
-                child_target.write_array(query_values.to_vec());
-            }
+    fn write_into(
+        &self,
+        target: &mut DynamicMemory,
+        params: FriProofParams,
+    ) -> Result<(), ParserError> {
+        with_hasher!(params.air.options().hash_fn(), |H| {
+            with_field_extension!(params.air.options().field_extension(), |E| {
+                write_fri_proof::<E, H>(self, target, params)
+            })
+        })
+    }
+}
+
+fn write_fri_proof<E, H>(
+    fri_proof: &FriProof,
+    target: &mut DynamicMemory,
+    params: FriProofParams,
+) -> Result<(), ParserError>
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+    H::Digest: Writeable,
+{
+    let air = &params.air;
+    let folding_factor = air.options().to_fri_options().folding_factor();
+    let (queries_values, proofs) = fri_proof
+        .clone()
+        .parse_layers::<H, E>(air.lde_domain_size(), folding_factor)
+        .map_err(|err| ParserError::AirMismatch(err.to_string()))?;
+    let mut indices = params.indexes.clone();
+    let mut source_domain_size = air.lde_domain_size();
+
+    for (layer, (proof, query_values)) in proofs.into_iter().zip(queries_values).enumerate() {
+        indices = fold_positions(&indices, source_domain_size, folding_factor);
+        source_domain_size /= folding_factor;
+        if params.layer.map_or(false, |only| only != layer) {
+            continue;
+        }
+        let paths = proof
+            .into_paths(&indices)
+            .map_err(|err| ParserError::MerklePath(err.to_string()))?;
+        let mut child_target = target.alloc_array(paths.len());
+        for (index, path) in paths.iter().enumerate() {
+            child_target.write_sized_array(path.to_vec())?;
+            let query_values = &query_values[index * folding_factor..(index + 1) * folding_factor];
+            child_target.write_array(base_elements(query_values))?;
         }
     }
+    Ok(())
 }
 
 pub fn fold_positions(
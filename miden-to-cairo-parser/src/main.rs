@@ -1,125 +1,112 @@
-use hex::FromHex;
 use miden_to_cairo_parser::{
-    memory::{Writeable, WriteableWith},
-    Air, BinaryProofData, Felt, FriProofParams, ProcessorAir, PublicInputs, StarkProof,
+    memory::MemoryFormat,
+    parser::{interpolate_poly, parse_felts, parse_indexes, ParsedProof},
+    ParserError,
 };
-use serde_json::from_str;
-use winter_crypto::hashers::Blake2s_256;
-use winter_math::polynom::interpolate;
-use winter_utils::{Deserializable, SliceReader};
-use winterfell::VerifierChannel;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(name = "parser")]
 #[command(about = "A parser for reencoding STARK proofs", long_about = None)]
 struct Cli {
     path: String,
+    /// Layout of the printed memory
+    #[arg(long, value_enum, default_value_t = Format::Hint)]
+    format: Format,
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Segments and pointers loaded by the Cairo 0 hints
+    Hint,
+    /// Starknet calldata in the Cairo 1 `Serde` layout
+    Calldata,
+}
+
+impl From<Format> for MemoryFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Hint => MemoryFormat::Hint,
+            Format::Calldata => MemoryFormat::Calldata,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     Proof,
     PublicInputs,
     TraceQueries {
-        indexes: Option<String>,
+        indexes: String,
     },
     ConstraintQueries {
-        indexes: Option<String>,
+        indexes: String,
     },
     FriQueries {
-        indexes: Option<String>,
+        indexes: String,
+    },
+    /// Proof, public inputs and every query decommitment at the positions the verifier draws
+    Bundle,
+    /// Verifier input as Starknet calldata chunks with running hashes, and their manifest
+    Chunks {
+        /// Split sections longer than this many felts
+        #[arg(long)]
+        max_chunk_len: Option<usize>,
     },
+    /// Coin states and values drawn or read at each stage of the native verifier
+    Transcript,
     InterpolatePoly {
-        x_values: Option<String>,
-        y_values: Option<String>,
+        x_values: String,
+        y_values: String,
     },
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    // Load the proof and its public inputs from file
-    let data = BinaryProofData::from_file(&cli.path);
-    let proof = StarkProof::from_bytes(&data.proof_bytes).unwrap();
-    let pub_inputs = PublicInputs::read_from(&mut SliceReader::new(&data.input_bytes[..])).unwrap();
-
-    // Serialize to Cairo-compatible memory
-    let json_arr = match &cli.command {
-        Commands::Proof => {
-            let air =
-                ProcessorAir::new(proof.get_trace_info(), pub_inputs, proof.options().clone());
-            proof.to_cairo_memory(&air)
+    match run(&cli) {
+        Ok(output) => println!("{}", output),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(err.exit_code());
         }
-        Commands::PublicInputs => pub_inputs.to_cairo_memory(),
-        Commands::TraceQueries { indexes } => {
-            let air = ProcessorAir::new(
-                proof.get_trace_info(),
-                pub_inputs.clone(),
-                proof.options().clone(),
-            );
-
-            let indexes: Vec<usize> = from_str(&indexes.clone().unwrap()).unwrap();
+    }
+}
 
-            let channel =
-                VerifierChannel::<Felt, Blake2s_256<Felt>>::new(&air, proof.clone()).unwrap();
+fn run(cli: &Cli) -> Result<String, ParserError> {
+    // Load the proof and its public inputs from file, the polynomial interpolation does not
+    // read the proof
+    let proof = || -> Result<ParsedProof, ParserError> {
+        Ok(ParsedProof::from_file(&cli.path)?.with_format(cli.format.into()))
+    };
 
-            channel.trace_queries.unwrap().to_cairo_memory(&indexes)
+    // Serialize to Cairo-compatible memory
+    let memory = match &cli.command {
+        Commands::Proof => proof()?.proof_memory()?,
+        Commands::PublicInputs => proof()?.public_inputs_memory()?,
+        Commands::TraceQueries { indexes } => {
+            proof()?.trace_queries_memory(&parse_indexes(indexes)?)?
         }
         Commands::ConstraintQueries { indexes } => {
-            let air = ProcessorAir::new(
-                proof.get_trace_info(),
-                pub_inputs.clone(),
-                proof.options().clone(),
-            );
-
-            let indexes: Vec<usize> = from_str(&indexes.clone().unwrap()).unwrap();
-
-            let channel =
-                VerifierChannel::<Felt, Blake2s_256<Felt>>::new(&air, proof.clone()).unwrap();
-
-            channel
-                .constraint_queries
-                .unwrap()
-                .to_cairo_memory(&indexes)
+            proof()?.constraint_queries_memory(&parse_indexes(indexes)?)?
         }
         Commands::FriQueries { indexes } => {
-            let air = ProcessorAir::new(
-                proof.get_trace_info(),
-                pub_inputs.clone(),
-                proof.options().clone(),
-            );
-
-            let indexes: Vec<usize> = from_str(&indexes.clone().unwrap()).unwrap();
-            proof.fri_proof.to_cairo_memory(FriProofParams {
-                air: &air,
-                indexes: &indexes,
-            })
+            proof()?.fri_queries_memory(&parse_indexes(indexes)?)?
         }
+        Commands::Bundle => return Ok(proof()?.verifier_bundle()?.to_json()),
+        Commands::Chunks { max_chunk_len } => {
+            return Ok(proof()?.calldata_chunks(*max_chunk_len)?.to_json())
+        }
+        Commands::Transcript => return Ok(proof()?.transcript()?.to_json()),
         Commands::InterpolatePoly { x_values, y_values } => {
-            let x_values = decode_felt_array(x_values);
-            let y_values = decode_felt_array(y_values);
-
-            let poly = interpolate(&x_values, &y_values, false);
-            poly.iter()
-                .fold(String::new(), |a, x| a + ", " + &x.to_string())
+            let poly = interpolate_poly(&parse_felts(x_values)?, &parse_felts(y_values)?)?;
+            return Ok(poly
+                .iter()
+                .fold(String::new(), |a, x| a + ", " + &x.to_string()));
         }
     };
-
-    println!("{}", json_arr);
-}
-
-fn decode_felt_array(values: &Option<String>) -> Vec<Felt> {
-    let values: Vec<String> = from_str(&values.clone().unwrap()).unwrap();
-    values
-        .into_iter()
-        .map(|value| {
-            let decoded = <[u8; 8]>::from_hex(value).unwrap();
-            let d = u64::from_le_bytes(decoded);
-            Felt::new(d)
-        })
-        .collect()
+    Ok(memory.to_json())
 }
@@ -1,6 +1,23 @@
+use std::fmt;
+
+use crate::error::ParserError;
+
+/// Layout the written objects are assembled into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryFormat {
+    /// Segments concatenated with relative pointers, loaded by Cairo 0 hints with
+    /// `segments.write_arg`
+    Hint,
+    /// Starknet calldata following the Cairo 1 `Serde` conventions, arrays are inlined after
+    /// their length and there are no pointers
+    Calldata,
+}
+
 pub enum MemoryEntry {
     Value { value: String },
     Pointer { pointer: usize },
+    // length written in front of an array pointer, the calldata array carries its own length
+    Size { value: u64 },
 }
 
 impl MemoryEntry {
@@ -8,6 +25,7 @@ impl MemoryEntry {
         match self {
             MemoryEntry::Value { value } => value.to_string(),
             MemoryEntry::Pointer { pointer } => format!("{}", pointers_map[*pointer]),
+            MemoryEntry::Size { value } => format!("{:#X}", value),
         }
     }
 
@@ -26,7 +44,20 @@ impl MemoryEntry {
     }
 }
 
-type Memory = Vec<MemoryEntry>;
+/// A memory segment, either an array of `len` objects or a single object behind a pointer
+pub struct Memory {
+    len: Option<usize>,
+    entries: Vec<MemoryEntry>,
+}
+
+impl Memory {
+    fn new(len: Option<usize>) -> Memory {
+        Memory {
+            len,
+            entries: Vec::new(),
+        }
+    }
+}
 
 pub struct DynamicMemory<'a> {
     memories: &'a mut Vec<Memory>,
@@ -35,21 +66,32 @@ pub struct DynamicMemory<'a> {
 
 impl<'a> DynamicMemory<'a> {
     pub fn new(memories: &'a mut Vec<Memory>) -> DynamicMemory<'a> {
-        memories.push(Vec::<MemoryEntry>::new());
+        memories.push(Memory::new(None));
         DynamicMemory {
             memories,
             segment: 0,
         }
     }
 
-    pub fn assemble(&self) -> Vec<String> {
+    pub fn assemble(&self, format: MemoryFormat) -> Vec<String> {
+        match format {
+            MemoryFormat::Hint => self.assemble_hint(),
+            MemoryFormat::Calldata => {
+                let mut calldata = Vec::new();
+                self.assemble_calldata(self.segment, &mut calldata);
+                calldata
+            }
+        }
+    }
+
+    fn assemble_hint(&self) -> Vec<String> {
         // Concatenate all memories and compute a mapping for pointers
         let mut concatenated = Vec::<&MemoryEntry>::new();
         let mut pointers_map = Vec::new();
 
         for vector in self.memories.iter() {
             pointers_map.push(concatenated.len());
-            concatenated.extend(vector);
+            concatenated.extend(&vector.entries);
         }
 
         // Iterate through all memory entries and map the pointers
@@ -61,8 +103,28 @@ impl<'a> DynamicMemory<'a> {
         memory
     }
 
+    // inlines the segments behind pointers depth first, arrays are prefixed with their length
+    fn assemble_calldata(&self, segment: usize, calldata: &mut Vec<String>) {
+        for entry in self.memories[segment].entries.iter() {
+            match entry {
+                MemoryEntry::Value { value } => calldata.push(value.to_string()),
+                MemoryEntry::Pointer { pointer } => {
+                    if let Some(len) = self.memories[*pointer].len {
+                        calldata.push(format!("{:#X}", len));
+                    }
+                    self.assemble_calldata(*pointer, calldata);
+                }
+                MemoryEntry::Size { .. } => {}
+            }
+        }
+    }
+
     fn write_entry(&mut self, entry: MemoryEntry) {
-        self.memories.get_mut(self.segment).unwrap().push(entry);
+        self.memories
+            .get_mut(self.segment)
+            .unwrap()
+            .entries
+            .push(entry);
     }
 
     pub fn write_pointer(&mut self, pointer: usize) {
@@ -77,113 +139,254 @@ impl<'a> DynamicMemory<'a> {
         self.write_entry(MemoryEntry::from_hex(value))
     }
 
-    pub fn write_array<T: Writeable>(&mut self, array: Vec<T>) {
-        let mut sub_memory = self.alloc();
+    pub fn write_array<T: Writeable>(&mut self, array: Vec<T>) -> Result<(), ParserError> {
+        let mut sub_memory = self.alloc_array(array.len());
         for writable in array {
-            writable.write_into(&mut sub_memory);
+            writable.write_into(&mut sub_memory)?;
         }
+        Ok(())
     }
 
-    pub fn write_array_with<Params, T: WriteableWith<Params>, F>(&mut self, array: Vec<T>, f: F)
+    pub fn write_array_with<Params, T: WriteableWith<Params>, F>(
+        &mut self,
+        array: Vec<T>,
+        f: F,
+    ) -> Result<(), ParserError>
     where
         F: Fn(u32) -> Params,
     {
-        let mut sub_memory = self.alloc();
+        let mut sub_memory = self.alloc_array(array.len());
         let mut i = 0;
         for writable in array {
-            writable.write_into(&mut sub_memory, f(i));
+            writable.write_into(&mut sub_memory, f(i))?;
             i += 1;
         }
+        Ok(())
     }
 
+    /// Allocates a segment holding a single object
     pub fn alloc(&mut self) -> DynamicMemory {
+        self.alloc_segment(None)
+    }
+
+    /// Allocates a segment holding `len` objects, which calldata prefixes with `len`
+    pub fn alloc_array(&mut self, len: usize) -> DynamicMemory {
+        self.alloc_segment(Some(len))
+    }
+
+    fn alloc_segment(&mut self, len: Option<usize>) -> DynamicMemory {
         let segment = self.memories.len();
         self.write_pointer(segment);
-        self.memories.push(Vec::<MemoryEntry>::new());
+        self.memories.push(Memory::new(len));
         DynamicMemory {
             memories: self.memories,
             segment,
         }
     }
 
-    pub fn write_sized_array<T: Writeable>(&mut self, array: Vec<T>) {
-        self.write_value(array.len() as u64);
-        self.write_array(array);
+    pub fn write_sized_array<T: Writeable>(&mut self, array: Vec<T>) -> Result<(), ParserError> {
+        self.write_entry(MemoryEntry::Size {
+            value: array.len() as u64,
+        });
+        self.write_array(array)
     }
 
     pub fn write_sized_array_with<Params, T: WriteableWith<Params>, F>(
         &mut self,
         array: Vec<T>,
         f: F,
-    ) where
+    ) -> Result<(), ParserError>
+    where
         F: Fn(u32) -> Params,
     {
-        self.write_value(array.len() as u64);
-        self.write_array_with(array, f);
+        self.write_entry(MemoryEntry::Size {
+            value: array.len() as u64,
+        });
+        self.write_array_with(array, f)
+    }
+}
+
+/// Assembled Cairo memory, felts are `0x` prefixed hex strings. In the hint format pointers are
+/// decimal offsets relative to the start of the memory, calldata has no pointers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CairoMemory(Vec<String>);
+
+impl CairoMemory {
+    pub fn entries(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Encodes the memory as the JSON array the Cairo hints load with `write_into_memory`
+    pub fn to_json(&self) -> String {
+        // a list of strings always serializes
+        serde_json::to_string(&self.0).unwrap()
+    }
+}
+
+impl From<Vec<String>> for CairoMemory {
+    fn from(entries: Vec<String>) -> Self {
+        CairoMemory(entries)
+    }
+}
+
+impl fmt::Display for CairoMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_json())
     }
 }
 
+/// Assembles the memory written by `write`, for values that are not a single `Writeable`
+pub fn build_cairo_memory<F>(format: MemoryFormat, write: F) -> Result<CairoMemory, ParserError>
+where
+    F: FnOnce(&mut DynamicMemory) -> Result<(), ParserError>,
+{
+    let mut memories = Vec::<Memory>::new();
+    let mut dynamic_memory = DynamicMemory::new(&mut memories);
+    write(&mut dynamic_memory)?;
+    Ok(CairoMemory(dynamic_memory.assemble(format)))
+}
+
 pub trait Writeable: Sized {
-    fn write_into(&self, target: &mut DynamicMemory);
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError>;
 
-    fn to_cairo_memory(&self) -> String {
-        let mut memories = Vec::<Vec<MemoryEntry>>::new();
-        let mut dynamic_memory = DynamicMemory::new(&mut memories);
-        self.write_into(&mut dynamic_memory);
-        let memory = dynamic_memory.assemble();
-        let json_arr = serde_json::to_string(&memory).unwrap();
-        json_arr
+    fn to_cairo_memory(&self, format: MemoryFormat) -> Result<CairoMemory, ParserError> {
+        build_cairo_memory(format, |target| self.write_into(target))
     }
 }
 
 pub trait WriteableWith<Parameters> {
-    fn write_into(&self, target: &mut DynamicMemory, params: Parameters);
+    fn write_into(&self, target: &mut DynamicMemory, params: Parameters)
+        -> Result<(), ParserError>;
 
-    fn to_cairo_memory(&self, params: Parameters) -> String {
-        let mut memories = Vec::<Vec<MemoryEntry>>::new();
-        let mut dynamic_memory = DynamicMemory::new(&mut memories);
-        self.write_into(&mut dynamic_memory, params);
-        let memory = dynamic_memory.assemble();
-        let json_arr = serde_json::to_string(&memory).unwrap();
-        json_arr
+    fn to_cairo_memory(
+        &self,
+        params: Parameters,
+        format: MemoryFormat,
+    ) -> Result<CairoMemory, ParserError> {
+        build_cairo_memory(format, |target| self.write_into(target, params))
     }
 }
 
 impl Writeable for Vec<u8> {
-    fn write_into(&self, target: &mut DynamicMemory) {
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
         target.write_value(self.len() as u64);
         for byte in self {
             target.write_value(*byte as u64);
         }
+        Ok(())
     }
 }
 
 impl Writeable for u8 {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_value(*self as u64)
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_value(*self as u64);
+        Ok(())
     }
 }
 
 impl Writeable for u16 {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_value(*self as u64)
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_value(*self as u64);
+        Ok(())
     }
 }
 
 impl Writeable for u32 {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_value(*self as u64)
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_value(*self as u64);
+        Ok(())
     }
 }
 
 impl Writeable for u64 {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_value(*self)
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_value(*self);
+        Ok(())
     }
 }
 
 impl Writeable for usize {
-    fn write_into(&self, target: &mut DynamicMemory) {
-        target.write_value(*self as u64)
+    fn write_into(&self, target: &mut DynamicMemory) -> Result<(), ParserError> {
+        target.write_value(*self as u64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParsedProof;
+
+    #[test]
+    fn test_memory_formats() {
+        let mut memories = Vec::<Memory>::new();
+        let mut memory = DynamicMemory::new(&mut memories);
+        memory.write_value(1);
+        memory.write_sized_array(vec![2u64, 3]).unwrap();
+        memory.alloc().write_value(4);
+
+        let hint = memory.assemble(MemoryFormat::Hint);
+        assert_eq!(hint, ["0x1", "0x2", "4", "6", "0x2", "0x3", "0x4"]);
+        let calldata = memory.assemble(MemoryFormat::Calldata);
+        assert_eq!(calldata, ["0x1", "0x2", "0x2", "0x3", "0x4"]);
+    }
+
+    #[test]
+    fn test_formats_encode_same_proof() {
+        let proof =
+            ParsedProof::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../proofs/fib.bin"))
+                .unwrap();
+        let mut memories = Vec::<Memory>::new();
+        let mut memory = DynamicMemory::new(&mut memories);
+        proof.proof.write_into(&mut memory, &proof.air).unwrap();
+        let hint = memory.assemble(MemoryFormat::Hint);
+        let calldata = memory.assemble(MemoryFormat::Calldata);
+
+        let offsets: Vec<usize> = memories
+            .iter()
+            .scan(0, |offset, segment| {
+                let start = *offset;
+                *offset += segment.entries.len();
+                Some(start)
+            })
+            .collect();
+        let mut decoded = Vec::new();
+        follow_hint(&hint, &memories, &offsets, 0, &mut decoded);
+        assert_eq!(decoded, calldata);
+        assert_eq!(
+            ParsedProof::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../proofs/fib.bin"))
+                .unwrap()
+                .with_format(MemoryFormat::Calldata)
+                .proof_memory()
+                .unwrap()
+                .entries(),
+            calldata
+        );
+    }
+
+    // reads the hint memory depth first, following its pointers and dropping the array sizes
+    // the way calldata inlines them
+    fn follow_hint(
+        hint: &[String],
+        memories: &[Memory],
+        offsets: &[usize],
+        segment: usize,
+        decoded: &mut Vec<String>,
+    ) {
+        let start = offsets[segment];
+        for (i, entry) in memories[segment].entries.iter().enumerate() {
+            let cell = &hint[start + i];
+            match entry {
+                MemoryEntry::Value { .. } => decoded.push(cell.clone()),
+                MemoryEntry::Size { .. } => {}
+                MemoryEntry::Pointer { pointer } => {
+                    assert_eq!(*cell, offsets[*pointer].to_string());
+                    if let Some(len) = memories[*pointer].len {
+                        decoded.push(format!("{:#X}", len));
+                    }
+                    follow_hint(hint, memories, offsets, *pointer, decoded);
+                }
+            }
+        }
     }
 }
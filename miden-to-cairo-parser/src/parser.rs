@@ -0,0 +1,356 @@
+use hex::FromHex;
+use winter_crypto::ElementHasher;
+use winter_math::{polynom::interpolate, FieldElement};
+use winterfell::VerifierChannel;
+
+use crate::chunks::{calldata_chunks, ChunkedCalldata};
+use crate::error::ParserError;
+use crate::memory::{
+    build_cairo_memory, CairoMemory, DynamicMemory, MemoryFormat, Writeable, WriteableWith,
+};
+use crate::transcript::{query_positions, transcript, Transcript};
+use crate::{
+    with_field_extension, with_hasher, Air, Felt, FriProofParams, HashFunction, ProcessorAir,
+    ProofData, PublicInputs, StarkProof,
+};
+
+/// A proof loaded from disk together with the AIR it is verified against
+pub struct ParsedProof {
+    pub proof: StarkProof,
+    pub pub_inputs: PublicInputs,
+    pub air: ProcessorAir,
+    /// Layout of the memory the methods below return
+    pub format: MemoryFormat,
+}
+
+impl ParsedProof {
+    pub fn from_data(data: &ProofData) -> Result<ParsedProof, ParserError> {
+        let proof = data.proof()?;
+        let pub_inputs = data.public_inputs()?;
+        let air = ProcessorAir::new(
+            proof.get_trace_info(),
+            pub_inputs.clone(),
+            proof.options().clone(),
+        );
+        Ok(ParsedProof {
+            proof,
+            pub_inputs,
+            air,
+            format: MemoryFormat::Hint,
+        })
+    }
+
+    pub fn from_file(path: &str) -> Result<ParsedProof, ParserError> {
+        Self::from_data(&ProofData::from_file(path)?)
+    }
+
+    /// Writes the memory in `format` instead of the Cairo 0 hint layout
+    pub fn with_format(mut self, format: MemoryFormat) -> ParsedProof {
+        self.format = format;
+        self
+    }
+
+    pub fn proof_memory(&self) -> Result<CairoMemory, ParserError> {
+        self.check_cairo_support()?;
+        self.proof.to_cairo_memory(&self.air, self.format)
+    }
+
+    pub fn public_inputs_memory(&self) -> Result<CairoMemory, ParserError> {
+        self.pub_inputs.to_cairo_memory(self.format)
+    }
+
+    /// Authentication paths of the trace segments at `indexes`
+    pub fn trace_queries_memory(&self, indexes: &[usize]) -> Result<CairoMemory, ParserError> {
+        build_cairo_memory(self.format, |target| {
+            self.write_trace_paths(target, indexes)
+        })
+    }
+
+    /// Authentication paths of the constraint evaluations at `indexes`
+    pub fn constraint_queries_memory(&self, indexes: &[usize]) -> Result<CairoMemory, ParserError> {
+        build_cairo_memory(self.format, |target| {
+            self.write_constraint_paths(target, indexes)
+        })
+    }
+
+    /// Authentication paths and values of every FRI layer at `indexes` folded into the layer
+    pub fn fri_queries_memory(&self, indexes: &[usize]) -> Result<CairoMemory, ParserError> {
+        self.check_cairo_support()?;
+        self.check_indexes(indexes)?;
+        self.proof.fri_proof.to_cairo_memory(
+            FriProofParams {
+                air: &self.air,
+                indexes: &indexes.to_vec(),
+                layer: None,
+            },
+            self.format,
+        )
+    }
+
+    /// Collects everything the Cairo verifier reads from the proof in one pass, the queries are
+    /// decommitted at the positions the verifier draws
+    pub fn verifier_bundle(&self) -> Result<VerifierBundle, ParserError> {
+        self.check_cairo_support()?;
+        let query_positions = query_positions(self)?;
+        Ok(VerifierBundle {
+            proof: self.proof_memory()?,
+            public_inputs: self.public_inputs_memory()?,
+            trace_queries: self.trace_queries_memory(&query_positions)?,
+            constraint_queries: self.constraint_queries_memory(&query_positions)?,
+            fri_queries: self.fri_queries_memory(&query_positions)?,
+            query_positions,
+        })
+    }
+
+    /// Verifier input as Starknet calldata split into chunks that fit in separate transactions,
+    /// no chunk is longer than `max_chunk_len` felts
+    pub fn calldata_chunks(
+        &self,
+        max_chunk_len: Option<usize>,
+    ) -> Result<ChunkedCalldata, ParserError> {
+        calldata_chunks(self, max_chunk_len)
+    }
+
+    /// Intermediate values of the native verifier, to compare against the Cairo verifier
+    pub fn transcript(&self) -> Result<Transcript, ParserError> {
+        transcript(self)
+    }
+
+    pub(crate) fn write_trace_paths(
+        &self,
+        target: &mut DynamicMemory,
+        indexes: &[usize],
+    ) -> Result<(), ParserError> {
+        self.check_cairo_support()?;
+        self.check_indexes(indexes)?;
+        with_hasher!(self.proof.options().hash_fn(), |H| {
+            with_field_extension!(self.proof.options().field_extension(), |E| {
+                self.channel::<E, H>()?
+                    .trace_queries
+                    .ok_or_else(|| ParserError::AirMismatch("missing trace queries".to_string()))?
+                    .write_into(target, indexes)
+            })
+        })
+    }
+
+    pub(crate) fn write_constraint_paths(
+        &self,
+        target: &mut DynamicMemory,
+        indexes: &[usize],
+    ) -> Result<(), ParserError> {
+        self.check_cairo_support()?;
+        self.check_indexes(indexes)?;
+        with_hasher!(self.proof.options().hash_fn(), |H| {
+            with_field_extension!(self.proof.options().field_extension(), |E| {
+                self.channel::<E, H>()?
+                    .constraint_queries
+                    .ok_or_else(|| {
+                        ParserError::AirMismatch("missing constraint queries".to_string())
+                    })?
+                    .write_into(target, indexes)
+            })
+        })
+    }
+
+    fn channel<E, H>(&self) -> Result<VerifierChannel<E, H>, ParserError>
+    where
+        E: FieldElement<BaseField = Felt>,
+        H: ElementHasher<BaseField = Felt>,
+    {
+        VerifierChannel::<E, H>::new(&self.air, self.proof.clone())
+            .map_err(|err| ParserError::AirMismatch(err.to_string()))
+    }
+
+    /// Fails unless the Cairo verifier can check this proof. Its public coin and Merkle paths
+    /// only implement Blake2s, other hash functions would fail opaquely inside Cairo
+    pub fn check_cairo_support(&self) -> Result<(), ParserError> {
+        match self.proof.options().hash_fn() {
+            HashFunction::Blake2s_256 => Ok(()),
+            hash_fn => Err(ParserError::Unsupported(format!(
+                "the Cairo verifier only implements Blake2s, the proof uses {:?}",
+                hash_fn
+            ))),
+        }
+    }
+
+    // out of range positions would otherwise surface as an opaque Merkle path error
+    fn check_indexes(&self, indexes: &[usize]) -> Result<(), ParserError> {
+        let lde_domain_size = self.air.lde_domain_size();
+        match indexes.iter().find(|&&index| index >= lde_domain_size) {
+            Some(index) => Err(ParserError::QueryIndexes(format!(
+                "position {} is outside of the LDE domain of size {}",
+                index, lde_domain_size
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Inputs of the Cairo verifier for a single proof, so that its hints do not call the parser
+/// for every query
+pub struct VerifierBundle {
+    pub proof: CairoMemory,
+    pub public_inputs: CairoMemory,
+    pub query_positions: Vec<usize>,
+    pub trace_queries: CairoMemory,
+    pub constraint_queries: CairoMemory,
+    pub fri_queries: CairoMemory,
+}
+
+impl VerifierBundle {
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "proof": self.proof.entries(),
+            "public_inputs": self.public_inputs.entries(),
+            "query_positions": self.query_positions,
+            "trace_queries": self.trace_queries.entries(),
+            "constraint_queries": self.constraint_queries.entries(),
+            "fri_queries": self.fri_queries.entries(),
+        })
+        .to_string()
+    }
+}
+
+/// Parses query indexes passed as a JSON array, e.g. `[1, 42, 7]`
+pub fn parse_indexes(indexes: &str) -> Result<Vec<usize>, ParserError> {
+    serde_json::from_str(indexes).map_err(|err| ParserError::QueryIndexes(err.to_string()))
+}
+
+/// Parses field elements passed as a JSON array of little endian hex strings
+pub fn parse_felts(values: &str) -> Result<Vec<Felt>, ParserError> {
+    let values: Vec<String> = serde_json::from_str(values)
+        .map_err(|err| ParserError::Deserialization(err.to_string()))?;
+    values
+        .into_iter()
+        .map(|value| {
+            let decoded = <[u8; 8]>::from_hex(&value).map_err(|err| {
+                ParserError::Deserialization(format!("invalid field element {}: {}", value, err))
+            })?;
+            Ok(Felt::new(u64::from_le_bytes(decoded)))
+        })
+        .collect()
+}
+
+/// Coefficients of the polynomial through the points `(x_values[i], y_values[i])`
+pub fn interpolate_poly(x_values: &[Felt], y_values: &[Felt]) -> Result<Vec<Felt>, ParserError> {
+    if x_values.len() != y_values.len() {
+        return Err(ParserError::Deserialization(format!(
+            "got {} x values but {} y values",
+            x_values.len(),
+            y_values.len()
+        )));
+    }
+    Ok(interpolate(x_values, y_values, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_proof_format::sdk;
+
+    use super::*;
+
+    const FIB_PROOF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../proofs/fib.bin");
+
+    #[test]
+    fn test_non_blake2s_proof_is_rejected() {
+        let (mut sdk_proof, sdk_pub_inputs) =
+            ProofData::from_file(FIB_PROOF).unwrap().to_sdk().unwrap();
+        // Blake3 digests have the same size, so the proof still decodes
+        let options = sdk_proof
+            .context
+            .as_mut()
+            .unwrap()
+            .options
+            .as_mut()
+            .unwrap();
+        options.hash_fn = sdk::HashFunction::from(HashFunction::Blake3_256) as i32;
+        let data = ProofData::from_sdk(sdk_proof, sdk_pub_inputs).unwrap();
+        let proof = ParsedProof::from_data(&data).unwrap();
+
+        let err = proof.proof_memory().unwrap_err();
+        assert!(matches!(err, ParserError::Unsupported(_)));
+        assert_eq!(err.exit_code(), 8);
+        assert!(matches!(
+            proof.verifier_bundle(),
+            Err(ParserError::Unsupported(_))
+        ));
+        assert!(matches!(
+            proof.trace_queries_memory(&[0]),
+            Err(ParserError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = ParsedProof::from_file("does/not/exist.bin").err().unwrap();
+        assert!(matches!(err, ParserError::Io(_)));
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_malformed_indexes() {
+        for indexes in ["[1, 2", "[-1]", "{}"] {
+            assert!(matches!(
+                parse_indexes(indexes),
+                Err(ParserError::QueryIndexes(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_out_of_domain_index() {
+        let proof = ParsedProof::from_file(FIB_PROOF).unwrap();
+        let lde_domain_size = proof.air.lde_domain_size();
+        assert_eq!(proof.check_indexes(&[0, lde_domain_size - 1]), Ok(()));
+        assert!(matches!(
+            proof.check_indexes(&[0, lde_domain_size]),
+            Err(ParserError::QueryIndexes(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_felts() {
+        assert_eq!(
+            parse_felts(r#"["0100000000000000"]"#),
+            Ok(vec![Felt::new(1)])
+        );
+        // not hex, and not 8 bytes
+        for values in [r#"["zz00000000000000"]"#, r#"["01"]"#] {
+            assert!(matches!(
+                parse_felts(values),
+                Err(ParserError::Deserialization(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_interpolate_mismatched_lengths() {
+        let x_values = [Felt::new(1), Felt::new(2)];
+        assert!(matches!(
+            interpolate_poly(&x_values, &[Felt::new(3)]),
+            Err(ParserError::Deserialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_bundle_matches_query_memories() {
+        let proof = ParsedProof::from_file(FIB_PROOF).unwrap();
+        let bundle = proof.verifier_bundle().unwrap();
+        let positions = &bundle.query_positions;
+        assert_eq!(
+            bundle.trace_queries.entries(),
+            proof.trace_queries_memory(positions).unwrap().entries()
+        );
+        assert_eq!(
+            bundle.constraint_queries.entries(),
+            proof
+                .constraint_queries_memory(positions)
+                .unwrap()
+                .entries()
+        );
+        assert_eq!(
+            bundle.fri_queries.entries(),
+            proof.fri_queries_memory(positions).unwrap().entries()
+        );
+    }
+}
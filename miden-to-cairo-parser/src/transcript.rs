@@ -0,0 +1,407 @@
+use miden_air::StarkField;
+use serde_json::{json, Value};
+use winter_crypto::{Digest, ElementHasher, RandomCoin};
+use winter_fri::VerifierChannel as FriVerifierChannel;
+use winter_math::{polynom, FieldElement};
+use winter_utils::Serializable;
+use winterfell::{
+    AuxTraceRandElements, ConstraintCompositionCoefficients, EvaluationFrame, VerifierChannel,
+};
+
+use crate::error::ParserError;
+use crate::memory::{build_cairo_memory, CairoMemory, DynamicMemory, MemoryFormat, Writeable};
+use crate::parser::ParsedProof;
+use crate::{base_elements, with_field_extension, with_hasher, Air, Felt};
+
+/// Intermediate values of the native verifier, stage by stage
+pub struct Transcript {
+    pub stages: Vec<TranscriptStage>,
+    pub query_positions: Vec<usize>,
+}
+
+/// Values drawn or read by the verifier in one stage, with the public coin state once the
+/// stage is done
+pub struct TranscriptStage {
+    pub name: &'static str,
+    pub coin_seed: String,
+    pub coin_counter: u64,
+    pub coin_memory: CairoMemory,
+    pub memory: CairoMemory,
+    pub values: Value,
+}
+
+impl Transcript {
+    pub fn to_json(&self) -> String {
+        let stages: Vec<Value> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                json!({
+                    "stage": stage.name,
+                    "coin": {
+                        "seed": stage.coin_seed,
+                        "counter": stage.coin_counter,
+                        "memory": stage.coin_memory.entries(),
+                    },
+                    "values": stage.values,
+                    "memory": stage.memory.entries(),
+                })
+            })
+            .collect();
+        json!({
+            "stages": stages,
+            "query_positions": self.query_positions,
+        })
+        .to_string()
+    }
+}
+
+/// Replays the verifier transcript of `proof` and records every stage
+pub fn transcript(proof: &ParsedProof) -> Result<Transcript, ParserError> {
+    with_hasher!(proof.proof.options().hash_fn(), |H| {
+        with_field_extension!(proof.proof.options().field_extension(), |E| {
+            replay::<E, H>(proof)
+        })
+    })
+}
+
+/// Query positions the verifier draws for `proof`, in the order they are drawn
+pub fn query_positions(proof: &ParsedProof) -> Result<Vec<usize>, ParserError> {
+    Ok(transcript(proof)?.query_positions)
+}
+
+// replays the Fiat-Shamir transcript of `winterfell::verify`, reseeding the public coin with the
+// prover messages read from the channel in the same order
+fn replay<E, H>(parsed: &ParsedProof) -> Result<Transcript, ParserError>
+where
+    E: FieldElement<BaseField = Felt>,
+    H: ElementHasher<BaseField = Felt>,
+{
+    let air = &parsed.air;
+    let mut channel = VerifierChannel::<E, H>::new(air, parsed.proof.clone())
+        .map_err(|err| ParserError::AirMismatch(err.to_string()))?;
+    let mut stages = Vec::new();
+
+    // the public coin is seeded with the public inputs and the proof context
+    let mut public_coin_seed = Vec::new();
+    parsed.pub_inputs.write_into(&mut public_coin_seed);
+    parsed.proof.context.write_into(&mut public_coin_seed);
+    let mut public_coin = RandomCoin::<Felt, H>::new(&public_coin_seed);
+    stages.push(stage(
+        "public_coin",
+        parsed.format,
+        &public_coin,
+        build_cairo_memory(parsed.format, |_| Ok(()))?,
+        json!({}),
+    )?);
+
+    // trace commitments, each auxiliary segment draws its random elements before it is committed
+    let trace_commitments = channel.read_trace_commitments().to_vec();
+    public_coin.reseed(trace_commitments[0]);
+    let mut aux_rand_elements = AuxTraceRandElements::<E>::new();
+    let mut aux_values = Vec::new();
+    for (i, commitment) in trace_commitments.iter().skip(1).enumerate() {
+        let rand_elements = air
+            .get_aux_trace_segment_random_elements::<E, H>(i, &mut public_coin)
+            .map_err(transcript_error)?;
+        aux_values.push(readable(&rand_elements));
+        aux_rand_elements.add_segment_elements(rand_elements);
+        public_coin.reseed(*commitment);
+    }
+    stages.push(stage(
+        "trace_commitments",
+        parsed.format,
+        &public_coin,
+        aux_rand_elements.to_cairo_memory(parsed.format)?,
+        json!({ "aux_rand_elements": aux_values }),
+    )?);
+
+    // constraint composition
+    let constraint_coeffs = air
+        .get_constraint_composition_coefficients::<E, H>(&mut public_coin)
+        .map_err(transcript_error)?;
+    public_coin.reseed(channel.read_constraint_commitment());
+    stages.push(stage(
+        "constraint_composition",
+        parsed.format,
+        &public_coin,
+        constraint_coeffs.to_cairo_memory(parsed.format)?,
+        json!({
+            "transition": readable_pairs(&constraint_coeffs.transition),
+            "boundary": readable_pairs(&constraint_coeffs.boundary),
+        }),
+    )?);
+
+    // out-of-domain point
+    let z = public_coin.draw::<E>().map_err(transcript_error)?;
+    stages.push(stage(
+        "ood_point",
+        parsed.format,
+        &public_coin,
+        build_cairo_memory(parsed.format, |target| write_inline(target, &[z]))?,
+        json!({ "z": readable(&[z])[0] }),
+    )?);
+
+    // out-of-domain evaluations, the auxiliary frame rows follow the main frame rows. The
+    // constraints evaluated over the frame must match the evaluations of the composition columns
+    let (ood_main_trace_frame, ood_aux_trace_frame) = channel.read_ood_trace_frame();
+    let ood_constraint_evaluation = evaluate_constraints(
+        air,
+        &constraint_coeffs,
+        &ood_main_trace_frame,
+        &ood_aux_trace_frame,
+        &aux_rand_elements,
+        z,
+    );
+    let mut ood_current = ood_main_trace_frame.current().to_vec();
+    let mut ood_next = ood_main_trace_frame.next().to_vec();
+    if let Some(aux_trace_frame) = &ood_aux_trace_frame {
+        ood_current.extend_from_slice(aux_trace_frame.current());
+        ood_next.extend_from_slice(aux_trace_frame.next());
+    }
+    public_coin.reseed(H::hash_elements(&ood_current));
+    public_coin.reseed(H::hash_elements(&ood_next));
+    let ood_constraint_evaluations = channel.read_ood_constraint_evaluations();
+    let ood_composition_evaluation = ood_constraint_evaluations
+        .iter()
+        .enumerate()
+        .fold(E::ZERO, |result, (i, &value)| {
+            result + z.exp(((i * air.trace_length()) as u32).into()) * value
+        });
+    public_coin.reseed(H::hash_elements(&ood_constraint_evaluations));
+    if ood_constraint_evaluation != ood_composition_evaluation {
+        return Err(ParserError::Transcript(format!(
+            "out-of-domain constraint evaluation {} does not match the composition columns {}",
+            readable(&[ood_constraint_evaluation])[0],
+            readable(&[ood_composition_evaluation])[0]
+        )));
+    }
+    stages.push(stage(
+        "ood_evaluations",
+        parsed.format,
+        &public_coin,
+        build_cairo_memory(parsed.format, |target| {
+            ood_main_trace_frame.write_into(target)?;
+            if let Some(aux_trace_frame) = &ood_aux_trace_frame {
+                aux_trace_frame.write_into(target)?;
+            }
+            target.write_sized_array(base_elements(&ood_constraint_evaluations))?;
+            write_inline(target, &[ood_constraint_evaluation])
+        })?,
+        json!({
+            "trace_current": readable(&ood_current),
+            "trace_next": readable(&ood_next),
+            "constraint_evaluations": readable(&ood_constraint_evaluations),
+            "constraint_evaluation": readable(&[ood_constraint_evaluation])[0],
+        }),
+    )?);
+
+    // DEEP composition
+    let deep_coeffs = air
+        .get_deep_composition_coefficients::<E, H>(&mut public_coin)
+        .map_err(transcript_error)?;
+    let deep_trace: Vec<Value> = deep_coeffs
+        .trace
+        .iter()
+        .map(|elem| json!(readable(&[elem.0, elem.1, elem.2])))
+        .collect();
+    stages.push(stage(
+        "deep_composition",
+        parsed.format,
+        &public_coin,
+        deep_coeffs.to_cairo_memory(parsed.format)?,
+        json!({
+            "trace": deep_trace,
+            "constraints": readable(&deep_coeffs.constraints),
+            "degree": readable(&[deep_coeffs.degree.0, deep_coeffs.degree.1]),
+        }),
+    )?);
+
+    // FRI layer commitments
+    let mut alphas = Vec::new();
+    for commitment in channel.read_fri_layer_commitments() {
+        public_coin.reseed(commitment);
+        alphas.push(public_coin.draw::<E>().map_err(transcript_error)?);
+    }
+    stages.push(stage(
+        "fri_layers",
+        parsed.format,
+        &public_coin,
+        build_cairo_memory(parsed.format, |target| {
+            target.write_array(base_elements(&alphas))
+        })?,
+        json!({ "alphas": readable(&alphas) }),
+    )?);
+
+    // proof of work and query positions
+    public_coin.reseed_with_int(channel.read_pow_nonce());
+    if public_coin.leading_zeros() < air.options().grinding_factor() {
+        return Err(ParserError::Transcript(
+            "proof of work does not satisfy the grinding factor".to_string(),
+        ));
+    }
+    let query_positions = public_coin
+        .draw_integers(air.options().num_queries(), air.lde_domain_size())
+        .map_err(transcript_error)?;
+    stages.push(stage(
+        "query_positions",
+        parsed.format,
+        &public_coin,
+        build_cairo_memory(parsed.format, |target| {
+            target.write_array(query_positions.clone())
+        })?,
+        json!({ "positions": query_positions }),
+    )?);
+
+    Ok(Transcript {
+        stages,
+        query_positions,
+    })
+}
+
+// the composition polynomial at `x` as the verifier computes it from the OOD frame, i.e. the
+// random linear combination of the transition and boundary constraints divided by their divisors
+fn evaluate_constraints<E: FieldElement<BaseField = Felt>>(
+    air: &impl Air<BaseField = Felt>,
+    coefficients: &ConstraintCompositionCoefficients<E>,
+    main_trace_frame: &EvaluationFrame<E>,
+    aux_trace_frame: &Option<EvaluationFrame<E>>,
+    aux_rand_elements: &AuxTraceRandElements<E>,
+    x: E,
+) -> E {
+    // transition constraints, periodic columns are evaluated at x
+    let t_constraints = air.get_transition_constraints(&coefficients.transition);
+    let periodic_values: Vec<E> = air
+        .get_periodic_column_polys()
+        .iter()
+        .map(|poly| {
+            let num_cycles = air.trace_length() / poly.len();
+            polynom::eval(poly, x.exp((num_cycles as u32).into()))
+        })
+        .collect();
+    let mut main_evaluations = E::zeroed_vector(t_constraints.num_main_constraints());
+    air.evaluate_transition(main_trace_frame, &periodic_values, &mut main_evaluations);
+    let mut aux_evaluations = E::zeroed_vector(t_constraints.num_aux_constraints());
+    if let Some(aux_trace_frame) = aux_trace_frame {
+        air.evaluate_aux_transition(
+            main_trace_frame,
+            aux_trace_frame,
+            &periodic_values,
+            aux_rand_elements,
+            &mut aux_evaluations,
+        );
+    }
+    let mut result = t_constraints.combine_evaluations::<E>(&main_evaluations, &aux_evaluations, x);
+
+    // boundary constraints, grouped by divisor and degree adjustment
+    let b_constraints = air.get_boundary_constraints(aux_rand_elements, &coefficients.boundary);
+    for group in b_constraints.main_constraints() {
+        let xp = x.exp(group.degree_adjustment().into());
+        result += group.evaluate_at(main_trace_frame.current(), x, xp);
+    }
+    if let Some(aux_trace_frame) = aux_trace_frame {
+        for group in b_constraints.aux_constraints() {
+            let xp = x.exp(group.degree_adjustment().into());
+            result += group.evaluate_at(aux_trace_frame.current(), x, xp);
+        }
+    }
+    result
+}
+
+fn stage<H: ElementHasher<BaseField = Felt>>(
+    name: &'static str,
+    format: MemoryFormat,
+    public_coin: &RandomCoin<Felt, H>,
+    memory: CairoMemory,
+    values: Value,
+) -> Result<TranscriptStage, ParserError> {
+    Ok(TranscriptStage {
+        name,
+        coin_seed: hex::encode(public_coin.seed.as_bytes()),
+        coin_counter: public_coin.counter,
+        coin_memory: public_coin.to_cairo_memory(format)?,
+        memory,
+        values,
+    })
+}
+
+// writes the base elements in place, as the Cairo verifier reads a single extension element
+fn write_inline<E: FieldElement<BaseField = Felt>>(
+    target: &mut DynamicMemory,
+    elements: &[E],
+) -> Result<(), ParserError> {
+    for elem in base_elements(elements) {
+        elem.write_into(target)?;
+    }
+    Ok(())
+}
+
+// base field elements are printed as integers, extension elements as their base coefficients
+fn readable<E: FieldElement<BaseField = Felt>>(elements: &[E]) -> Vec<Value> {
+    E::as_base_elements(elements)
+        .chunks(E::EXTENSION_DEGREE)
+        .map(|coeffs| match coeffs {
+            [coeff] => json!(coeff.as_int()),
+            coeffs => json!(coeffs.iter().map(|c| c.as_int()).collect::<Vec<_>>()),
+        })
+        .collect()
+}
+
+fn readable_pairs<E: FieldElement<BaseField = Felt>>(pairs: &[(E, E)]) -> Vec<Value> {
+    pairs
+        .iter()
+        .map(|pair| json!(readable(&[pair.0, pair.1])))
+        .collect()
+}
+
+fn transcript_error(err: impl ToString) -> ParserError {
+    ParserError::Transcript(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use winter_crypto::hashers::Blake2s_256;
+
+    use super::*;
+    use crate::HashFunction;
+
+    #[test]
+    fn test_fib_transcript() {
+        let proof =
+            ParsedProof::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../proofs/fib.bin"))
+                .unwrap();
+        assert_eq!(proof.proof.options().hash_fn(), HashFunction::Blake2s_256);
+        let transcript = proof.transcript().unwrap();
+        let names: Vec<_> = transcript.stages.iter().map(|stage| stage.name).collect();
+        assert_eq!(
+            names,
+            [
+                "public_coin",
+                "trace_commitments",
+                "constraint_composition",
+                "ood_point",
+                "ood_evaluations",
+                "deep_composition",
+                "fri_layers",
+                "query_positions",
+            ]
+        );
+
+        // the native verifier accepts the proof, and the queries the prover opened are the ones
+        // at the replayed positions
+        winterfell::verify::<crate::ProcessorAir, Blake2s_256<Felt>>(
+            proof.proof.clone(),
+            proof.pub_inputs.clone(),
+        )
+        .unwrap();
+        let mut channel =
+            VerifierChannel::<Felt, Blake2s_256<Felt>>::new(&proof.air, proof.proof.clone())
+                .unwrap();
+        channel
+            .read_queried_trace_states(&transcript.query_positions)
+            .unwrap();
+        channel
+            .read_constraint_evaluations(&transcript.query_positions)
+            .unwrap();
+    }
+}
@@ -320,26 +320,14 @@ func read_queried_trace_states{
     alloc_locals;
     let (local trace_queries_proof_ptr: QueriesProofs*) = alloc();
     %{
-        import json
-        import subprocess
-        from src.stark_verifier.utils import write_into_memory
+        from src.stark_verifier.utils import read_queries_proofs
 
         positions = []
         for i in range(ids.num_queries): 
             positions.append( memory[ids.positions + i] )
 
-        positions = json.dumps( positions )
-
-        completed_process = subprocess.run([
-            'bin/stark_parser',
-            'proofs/fib.bin', # TODO: this path shouldn't be hardcoded!
-            'trace-queries',
-            positions
-            ],
-            capture_output=True)
-
-        json_data = completed_process.stdout
-        write_into_memory(ids.trace_queries_proof_ptr, json_data, segments)
+        # TODO: the proof path shouldn't be hardcoded!
+        read_queries_proofs(ids.trace_queries_proof_ptr, 'trace-queries', positions, segments)
     %}
 
     let num_queries = 4;  // TODO: this should be num_queries, but it takes forever...
@@ -386,26 +374,14 @@ func read_constraint_evaluations{
     alloc_locals;
     let (local constraint_queries_proof_ptr: QueriesProofs*) = alloc();
     %{
-        import json
-        import subprocess
-        from src.stark_verifier.utils import write_into_memory
+        from src.stark_verifier.utils import read_queries_proofs
 
         positions = []
         for i in range(ids.num_queries):
             positions.append( memory[ids.positions + i] )
 
-        positions = json.dumps( positions )
-
-        completed_process = subprocess.run([
-            'bin/stark_parser',
-            'proofs/fib.bin', # TODO: this path shouldn't be hardcoded!
-            'constraint-queries',
-            positions
-            ],
-            capture_output=True)
-
-        json_data = completed_process.stdout
-        write_into_memory(ids.constraint_queries_proof_ptr, json_data, segments)
+        # TODO: the proof path shouldn't be hardcoded!
+        read_queries_proofs(ids.constraint_queries_proof_ptr, 'constraint-queries', positions, segments)
     %}
     let num_queries = 4;  // TODO: this should be 54, but it takes forever...
 
@@ -1,4 +1,6 @@
+import functools
 import json
+import logging
 import subprocess
 
 PWD = subprocess.run(['pwd'], capture_output=True).stdout[:-1].decode("utf-8")
@@ -6,13 +8,35 @@ PROOF_PATH = f'{PWD}/proofs/fib.bin'
 PARSER_PATH = f'{PWD}/bin/stark_parser'
 CAIRO_PRIME = 2**251 + 17 * 2**192 + 1
 
+logger = logging.getLogger(__name__)
+
+
+def run_parser(*args):
+    """Runs the proof parser and returns its stdout, raising with its error message on failure"""
+    completed_process = subprocess.run(
+        [PARSER_PATH, *args], capture_output=True)
+    if completed_process.returncode != 0:
+        raise RuntimeError(
+            f'stark_parser {" ".join(args)} exited with code {completed_process.returncode}: '
+            f'{completed_process.stderr.decode("utf-8").strip()}')
+    return completed_process.stdout
+
+
+@functools.lru_cache(maxsize=None)
+def load_bundle(proof_path=PROOF_PATH):
+    """Parses the proof once into the proof, public inputs and query decommitments the hints read"""
+    return json.loads(run_parser(proof_path, 'bundle'))
+
 
 def write_into_memory(ptr, json_data, segments):
+    write_array_into_memory(ptr, json.loads(json_data), segments)
+
+
+def write_array_into_memory(ptr, my_array, segments):
     addr = ptr
     if hasattr(ptr, 'address_'):
         addr = ptr.address_
 
-    my_array = json.loads(json_data)
     # Note the following:
     # - Addresses are stored as `Relocatable` values in the Cairo VM.
     # - The "+" operator is overloaded to perform pointer arithmetics.
@@ -30,15 +54,23 @@ def index_of(elements_ptr, n_elements, element, memory):
     return CAIRO_PRIME-1
 
 
-def read_fri_queries_proofs(positions_ptr, fri_queries_proof_ptr, num_queries, memory, segments):
-    positions = to_json_array(positions_ptr, num_queries, memory)
+def read_queries_proofs(ptr, command, positions, segments, proof_path=PROOF_PATH):
+    """Writes the decommitments of `command` at `positions`. They come from the bundle when the
+    verifier queries the positions it drew, the parser is only run for other positions."""
+    bundle = load_bundle(proof_path)
+    if positions == bundle['query_positions']:
+        write_array_into_memory(ptr, bundle[command.replace('-', '_')], segments)
+    else:
+        logger.warning(
+            f'{command} positions {positions} differ from the bundle positions '
+            f'{bundle["query_positions"]}, running stark_parser for them')
+        json_data = run_parser(proof_path, command, json.dumps(positions))
+        write_into_memory(ptr, json_data, segments)
 
-    completed_process = subprocess.run(
-        [PARSER_PATH, PROOF_PATH, 'fri-queries', positions],
-        capture_output=True)
 
-    json_data = completed_process.stdout
-    write_into_memory(fri_queries_proof_ptr, json_data, segments)
+def read_fri_queries_proofs(positions_ptr, fri_queries_proof_ptr, num_queries, memory, segments):
+    positions = json.loads(to_json_array(positions_ptr, num_queries, memory))
+    read_queries_proofs(fri_queries_proof_ptr, 'fri-queries', positions, segments)
 
 
 def to_json_array(arr_ptr, arr_length, memory):
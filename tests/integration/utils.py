@@ -1,21 +1,11 @@
 import json
-import subprocess
+
+from src.stark_verifier.utils import load_bundle
 
 
 def parse_proof(program_name):
-    completed_process = subprocess.run([
-        'bin/stark_parser',
-        f'proofs/{program_name}.bin',
-        'proof'],
-        capture_output=True)
-    return completed_process.stdout
+    return json.dumps(load_bundle(f'proofs/{program_name}.bin')['proof'])
 
 
 def parse_public_inputs(program_name):
-    pwd = subprocess.run(['pwd'], capture_output=True).stdout[:-1]
-    completed_process = subprocess.run([
-        'bin/stark_parser',
-        f'proofs/{program_name}.bin',
-        'public-inputs'],
-        capture_output=True)
-    return completed_process.stdout
+    return json.dumps(load_bundle(f'proofs/{program_name}.bin')['public_inputs'])